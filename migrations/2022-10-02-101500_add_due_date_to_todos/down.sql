@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+
+DROP INDEX todos_user_id_due_at_idx;
+
+ALTER TABLE todos DROP COLUMN due_at;
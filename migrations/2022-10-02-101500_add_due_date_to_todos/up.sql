@@ -0,0 +1,5 @@
+-- Your SQL goes here
+
+ALTER TABLE todos ADD COLUMN due_at TIMESTAMP;
+
+CREATE INDEX todos_user_id_due_at_idx ON todos (user_id, due_at);
@@ -0,0 +1,9 @@
+-- This file should undo anything in `up.sql`
+
+DROP INDEX todos_project_id_idx;
+
+ALTER TABLE todos DROP CONSTRAINT todo_project_foreign_key;
+
+ALTER TABLE todos DROP COLUMN project_id;
+
+DROP TABLE projects;
@@ -0,0 +1,26 @@
+-- Your SQL goes here
+
+CREATE TABLE projects (
+    id UUID NOT NULL PRIMARY KEY,
+
+    name VARCHAR(100) NOT NULL,
+
+    user_id UUID NOT NULL,
+
+    created_at TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP NOT NULL,
+
+    CONSTRAINT project_user_foreign_key
+        FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE CASCADE,
+
+    CONSTRAINT project_user_name_unique UNIQUE (user_id, name)
+);
+
+ALTER TABLE todos ADD COLUMN project_id UUID;
+
+-- Todos of a deleted project are kept, they just no longer belong to a project
+ALTER TABLE todos ADD CONSTRAINT
+    todo_project_foreign_key
+    FOREIGN KEY(project_id) REFERENCES projects(id) ON DELETE SET NULL;
+
+CREATE INDEX todos_project_id_idx ON todos (project_id);
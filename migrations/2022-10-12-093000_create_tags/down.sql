@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+
+DROP TABLE todo_tags;
+
+DROP TABLE tags;
@@ -0,0 +1,31 @@
+-- Your SQL goes here
+
+CREATE TABLE tags (
+    id UUID NOT NULL PRIMARY KEY,
+
+    name VARCHAR(50) NOT NULL,
+
+    user_id UUID NOT NULL,
+
+    created_at TIMESTAMP NOT NULL,
+
+    CONSTRAINT tag_user_foreign_key
+        FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE CASCADE,
+
+    CONSTRAINT tag_user_name_unique UNIQUE (user_id, name)
+);
+
+CREATE TABLE todo_tags (
+    todo_id UUID NOT NULL,
+    tag_id UUID NOT NULL,
+
+    PRIMARY KEY (todo_id, tag_id),
+
+    CONSTRAINT todo_tag_todo_foreign_key
+        FOREIGN KEY(todo_id) REFERENCES todos(id) ON DELETE CASCADE,
+
+    CONSTRAINT todo_tag_tag_foreign_key
+        FOREIGN KEY(tag_id) REFERENCES tags(id) ON DELETE CASCADE
+);
+
+CREATE INDEX todo_tags_tag_id_idx ON todo_tags (tag_id);
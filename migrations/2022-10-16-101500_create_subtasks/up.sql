@@ -0,0 +1,22 @@
+-- Your SQL goes here
+
+CREATE TABLE subtasks (
+    id UUID NOT NULL PRIMARY KEY,
+
+    todo_id UUID NOT NULL,
+
+    title VARCHAR(200) NOT NULL,
+
+    completed BOOLEAN NOT NULL DEFAULT FALSE,
+
+    position INTEGER NOT NULL,
+
+    created_at TIMESTAMP NOT NULL,
+
+    updated_at TIMESTAMP NOT NULL,
+
+    CONSTRAINT subtask_todo_foreign_key
+        FOREIGN KEY(todo_id) REFERENCES todos(id) ON DELETE CASCADE
+);
+
+CREATE INDEX subtasks_todo_id_position_idx ON subtasks (todo_id, position);
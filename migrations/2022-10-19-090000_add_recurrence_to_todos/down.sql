@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+
+DROP INDEX todos_series_id_idx;
+
+ALTER TABLE todos DROP COLUMN series_id;
+
+ALTER TABLE todos DROP COLUMN recurrence;
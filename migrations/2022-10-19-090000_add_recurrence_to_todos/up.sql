@@ -0,0 +1,9 @@
+-- Your SQL goes here
+
+-- daily, weekly:mon,fri, monthly:15 or every:3
+ALTER TABLE todos ADD COLUMN recurrence VARCHAR(50);
+
+-- Occurrences of a recurring todo share the id of the first one
+ALTER TABLE todos ADD COLUMN series_id UUID;
+
+CREATE INDEX todos_series_id_idx ON todos (series_id);
@@ -0,0 +1,4 @@
+-- Your SQL goes here
+
+-- Markdown, shown in the detail pane of the todo list
+ALTER TABLE todos ADD COLUMN notes TEXT;
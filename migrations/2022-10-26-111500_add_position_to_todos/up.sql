@@ -0,0 +1,17 @@
+-- Your SQL goes here
+
+-- Positions are spaced out so a todo can be moved
+-- between two others without touching any other row
+ALTER TABLE todos ADD COLUMN position BIGINT NOT NULL DEFAULT 0;
+
+UPDATE todos SET position = ordered.row_number * 1024
+FROM (
+    SELECT id, ROW_NUMBER() OVER (
+        PARTITION BY user_id
+        ORDER BY completed, priority DESC, due_at, created_at DESC
+    ) AS row_number
+    FROM todos
+) AS ordered
+WHERE todos.id = ordered.id;
+
+CREATE INDEX todos_user_id_position_idx ON todos (user_id, position);
@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+
+DROP INDEX todos_deleted_at_idx;
+
+ALTER TABLE todos DROP COLUMN deleted_at;
@@ -0,0 +1,6 @@
+-- Your SQL goes here
+
+-- Todos in the trash, they are purged once the retention period is over
+ALTER TABLE todos ADD COLUMN deleted_at TIMESTAMP;
+
+CREATE INDEX todos_deleted_at_idx ON todos (deleted_at) WHERE deleted_at IS NOT NULL;
@@ -0,0 +1,11 @@
+-- Your SQL goes here
+
+-- Archived todos are left out of todo lists unless asked for
+ALTER TABLE todos ADD COLUMN archived_at TIMESTAMP;
+
+-- When the todo was completed, archiving goes by it so that
+-- editing a completed todo doesn't make it recent again
+ALTER TABLE todos ADD COLUMN completed_at TIMESTAMP;
+
+-- The last update is the best guess for todos completed before
+UPDATE todos SET completed_at = updated_at WHERE completed;
@@ -0,0 +1,10 @@
+-- Your SQL goes here
+
+-- Words of the title and the notes for full-text search, title words rank higher.
+-- Only read through raw SQL by the search handler, so it is left out of `schema.rs`
+ALTER TABLE todos ADD COLUMN search_vector TSVECTOR GENERATED ALWAYS AS (
+    setweight(to_tsvector('english', title), 'A') ||
+    setweight(to_tsvector('english', coalesce(notes, '')), 'B')
+) STORED;
+
+CREATE INDEX todos_search_vector_idx ON todos USING GIN (search_vector);
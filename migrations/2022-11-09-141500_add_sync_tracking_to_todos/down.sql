@@ -0,0 +1,21 @@
+-- This file should undo anything in `up.sql`
+
+DROP TRIGGER todos_add_tombstone ON todos;
+
+DROP FUNCTION add_todo_tombstone();
+
+DROP TABLE todo_tombstones;
+
+DROP TRIGGER subtasks_touch_todo ON subtasks;
+
+DROP TRIGGER todo_tags_touch_todo ON todo_tags;
+
+DROP FUNCTION touch_todo_sync_txid();
+
+DROP TRIGGER todos_set_sync_txid ON todos;
+
+DROP FUNCTION set_todo_sync_txid();
+
+DROP INDEX todos_user_id_sync_txid_idx;
+
+ALTER TABLE todos DROP COLUMN sync_txid;
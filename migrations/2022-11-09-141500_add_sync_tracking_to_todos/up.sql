@@ -0,0 +1,66 @@
+-- Your SQL goes here
+
+-- Id of the transaction that last wrote the todo, the change feed
+-- hands out transaction ids as sync tokens. Only maintained by the
+-- triggers below and read through raw SQL, so it is left out of `schema.rs`
+ALTER TABLE todos ADD COLUMN sync_txid BIGINT NOT NULL DEFAULT txid_current();
+
+CREATE INDEX todos_user_id_sync_txid_idx ON todos (user_id, sync_txid);
+
+CREATE FUNCTION set_todo_sync_txid() RETURNS TRIGGER AS $$
+BEGIN
+    NEW.sync_txid := txid_current();
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER todos_set_sync_txid
+    BEFORE INSERT OR UPDATE ON todos
+    FOR EACH ROW EXECUTE FUNCTION set_todo_sync_txid();
+
+-- Tags and subtasks are part of a todo for clients, changing them touches the todo
+CREATE FUNCTION touch_todo_sync_txid() RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        UPDATE todos SET sync_txid = txid_current() WHERE id = OLD.todo_id;
+    ELSE
+        UPDATE todos SET sync_txid = txid_current() WHERE id = NEW.todo_id;
+    END IF;
+
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER todo_tags_touch_todo
+    AFTER INSERT OR DELETE ON todo_tags
+    FOR EACH ROW EXECUTE FUNCTION touch_todo_sync_txid();
+
+CREATE TRIGGER subtasks_touch_todo
+    AFTER INSERT OR UPDATE OR DELETE ON subtasks
+    FOR EACH ROW EXECUTE FUNCTION touch_todo_sync_txid();
+
+-- Todos deleted for good, so that clients can drop them too
+CREATE TABLE todo_tombstones (
+    todo_id UUID PRIMARY KEY,
+    user_id UUID NOT NULL,
+    deleted_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    sync_txid BIGINT NOT NULL DEFAULT txid_current()
+);
+
+CREATE INDEX todo_tombstones_user_id_sync_txid_idx ON todo_tombstones (user_id, sync_txid);
+
+CREATE FUNCTION add_todo_tombstone() RETURNS TRIGGER AS $$
+BEGIN
+    INSERT INTO todo_tombstones (todo_id, user_id)
+        VALUES (OLD.id, OLD.user_id)
+        ON CONFLICT (todo_id) DO UPDATE SET
+            deleted_at = NOW(),
+            sync_txid = txid_current();
+
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER todos_add_tombstone
+    AFTER DELETE ON todos
+    FOR EACH ROW EXECUTE FUNCTION add_todo_tombstone();
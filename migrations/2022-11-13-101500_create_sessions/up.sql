@@ -0,0 +1,23 @@
+-- Your SQL goes here
+
+-- A login of a user, access tokens carry its id as `jti`. Only a hash
+-- of the refresh token is kept, the token itself is given to the client
+CREATE TABLE sessions (
+    id UUID NOT NULL PRIMARY KEY,
+
+    user_id UUID NOT NULL,
+
+    refresh_token_hash VARCHAR(64) NOT NULL UNIQUE,
+
+    created_at TIMESTAMP NOT NULL,
+
+    -- When the refresh token stops working, pushed back on every refresh
+    expires_at TIMESTAMP NOT NULL,
+
+    revoked_at TIMESTAMP,
+
+    CONSTRAINT session_user_foreign_key
+        FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE INDEX sessions_user_id_idx ON sessions (user_id);
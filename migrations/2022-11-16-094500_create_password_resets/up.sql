@@ -0,0 +1,22 @@
+-- Your SQL goes here
+
+-- A password reset requested by email, only a hash of the token sent
+-- in the mail is kept. A token works once and only until it expires
+CREATE TABLE password_resets (
+    id UUID NOT NULL PRIMARY KEY,
+
+    user_id UUID NOT NULL,
+
+    token_hash VARCHAR(64) NOT NULL UNIQUE,
+
+    created_at TIMESTAMP NOT NULL,
+
+    expires_at TIMESTAMP NOT NULL,
+
+    used_at TIMESTAMP,
+
+    CONSTRAINT password_reset_user_foreign_key
+        FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE INDEX password_resets_user_id_idx ON password_resets (user_id);
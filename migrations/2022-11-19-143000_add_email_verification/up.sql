@@ -0,0 +1,24 @@
+-- Your SQL goes here
+
+ALTER TABLE users ADD COLUMN email_verified_at TIMESTAMP;
+
+-- Accounts made before emails were verified keep working
+UPDATE users SET email_verified_at = created_at;
+
+-- A verification mail sent to a user, only a hash of its token is kept
+CREATE TABLE email_verifications (
+    id UUID NOT NULL PRIMARY KEY,
+
+    user_id UUID NOT NULL,
+
+    token_hash VARCHAR(64) NOT NULL UNIQUE,
+
+    created_at TIMESTAMP NOT NULL,
+
+    expires_at TIMESTAMP NOT NULL,
+
+    CONSTRAINT email_verification_user_foreign_key
+        FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE INDEX email_verifications_user_id_idx ON email_verifications (user_id);
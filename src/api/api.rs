@@ -1,10 +1,9 @@
 use actix_web::{self, web, App, HttpServer};
-use diesel::r2d2::ConnectionManager;
-use r2d2::Pool;
 
-use crate::models;
-
-use super::{auth_handler, middlewares::auth::BasicAuth, todos_handler};
+use super::{
+    auth_handler, jwt_keys, mailer, middlewares::auth::BasicAuth, projects_handler, repository,
+    subtasks_handler, sync_handler, todos_handler, trash,
+};
 
 #[actix_web::main]
 pub async fn start_server() -> std::io::Result<()> {
@@ -21,38 +20,108 @@ pub async fn start_server() -> std::io::Result<()> {
 
     let api_url = std::env::var("API_URL").unwrap_or(String::from("localhost:9000"));
 
-    let manager = ConnectionManager::<diesel::PgConnection>::new(database_url);
+    // `sqlite://` urls keep everything in a single file, anything else is Postgres
+    let repository: web::Data<dyn repository::Repository> = web::Data::from(
+        repository::connect(database_url.as_str()).expect("Failed to connect to the database"),
+    );
+
+    // SparkPost when `SPARKPOST_API_KEY` is set, otherwise mails are only logged
+    let mailer: web::Data<dyn mailer::Mailer> = web::Data::from(mailer::from_env());
 
-    let pool: models::Pool = Pool::builder()
-        .build(manager)
-        .expect("Failed to connection to PG database");
+    // Fails on start instead of on the first login when the keys are misconfigured
+    lazy_static::initialize(&jwt_keys::JWT_KEYS);
 
     let _: String = std::env::var("DOMAIN").unwrap_or_else(|_| "localhost".to_string());
 
+    trash::spawn_trash_purge(repository.clone());
+
     HttpServer::new(move || {
-        App::new().app_data(web::Data::new(pool.clone())).service(
-            web::scope("/api")
-                .service(auth_handler::signup)
-                .service(auth_handler::login)
-                .service(
-                    web::scope("/todo")
-                        .wrap(BasicAuth)
-                        .route("", web::get().to(todos_handler::get_todos))
-                        .route("", web::post().to(todos_handler::create_todo))
-                        .route("/{id}", web::delete().to(todos_handler::delete_todo))
-                        .route(
-                            "{id}/complete",
-                            web::put().to(todos_handler::mark_todo_as_complete),
-                        )
-                        .route(
-                            "{id}/incomplete",
-                            web::put().to(todos_handler::mark_todo_as_incomplete),
-                        ),
-                ),
-        )
+        App::new()
+            .app_data(repository.clone())
+            .app_data(mailer.clone())
+            .configure(configure)
     })
     .workers(1) // Num of threads
     .bind(api_url.as_str())?
     .run()
     .await
 }
+
+/// Registers all the api routes, shared by the server and the tests
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/api")
+            .service(auth_handler::signup)
+            .service(auth_handler::login)
+            .service(auth_handler::refresh)
+            .service(auth_handler::forgot_password)
+            .service(auth_handler::reset_password)
+            .service(auth_handler::verify_email)
+            .service(
+                web::resource("/auth/logout")
+                    .wrap(BasicAuth)
+                    .route(web::post().to(auth_handler::logout)),
+            )
+            .service(
+                web::scope("/todo")
+                    .wrap(BasicAuth)
+                    .route("", web::get().to(todos_handler::get_todos))
+                    .route("", web::post().to(todos_handler::create_todo))
+                    .route("/trash", web::get().to(todos_handler::get_trash))
+                    .route("/search", web::get().to(todos_handler::search_todos))
+                    .route("/bulk", web::post().to(todos_handler::bulk_update_todos))
+                    .route("/changes", web::get().to(sync_handler::get_changes))
+                    .route("/archive", web::post().to(todos_handler::archive_todos))
+                    .route("/{id}", web::get().to(todos_handler::get_todo))
+                    .route("/{id}", web::patch().to(todos_handler::update_todo))
+                    .route("/{id}", web::delete().to(todos_handler::delete_todo))
+                    .route(
+                        "{id}/complete",
+                        web::put().to(todos_handler::mark_todo_as_complete),
+                    )
+                    .route(
+                        "{id}/incomplete",
+                        web::put().to(todos_handler::mark_todo_as_incomplete),
+                    )
+                    .route(
+                        "{id}/priority",
+                        web::put().to(todos_handler::set_todo_priority),
+                    )
+                    .route("{id}/move", web::post().to(todos_handler::move_todo))
+                    .route("{id}/restore", web::post().to(todos_handler::restore_todo))
+                    .route(
+                        "{id}/subtasks",
+                        web::get().to(subtasks_handler::get_subtasks),
+                    )
+                    .route(
+                        "{id}/subtasks",
+                        web::post().to(subtasks_handler::create_subtask),
+                    )
+                    .route(
+                        "{id}/subtasks/order",
+                        web::put().to(subtasks_handler::reorder_subtasks),
+                    )
+                    .route(
+                        "{id}/subtasks/{subtask_id}",
+                        web::delete().to(subtasks_handler::delete_subtask),
+                    )
+                    .route(
+                        "{id}/subtasks/{subtask_id}/complete",
+                        web::put().to(subtasks_handler::mark_subtask_as_complete),
+                    )
+                    .route(
+                        "{id}/subtasks/{subtask_id}/incomplete",
+                        web::put().to(subtasks_handler::mark_subtask_as_incomplete),
+                    ),
+            )
+            .service(
+                web::scope("/projects")
+                    .wrap(BasicAuth)
+                    .route("", web::get().to(projects_handler::get_projects))
+                    .route("", web::post().to(projects_handler::create_project))
+                    .route("/{id}", web::get().to(projects_handler::get_project))
+                    .route("/{id}", web::patch().to(projects_handler::rename_project))
+                    .route("/{id}", web::delete().to(projects_handler::delete_project)),
+            ),
+    );
+}
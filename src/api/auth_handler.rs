@@ -1,30 +1,42 @@
 use actix_web::{route, web, HttpResponse};
-use diesel::prelude::*;
 
 use crate::{
     api::{
-        auth_utils::{encode_token, hash_password, verify_hash},
+        auth_utils::{
+            encode_token, hash_password, hash_secret_token, needs_rehash, new_secret_token,
+            verify_hash, MAIL_INTERVAL_MINUTES, REFRESH_TOKEN_DAYS, REQUIRE_VERIFIED_EMAIL,
+            RESET_TOKEN_MINUTES, VERIFY_TOKEN_HOURS,
+        },
         errors::AuthError,
-        middlewares::auth::Claims,
+        middlewares::auth::{Authenticated, Claims},
     },
     models::{
+        email_verification_model::EmailVerification,
+        password_reset_model::PasswordReset,
+        session_model::Session,
         user_model::{SlimUser, User},
-        Pool,
     },
 };
 
 use super::{
-    dtos::auth::{LoginDTO, LoginResponseDTO, SignupRequestDTO, SignupResponseDTO},
+    dtos::auth::{
+        ForgotPasswordDTO, LoginDTO, LoginResponseDTO, RefreshResponseDTO, RefreshTokenDTO,
+        ResetPasswordDTO, SignupRequestDTO, SignupResponseDTO, VerifyEmailQuery,
+    },
     errors::TodoApiError,
+    mailer::{Mail, Mailer, PUBLIC_URL},
+    repository::Repository,
 };
 
 #[route("/auth/login", method = "POST")]
 /// Login a user
 pub async fn login(
     request_data: web::Json<LoginDTO>,
-    pool: web::Data<Pool>,
+    repository: web::Data<dyn Repository>,
+    mailer: web::Data<dyn Mailer>,
 ) -> Result<HttpResponse, actix_web::Error> {
-    let user = web::block(move || get_user(pool, request_data.into_inner())).await??;
+    let user =
+        web::block(move || get_user(repository, mailer, request_data.into_inner())).await??;
 
     Ok(HttpResponse::Ok().json(&user))
 }
@@ -32,29 +44,298 @@ pub async fn login(
 #[route("/auth/signup", method = "POST")]
 pub async fn signup(
     request_data: web::Json<SignupRequestDTO>,
-    pool: web::Data<Pool>,
+    repository: web::Data<dyn Repository>,
+    mailer: web::Data<dyn Mailer>,
 ) -> Result<HttpResponse, actix_web::Error> {
-    let user = web::block(move || insert_new_user(pool, request_data.into_inner())).await??;
+    request_data.validate()?;
+
+    let user = web::block(move || insert_new_user(repository, mailer, request_data.into_inner()))
+        .await??;
 
     Ok(HttpResponse::Ok().json(&user))
 }
 
-/// Get User by email
-fn get_user(pool: web::Data<Pool>, user_data: LoginDTO) -> Result<LoginResponseDTO, TodoApiError> {
-    use crate::schema::users::dsl::*;
+#[route("/auth/verify", method = "GET")]
+/// Verify the email of a user, the link of the verification mail leads here
+pub async fn verify_email(
+    query: web::Query<VerifyEmailQuery>,
+    repository: web::Data<dyn Repository>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let verified = web::block(move || {
+        repository.verify_email(
+            &hash_secret_token(&query.token),
+            chrono::Local::now().naive_local(),
+        )
+    })
+    .await??;
+
+    if !verified {
+        return Err(
+            TodoApiError::BadRequest("Invalid or expired verification token".into()).into(),
+        );
+    }
+
+    Ok(HttpResponse::Ok().body("Your email is verified, you can login to Todo now"))
+}
+
+#[route("/auth/refresh", method = "POST")]
+/// Exchange a refresh token for a new access token and refresh token
+pub async fn refresh(
+    request_data: web::Json<RefreshTokenDTO>,
+    repository: web::Data<dyn Repository>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let tokens =
+        web::block(move || refresh_session(repository, request_data.into_inner())).await??;
+
+    Ok(HttpResponse::Ok().json(&tokens))
+}
+
+/// Logout, the tokens of the session stop working right away
+pub async fn logout(
+    auth: Authenticated,
+    repository: web::Data<dyn Repository>,
+) -> Result<HttpResponse, actix_web::Error> {
+    web::block(move || repository.revoke_session(auth.session_id)).await??;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[route("/auth/forgot-password", method = "POST")]
+/// Mail a password reset token to the user. The response is the same
+/// whether the email has an account or not, so accounts can't be found with it
+pub async fn forgot_password(
+    request_data: web::Json<ForgotPasswordDTO>,
+    repository: web::Data<dyn Repository>,
+    mailer: web::Data<dyn Mailer>,
+) -> Result<HttpResponse, actix_web::Error> {
+    // The mail is sent after responding, so that neither how long
+    // sending takes nor whether it fails tells that the account exists
+    actix_web::rt::spawn(async move {
+        let sent =
+            web::block(move || send_password_reset(repository, mailer, request_data.into_inner()))
+                .await;
+
+        match sent {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => eprintln!("Failed to send the password reset mail: {}", e),
+            Err(e) => eprintln!("Failed to send the password reset mail: {}", e),
+        }
+    });
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[route("/auth/reset-password", method = "POST")]
+/// Set a new password with the token of a password reset mail
+pub async fn reset_password(
+    request_data: web::Json<ResetPasswordDTO>,
+    repository: web::Data<dyn Repository>,
+) -> Result<HttpResponse, actix_web::Error> {
+    request_data.validate()?;
+
+    web::block(move || {
+        let request_data = request_data.into_inner();
+
+        let hashed = hash_password(&request_data.password)?;
+
+        let reset = repository.reset_password(
+            &hash_secret_token(&request_data.token),
+            &hashed,
+            chrono::Local::now().naive_local(),
+        )?;
+
+        if !reset {
+            return Err(TodoApiError::BadRequest(
+                "Invalid or expired reset token".into(),
+            ));
+        }
+
+        Ok(())
+    })
+    .await??;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Create a password reset for the user with the email and mail them its token,
+/// unless they got one less than `MAIL_INTERVAL_MINUTES` ago
+fn send_password_reset(
+    repository: web::Data<dyn Repository>,
+    mailer: web::Data<dyn Mailer>,
+    request_data: ForgotPasswordDTO,
+) -> Result<(), TodoApiError> {
+    let user = match repository.find_user_by_email(&request_data.email)? {
+        Some(user) => user,
+        None => return Ok(()),
+    };
+
+    let token = new_secret_token();
+    let now = chrono::Local::now().naive_local();
+
+    let reset = PasswordReset::from(
+        user.id,
+        hash_secret_token(&token),
+        now + chrono::Duration::minutes(*RESET_TOKEN_MINUTES),
+    );
+
+    if !repository.insert_password_reset(
+        &reset,
+        now - chrono::Duration::minutes(*MAIL_INTERVAL_MINUTES),
+    )? {
+        return Ok(());
+    }
+
+    mailer.send(&Mail {
+        to: user.email,
+        subject: "Reset your Todo password".to_string(),
+        text: format!(
+            "Hi {},\n\nRun `todo-cli reset-password` and enter this reset code to choose \
+             a new password:\n\n{}\n\nThe code works once and expires in {} minutes. \
+             If you didn't ask to reset your password, ignore this mail.",
+            user.name, token, *RESET_TOKEN_MINUTES
+        ),
+    })
+}
+
+/// Mail the user a link that verifies their email, unless
+/// they got one less than `MAIL_INTERVAL_MINUTES` ago
+fn send_email_verification(
+    repository: &web::Data<dyn Repository>,
+    mailer: &web::Data<dyn Mailer>,
+    user: &User,
+) -> Result<(), TodoApiError> {
+    let token = new_secret_token();
+    let now = chrono::Local::now().naive_local();
+
+    let verification = EmailVerification::from(
+        user.id,
+        hash_secret_token(&token),
+        now + chrono::Duration::hours(*VERIFY_TOKEN_HOURS),
+    );
+
+    if !repository.insert_email_verification(
+        &verification,
+        now - chrono::Duration::minutes(*MAIL_INTERVAL_MINUTES),
+    )? {
+        return Ok(());
+    }
+
+    mailer.send(&Mail {
+        to: user.email.clone(),
+        subject: "Verify your Todo email".to_string(),
+        text: format!(
+            "Hi {},\n\nOpen this link to verify your email:\n\n{}/api/auth/verify?token={}\n\n\
+             The link works for {} hours.",
+            user.name, *PUBLIC_URL, token, *VERIFY_TOKEN_HOURS
+        ),
+    })
+}
+
+/// Expiry of a refresh token issued now
+fn refresh_token_expiry() -> chrono::NaiveDateTime {
+    chrono::Local::now().naive_local() + chrono::Duration::days(*REFRESH_TOKEN_DAYS)
+}
 
-    let conn = &pool.get()?;
+/// Tokens of the session with `session_id`, the refresh token is only returned here
+fn issue_tokens(
+    user: &SlimUser,
+    session_id: uuid::Uuid,
+    refresh_token: String,
+) -> Result<LoginResponseDTO, TodoApiError> {
+    let claims = Claims::new(user, session_id);
 
-    let found_user: Result<SlimUser, _> = users
-        .filter(email.eq(user_data.email))
-        .load::<User>(conn)
+    let token = encode_token(&claims).map_err(TodoApiError::AuthError)?;
+
+    Ok(LoginResponseDTO {
+        id: user.id.to_string(),
+        email: user.email.clone(),
+        token,
+        refresh_token,
+        expires_at: claims.exp,
+    })
+}
+
+/// Start a new session for a user that logged in or signed up
+fn start_session(
+    repository: &web::Data<dyn Repository>,
+    user: &SlimUser,
+) -> Result<LoginResponseDTO, TodoApiError> {
+    let refresh_token = new_secret_token();
+
+    let session = Session::from(
+        user.id,
+        hash_secret_token(&refresh_token),
+        refresh_token_expiry(),
+    );
+
+    repository.insert_session(&session)?;
+
+    issue_tokens(user, session.id, refresh_token)
+}
+
+/// Rotate the refresh token of a session, a refresh token that was already
+/// used is rejected so a stolen one works at most once
+fn refresh_session(
+    repository: web::Data<dyn Repository>,
+    request_data: RefreshTokenDTO,
+) -> Result<RefreshResponseDTO, TodoApiError> {
+    let old_hash = hash_secret_token(&request_data.refresh_token);
+
+    let session = repository
+        .find_session_by_refresh_token(&old_hash)?
+        .filter(|session| session.is_active(chrono::Local::now().naive_local()))
+        .ok_or(TodoApiError::AuthError(AuthError::InvalidRefreshToken))?;
+
+    let refresh_token = new_secret_token();
+
+    let rotated = repository.rotate_refresh_token(
+        session.id,
+        &old_hash,
+        &hash_secret_token(&refresh_token),
+        refresh_token_expiry(),
+    )?;
+
+    // Someone else refreshed the session in the meantime
+    if !rotated {
+        return Err(TodoApiError::AuthError(AuthError::InvalidRefreshToken));
+    }
+
+    let user: SlimUser = repository
+        .find_user_by_id(session.user_id)?
+        .ok_or(TodoApiError::AuthError(AuthError::InvalidRefreshToken))?
+        .into();
+
+    issue_tokens(&user, session.id, refresh_token)
+}
+
+/// Get User by email
+fn get_user(
+    repository: web::Data<dyn Repository>,
+    mailer: web::Data<dyn Mailer>,
+    user_data: LoginDTO,
+) -> Result<LoginResponseDTO, TodoApiError> {
+    let found_user: Result<SlimUser, _> = repository
+        .find_user_by_email(&user_data.email)
         .map_err(|_db_error| {
             eprintln!("Db error {}", _db_error);
             TodoApiError::InternalServerError
         })
-        .and_then(|mut result| {
-            if let Some(user) = result.pop() {
+        .and_then(|result| {
+            if let Some(user) = result {
                 if verify_hash(&user.password, &user_data.password)? {
+                    if needs_rehash(&user.password) {
+                        rehash_password(&repository, &user, &user_data.password);
+                    }
+
+                    if *REQUIRE_VERIFIED_EMAIL && user.email_verified_at.is_none() {
+                        // Another mail, in case the first one got lost
+                        send_email_verification(&repository, &mailer, &user).unwrap_or_else(|e| {
+                            eprintln!("Failed to send the verification mail: {}", e)
+                        });
+
+                        return Err(TodoApiError::AuthError(AuthError::EmailNotVerified));
+                    }
+
                     Ok(user.into())
                 } else {
                     Err(TodoApiError::AuthError(AuthError::Unauthorized))
@@ -65,63 +346,476 @@ fn get_user(pool: web::Data<Pool>, user_data: LoginDTO) -> Result<LoginResponseD
         });
 
     match found_user {
-        Ok(user) => {
-            let token = encode_token::<Claims>(&user).unwrap();
-
-            Ok(LoginResponseDTO {
-                id: user.id.to_string(),
-                email: user.email,
-                token,
-            })
-        }
+        Ok(user) => start_session(&repository, &user),
         Err(err) => Err(err),
     }
 }
 
+/// Hash a password again with the current parameters and a salt of its own.
+/// Login works with the old hash too, so failing here doesn't stop it
+fn rehash_password(repository: &web::Data<dyn Repository>, user: &User, password: &str) {
+    let rehashed = hash_password(password)
+        .and_then(|password_hash| repository.update_password(user.id, &password_hash));
+
+    if let Err(e) = rehashed {
+        eprintln!("Failed to rehash the password of {}: {}", user.id, e);
+    }
+}
+
 /// Query Database to insert a new user on signup
 fn insert_new_user(
-    pool: web::Data<Pool>,
+    repository: web::Data<dyn Repository>,
+    mailer: web::Data<dyn Mailer>,
     user_data: SignupRequestDTO,
 ) -> Result<SignupResponseDTO, TodoApiError> {
-    use crate::schema::users::dsl::email;
-    use crate::schema::users::dsl::*;
-
-    let conn = &pool.get()?;
-
-    let user: Result<SlimUser, _> = users
-        .filter(email.eq(&user_data.email))
-        .load::<User>(conn)
+    let user: Result<User, _> = repository
+        .find_user_by_email(&user_data.email)
         .map_err(|_db_error| {
             eprintln!("Db Error User {}", _db_error);
             TodoApiError::InternalServerError
         })
-        .and_then(|mut result| {
-            if let Some(_) = result.pop() {
+        .and_then(|result| {
+            if let Some(_) = result {
                 return Err(TodoApiError::BadRequest("User Already Exists".into()));
             } else {
-                let hashed = hash_password(&user_data.password).unwrap();
+                let hashed = hash_password(&user_data.password)?;
 
                 let new_user = User::from_details(user_data.name, user_data.email, hashed.into());
-                let _: User = diesel::insert_into(users)
-                    .values(&new_user)
-                    .get_result(conn)?;
+                repository.insert_user(&new_user)?;
 
-                Ok(new_user.into())
+                Ok(new_user)
             }
         });
 
     match user {
-        Ok(slim_user) => {
-            let token = encode_token::<Claims>(&slim_user).unwrap();
-
-            return Ok(SignupResponseDTO {
-                email: slim_user.email,
-                id: slim_user.id.to_string(),
-                token,
-            });
+        Ok(new_user) => {
+            // The account works without the mail, unless emails have to be verified
+            send_email_verification(&repository, &mailer, &new_user)
+                .unwrap_or_else(|e| eprintln!("Failed to send the verification mail: {}", e));
+
+            if *REQUIRE_VERIFIED_EMAIL {
+                return Ok(SignupResponseDTO {
+                    id: new_user.id.to_string(),
+                    email: new_user.email,
+                    token: None,
+                    refresh_token: None,
+                    expires_at: None,
+                });
+            }
+
+            return start_session(&repository, &new_user.into()).map(Into::into);
         }
         Err(err) => {
             return Err(err);
         }
     }
 }
+
+#[cfg(test)]
+mod auth_handler_test {
+    use std::{path::PathBuf, time::Duration};
+
+    use actix_web::{http::StatusCode, test};
+    use serde_json::{json, Value};
+
+    use super::{send_email_verification, send_password_reset};
+    use crate::{
+        api::{
+            auth_utils::{needs_rehash, SECRET_KEY},
+            dtos::auth::ForgotPasswordDTO,
+            test_utils::{test_app, test_mailer, test_repository},
+        },
+        models::user_model::User,
+    };
+
+    /// A new temporary file for the mails of a test
+    fn mail_file() -> PathBuf {
+        std::env::temp_dir().join(format!("todo-mails-{}.txt", uuid::Uuid::new_v4()))
+    }
+
+    /// The mails written to `path`, waiting for the ones sent after responding
+    async fn read_mails(path: &PathBuf) -> String {
+        for _ in 0..100 {
+            if let Ok(mails) = std::fs::read_to_string(path) {
+                return mails;
+            }
+
+            actix_web::rt::time::sleep(Duration::from_millis(20)).await;
+        }
+
+        panic!("No mail was written to {}", path.display());
+    }
+
+    /// The token of the last mail written to `path`
+    fn mailed_token(path: &PathBuf) -> String {
+        let mails = std::fs::read_to_string(path).unwrap();
+
+        let token = mails
+            .split(|c: char| !c.is_ascii_hexdigit())
+            .filter(|word| word.len() == 64)
+            .last()
+            .unwrap();
+
+        token.to_string()
+    }
+
+    fn login_request(email: &str, password: &str) -> test::TestRequest {
+        test::TestRequest::post()
+            .uri("/api/auth/login")
+            .set_json(json!({ "email": email, "password": password }))
+    }
+
+    #[actix_web::test]
+    async fn test_signup_and_login() {
+        let app = test::init_service(test_app(test_repository(), test_mailer(None))).await;
+
+        let signup = test::TestRequest::post().uri("/api/auth/signup").set_json(
+            json!({ "email": "ada@todo.test", "password": "open sesame", "name": "Ada" }),
+        );
+
+        let user: Value = test::call_and_read_body_json(&app, signup.to_request()).await;
+
+        assert_eq!(user["email"], "ada@todo.test");
+
+        let login: Value = test::call_and_read_body_json(
+            &app,
+            login_request("ada@todo.test", "open sesame").to_request(),
+        )
+        .await;
+
+        assert_eq!(login["id"], user["id"]);
+
+        // The token of the login is accepted by the api
+        let response = test::call_service(
+            &app,
+            test::TestRequest::get()
+                .uri("/api/todo")
+                .insert_header((
+                    "Authorization",
+                    format!("Bearer {}", login["token"].as_str().unwrap()),
+                ))
+                .to_request(),
+        )
+        .await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn test_refresh_and_logout() {
+        let app = test::init_service(test_app(test_repository(), test_mailer(None))).await;
+
+        let signup = test::TestRequest::post().uri("/api/auth/signup").set_json(
+            json!({ "email": "eve@todo.test", "password": "open sesame", "name": "Eve" }),
+        );
+
+        let signup: Value = test::call_and_read_body_json(&app, signup.to_request()).await;
+
+        let refresh_request = |refresh_token: &Value| {
+            test::TestRequest::post()
+                .uri("/api/auth/refresh")
+                .set_json(json!({ "refresh_token": refresh_token }))
+        };
+
+        let refreshed: Value = test::call_and_read_body_json(
+            &app,
+            refresh_request(&signup["refresh_token"]).to_request(),
+        )
+        .await;
+
+        assert_eq!(refreshed["id"], signup["id"]);
+        assert_ne!(refreshed["refresh_token"], signup["refresh_token"]);
+
+        // A refresh token works only once
+        let response =
+            test::call_service(&app, refresh_request(&signup["refresh_token"]).to_request()).await;
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let authorized = |request: test::TestRequest| {
+            request.insert_header((
+                "Authorization",
+                format!("Bearer {}", refreshed["token"].as_str().unwrap()),
+            ))
+        };
+
+        let response = test::call_service(
+            &app,
+            authorized(test::TestRequest::post().uri("/api/auth/logout")).to_request(),
+        )
+        .await;
+
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+        // Neither the token nor the refresh token of the session work after logout
+        let response = test::call_service(
+            &app,
+            authorized(test::TestRequest::get().uri("/api/todo")).to_request(),
+        )
+        .await;
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = test::call_service(
+            &app,
+            refresh_request(&refreshed["refresh_token"]).to_request(),
+        )
+        .await;
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn test_password_reset() {
+        let mails = mail_file();
+        let repository = test_repository();
+        let mailer = test_mailer(Some(mails.clone()));
+
+        let app = test::init_service(test_app(repository.clone(), mailer.clone())).await;
+
+        let signup = test::TestRequest::post().uri("/api/auth/signup").set_json(
+            json!({ "email": "kim@todo.test", "password": "open sesame", "name": "Kim" }),
+        );
+
+        let signup: Value = test::call_and_read_body_json(&app, signup.to_request()).await;
+
+        // Only the verification mail was sent so far
+        std::fs::remove_file(&mails).unwrap();
+
+        let forgot_password = |email: &str| {
+            test::TestRequest::post()
+                .uri("/api/auth/forgot-password")
+                .set_json(json!({ "email": email }))
+        };
+
+        // Unknown emails get the same answer, but no mail
+        for email in ["nobody@todo.test", "kim@todo.test"] {
+            let response = test::call_service(&app, forgot_password(email).to_request()).await;
+
+            assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        }
+
+        let mail = read_mails(&mails).await;
+
+        assert_eq!(mail.matches("To: ").count(), 1);
+        assert!(mail.starts_with("To: kim@todo.test\n"));
+
+        // Asking again right away sends no other mail
+        send_password_reset(
+            repository.clone(),
+            mailer.clone(),
+            ForgotPasswordDTO {
+                email: "kim@todo.test".to_string(),
+            },
+        )
+        .unwrap();
+
+        let mail = std::fs::read_to_string(&mails).unwrap();
+
+        assert_eq!(mail.matches("To: ").count(), 1);
+
+        let token = mailed_token(&mails);
+
+        let reset_password = || {
+            test::TestRequest::post()
+                .uri("/api/auth/reset-password")
+                .set_json(json!({ "token": token, "password": "new secret" }))
+        };
+
+        let response = test::call_service(&app, reset_password().to_request()).await;
+
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+        // The token works only once
+        let response = test::call_service(&app, reset_password().to_request()).await;
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = test::call_service(
+            &app,
+            login_request("kim@todo.test", "open sesame").to_request(),
+        )
+        .await;
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = test::call_service(
+            &app,
+            login_request("kim@todo.test", "new secret").to_request(),
+        )
+        .await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Sessions started with the old password are over
+        let response = test::call_service(
+            &app,
+            test::TestRequest::get()
+                .uri("/api/todo")
+                .insert_header((
+                    "Authorization",
+                    format!("Bearer {}", signup["token"].as_str().unwrap()),
+                ))
+                .to_request(),
+        )
+        .await;
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        std::fs::remove_file(mails).ok();
+    }
+
+    #[actix_web::test]
+    async fn test_verify_email() {
+        let mails = mail_file();
+        let repository = test_repository();
+        let mailer = test_mailer(Some(mails.clone()));
+
+        let app = test::init_service(test_app(repository.clone(), mailer.clone())).await;
+
+        let signup = test::TestRequest::post().uri("/api/auth/signup").set_json(
+            json!({ "email": "lee@todo.test", "password": "open sesame", "name": "Lee" }),
+        );
+
+        let response = test::call_service(&app, signup.to_request()).await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let mail = std::fs::read_to_string(&mails).unwrap();
+
+        assert!(mail.contains("/api/auth/verify?token="));
+
+        // Logging in before verifying doesn't send another mail right away
+        let user = repository
+            .find_user_by_email("lee@todo.test")
+            .unwrap()
+            .unwrap();
+
+        send_email_verification(&repository, &mailer, &user).unwrap();
+
+        let mail = std::fs::read_to_string(&mails).unwrap();
+
+        assert_eq!(mail.matches("To: ").count(), 1);
+
+        let verify = || {
+            test::TestRequest::get()
+                .uri(&format!("/api/auth/verify?token={}", mailed_token(&mails)))
+        };
+
+        let response = test::call_service(&app, verify().to_request()).await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // The link works only once
+        let response = test::call_service(&app, verify().to_request()).await;
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        std::fs::remove_file(mails).ok();
+    }
+
+    #[actix_web::test]
+    async fn test_legacy_password_is_rehashed() {
+        let repository = test_repository();
+
+        // Hashed the way every password was before salts were random
+        let legacy_hash = argon2::hash_encoded(
+            b"open sesame",
+            b"supersecuresalt",
+            &argon2::Config {
+                secret: SECRET_KEY.as_bytes(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        repository
+            .insert_user(&User::from_details(
+                "Old".to_string(),
+                "old@todo.test".to_string(),
+                legacy_hash.clone(),
+            ))
+            .unwrap();
+
+        let app = test::init_service(test_app(repository.clone(), test_mailer(None))).await;
+
+        for _ in 0..2 {
+            let response = test::call_service(
+                &app,
+                login_request("old@todo.test", "open sesame").to_request(),
+            )
+            .await;
+
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let stored = repository
+                .find_user_by_email("old@todo.test")
+                .unwrap()
+                .unwrap()
+                .password;
+
+            assert_ne!(stored, legacy_hash);
+            assert!(!needs_rehash(&stored));
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_auth_errors() {
+        let app = test::init_service(test_app(test_repository(), test_mailer(None))).await;
+
+        let signup = || {
+            test::TestRequest::post().uri("/api/auth/signup").set_json(
+                json!({ "email": "bob@todo.test", "password": "open sesame", "name": "Bob" }),
+            )
+        };
+
+        let response = test::call_service(&app, signup().to_request()).await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let failures = vec![
+            (signup(), StatusCode::BAD_REQUEST),
+            (
+                test::TestRequest::post().uri("/api/auth/signup").set_json(
+                    json!({ "email": "bob.todo.test", "password": "open sesame", "name": "Bob" }),
+                ),
+                StatusCode::BAD_REQUEST,
+            ),
+            (
+                test::TestRequest::post().uri("/api/auth/signup").set_json(
+                    json!({ "email": "ann@todo.test", "password": "short", "name": "Ann" }),
+                ),
+                StatusCode::BAD_REQUEST,
+            ),
+            (
+                login_request("bob@todo.test", "wrong"),
+                StatusCode::UNAUTHORIZED,
+            ),
+            (
+                login_request("nobody@todo.test", "open sesame"),
+                StatusCode::BAD_REQUEST,
+            ),
+            (
+                test::TestRequest::get().uri("/api/todo"),
+                StatusCode::UNAUTHORIZED,
+            ),
+            (
+                test::TestRequest::get()
+                    .uri("/api/todo")
+                    .insert_header(("Authorization", "Bearer not-a-token")),
+                StatusCode::UNAUTHORIZED,
+            ),
+        ];
+
+        for (request, status) in failures {
+            let response = test::call_service(&app, request.to_request()).await;
+
+            assert_eq!(response.status(), status);
+
+            // Errors are always reported as json
+            let body: Value = test::read_body_json(response).await;
+
+            assert!(body["error"].is_string());
+        }
+    }
+}
@@ -1,161 +1,164 @@
 use actix_web::http::header::HeaderValue;
-use argon2::Config;
-use diesel::{r2d2::ConnectionManager, PgConnection};
-use jsonwebtoken::{decode, decode_header, encode, DecodingKey, EncodingKey, Header, Validation};
-use r2d2::PooledConnection;
+use argon2::{Config, Variant, Version};
+use rand::RngCore;
 use serde::{de::DeserializeOwned, Serialize};
-use serde_json::{from_value, Value};
-
-use crate::models::{todo_model::Todo, user_model::SlimUser};
+use sha2::{Digest, Sha256};
 
 use super::{
     errors::{AuthError, TodoApiError},
-    middlewares::auth::Claims,
+    jwt_keys::JWT_KEYS,
 };
-use diesel::prelude::*;
 
 lazy_static::lazy_static! {
     pub static ref SECRET_KEY: String = std::env::var("SECRET_KEY").unwrap_or_else(|_| "0123".repeat(8));
-}
 
-const SALT: &'static [u8] = b"supersecuresalt";
+    /// Minutes an access token is accepted before it has to be refreshed
+    pub static ref ACCESS_TOKEN_MINUTES: i64 = std::env::var("ACCESS_TOKEN_MINUTES")
+        .ok()
+        .and_then(|minutes| minutes.parse().ok())
+        .unwrap_or(15);
+
+    /// Days a session can be refreshed without logging in again
+    pub static ref REFRESH_TOKEN_DAYS: i64 = std::env::var("REFRESH_TOKEN_DAYS")
+        .ok()
+        .and_then(|days| days.parse().ok())
+        .unwrap_or(30);
+
+    /// Hours the link of a verification mail works
+    pub static ref VERIFY_TOKEN_HOURS: i64 = std::env::var("VERIFY_TOKEN_HOURS")
+        .ok()
+        .and_then(|hours| hours.parse().ok())
+        .unwrap_or(48);
+
+    /// Whether users have to verify their email before they can login
+    pub static ref REQUIRE_VERIFIED_EMAIL: bool = std::env::var("REQUIRE_VERIFIED_EMAIL")
+        .is_ok_and(|required| required == "true" || required == "1");
+
+    /// Minutes a password reset token sent by mail can be used
+    pub static ref RESET_TOKEN_MINUTES: i64 = std::env::var("RESET_TOKEN_MINUTES")
+        .ok()
+        .and_then(|minutes| minutes.parse().ok())
+        .unwrap_or(30);
+
+    /// Minutes before a user can get another verification or password reset mail
+    pub static ref MAIL_INTERVAL_MINUTES: i64 = std::env::var("MAIL_INTERVAL_MINUTES")
+        .ok()
+        .and_then(|minutes| minutes.parse().ok())
+        .unwrap_or(5);
+
+    /// KiB of memory hashing a password takes
+    pub static ref ARGON2_MEMORY_KIB: u32 = std::env::var("ARGON2_MEMORY_KIB")
+        .ok()
+        .and_then(|memory| memory.parse().ok())
+        .unwrap_or(19 * 1024);
+
+    /// Passes over that memory when hashing a password
+    pub static ref ARGON2_ITERATIONS: u32 = std::env::var("ARGON2_ITERATIONS")
+        .ok()
+        .and_then(|iterations| iterations.parse().ok())
+        .unwrap_or(2);
+}
 
-const JWT_SECRET_KEY: &'static [u8] = b"secure jwt secret";
+/// Bytes of random salt in every password hash
+const SALT_LENGTH: usize = 16;
 
 // Hashing
 
-/// Hash a password, to secure
-pub fn hash_password(password: &str) -> Result<String, TodoApiError> {
-    let config = Config {
+/// Argon2id with the memory and iterations of the environment
+fn argon2_config() -> Config<'static> {
+    Config {
+        variant: Variant::Argon2id,
+        version: Version::Version13,
+        mem_cost: *ARGON2_MEMORY_KIB,
+        time_cost: *ARGON2_ITERATIONS,
         secret: SECRET_KEY.as_bytes(),
         ..Default::default()
-    };
+    }
+}
 
-    argon2::hash_encoded(password.as_bytes(), &SALT, &config).map_err(|err| {
-        dbg!(err);
+/// Hash a password with a new random salt, the salt
+/// and the parameters are kept in the returned hash
+pub fn hash_password(password: &str) -> Result<String, TodoApiError> {
+    let mut salt = [0u8; SALT_LENGTH];
+
+    rand::thread_rng().fill_bytes(&mut salt);
+
+    argon2::hash_encoded(password.as_bytes(), &salt, &argon2_config()).map_err(|err| {
+        eprintln!("Failed to hash a password: {}", err);
         TodoApiError::InternalServerError
     })
 }
 
-/// Verify password and hash are equal
+/// Verify password and hash are equal. A hash that can't be read is
+/// broken data, not a wrong password, so it is an internal error
 pub fn verify_hash(hash: &str, password: &str) -> Result<bool, TodoApiError> {
     argon2::verify_encoded_ext(hash, password.as_bytes(), SECRET_KEY.as_bytes(), &[]).map_err(
         |err| {
-            dbg!(err);
-            TodoApiError::AuthError(crate::api::errors::AuthError::InvalidToken)
+            eprintln!("Failed to verify a password hash: {}", err);
+            TodoApiError::InternalServerError
         },
     )
 }
 
-///Verifies that a user with `user_id` has access to todo with `todo_id`
-pub fn verify_todo_owner(
-    conn: &PooledConnection<ConnectionManager<PgConnection>>,
-    requester_id: &str,
-    todo_id: &str,
-) -> Result<(), TodoApiError> {
-    use crate::schema::todos::dsl::*;
-
-    let todo_id = uuid::Uuid::parse_str(todo_id).unwrap();
-
-    let requester_id = uuid::Uuid::parse_str(requester_id).unwrap();
-
-    let result: Vec<Todo> = todos
-        .filter(id.eq(todo_id))
-        .filter(user_id.eq(requester_id))
-        .load::<Todo>(conn)?;
+/// Whether a hash was made with other parameters than the current ones, as
+/// the Argon2i hashes made with a salt shared by every user before. These
+/// are hashed again once the password is known, on login
+pub fn needs_rehash(hash: &str) -> bool {
+    let config = argon2_config();
+
+    let parameters = format!(
+        "${}$v={}$m={},t={},p={}$",
+        config.variant.as_lowercase_str(),
+        config.version.as_u32(),
+        config.mem_cost,
+        config.time_cost,
+        config.lanes
+    );
+
+    !hash.starts_with(&parameters)
+}
 
-    if result.len() == 0 {
+/// Maps the number of rows touched by a scoped write to a result,
+/// nothing touched means the todo does not exist for the requester
+pub fn ensure_todo_found(affected_rows: usize) -> Result<(), TodoApiError> {
+    if affected_rows == 0 {
         return Err(TodoApiError::NotFound("Todo".to_string()));
     }
 
     Ok(())
 }
 
-// JWT STUFF
-pub trait Claimable<'a>: Serialize + From<&'a SlimUser> + DeserializeOwned {}
-
-/// Create a jwt token for user
-pub fn _encode_token(user: &SlimUser) -> Result<String, AuthError> {
-    Ok(encode::<Claims>(
-        &Header::new(jsonwebtoken::Algorithm::HS256),
-        &user.into(),
-        &EncodingKey::from_secret(JWT_SECRET_KEY),
-    )?)
+// Refresh and password reset tokens
+
+/// A new random token, only its hash is stored on the server
+pub fn new_secret_token() -> String {
+    let mut bytes = [0u8; 32];
+
+    rand::thread_rng().fill_bytes(&mut bytes);
+
+    to_hex(&bytes)
 }
 
-pub fn encode_token<'a, T: Claimable<'a>>(user: &'a SlimUser) -> Result<String, AuthError> {
-    Ok(encode::<T>(
-        &Header::new(jsonwebtoken::Algorithm::HS256),
-        &user.into(),
-        &EncodingKey::from_secret(JWT_SECRET_KEY),
-    )?)
+/// Hash of a token, the form it is stored and looked up in
+pub fn hash_secret_token(token: &str) -> String {
+    to_hex(&Sha256::digest(token.as_bytes()))
 }
 
-pub fn decode_token<'a, T: Claimable<'a>>(auth_header: &HeaderValue) -> Result<T, AuthError> {
-    match auth_header.to_str() {
-        Ok(auth_header_string) => {
-            let token = auth_header_string.trim_start_matches("Bearer ");
-
-            match decode_header(token) {
-                Ok(jwt_header) => {
-                    match decode::<Value>(
-                        token,
-                        &DecodingKey::from_secret(JWT_SECRET_KEY),
-                        &Validation::new(jwt_header.alg),
-                    ) {
-                        Ok(raw_token) => {
-                            let decoded = from_value::<T>(raw_token.claims.clone());
-
-                            match decoded {
-                                Ok(claims) => Ok(claims),
-                                Err(e) => Err(AuthError::Claims(e)),
-                            }
-                        }
-
-                        Err(_) => Err(AuthError::InvalidToken),
-                    }
-                }
-                Err(e) => {
-                    dbg!(e);
-                    Err(AuthError::InvalidToken)
-                }
-            }
-        }
-        Err(_) => Err(AuthError::InvalidAuthorizationHeader),
-    }
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+// JWT STUFF
+pub trait Claimable: Serialize + DeserializeOwned {}
+
+/// Signs the claims with the current signing key
+pub fn encode_token<T: Claimable>(claims: &T) -> Result<String, AuthError> {
+    JWT_KEYS.encode(claims)
 }
 
-/// Verifies a jwt token
-pub fn _decode_token(auth_header: &HeaderValue) -> Result<Claims, AuthError> {
+pub fn decode_token<T: Claimable>(auth_header: &HeaderValue) -> Result<T, AuthError> {
     match auth_header.to_str() {
-        Ok(auth_header_string) => {
-            let token = auth_header_string.trim_start_matches("Bearer ");
-
-            match decode_header(token) {
-                Ok(jwt_header) => {
-                    match decode::<Value>(
-                        token,
-                        &DecodingKey::from_secret(JWT_SECRET_KEY),
-                        &Validation::new(jwt_header.alg),
-                    ) {
-                        Ok(raw_token) => {
-                            let decoded = from_value::<Claims>(raw_token.claims.clone());
-
-                            match decoded {
-                                Ok(claims) => Ok(claims),
-                                Err(e) => Err(AuthError::Claims(e)),
-                            }
-                        }
-
-                        Err(_) => Err(AuthError::InvalidToken),
-                    }
-                }
-                Err(e) => {
-                    dbg!(e);
-                    Err(AuthError::InvalidToken)
-                }
-            }
-        }
+        Ok(auth_header_string) => JWT_KEYS.decode(auth_header_string.trim_start_matches("Bearer ")),
         Err(_) => Err(AuthError::InvalidAuthorizationHeader),
     }
 }
@@ -1,3 +1,13 @@
+use crate::api::errors::TodoApiError;
+
+/// Emails are stored in a `VARCHAR(100)`
+const MAX_EMAIL_LENGTH: usize = 100;
+
+const MIN_PASSWORD_LENGTH: usize = 8;
+
+/// Hashing takes as long for any password, this only keeps requests reasonable
+const MAX_PASSWORD_LENGTH: usize = 128;
+
 #[derive(Debug, serde::Deserialize)]
 pub struct LoginDTO {
     pub email: String,
@@ -9,9 +19,53 @@ pub struct LoginResponseDTO {
     pub id: String,
     pub email: String,
     pub token: String,
+    /// Exchanged for a new `token` at `/auth/refresh`, works only once
+    pub refresh_token: String,
+    /// When `token` expires
+    pub expires_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// The new account, with the tokens of a session when the user can login
+/// right away. There are none when the email has to be verified first
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct SignupResponseDTO {
+    pub id: String,
+    pub email: String,
+    pub token: Option<String>,
+    pub refresh_token: Option<String>,
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl From<LoginResponseDTO> for SignupResponseDTO {
+    fn from(login: LoginResponseDTO) -> Self {
+        Self {
+            id: login.id,
+            email: login.email,
+            token: Some(login.token),
+            refresh_token: Some(login.refresh_token),
+            expires_at: Some(login.expires_at),
+        }
+    }
+}
+
+pub type RefreshResponseDTO = LoginResponseDTO;
+
+#[derive(Debug, serde::Deserialize)]
+pub struct RefreshTokenDTO {
+    pub refresh_token: String,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct ForgotPasswordDTO {
+    pub email: String,
 }
 
-pub type SignupResponseDTO = LoginResponseDTO;
+#[derive(Debug, serde::Deserialize)]
+pub struct ResetPasswordDTO {
+    /// Token of the password reset mail
+    pub token: String,
+    pub password: String,
+}
 
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub struct SignupRequestDTO {
@@ -19,3 +73,89 @@ pub struct SignupRequestDTO {
     pub password: String,
     pub name: String,
 }
+
+#[derive(Debug, serde::Deserialize)]
+pub struct VerifyEmailQuery {
+    /// Token of the verification mail
+    pub token: String,
+}
+
+impl SignupRequestDTO {
+    pub fn validate(&self) -> Result<(), TodoApiError> {
+        validate_email(&self.email)?;
+
+        validate_password(&self.password)?;
+
+        if self.password.eq_ignore_ascii_case(&self.email) {
+            return Err(TodoApiError::BadRequest(
+                "Password can not be the email".into(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl ResetPasswordDTO {
+    pub fn validate(&self) -> Result<(), TodoApiError> {
+        validate_password(&self.password)
+    }
+}
+
+/// The minimum every password has to meet
+pub fn validate_password(password: &str) -> Result<(), TodoApiError> {
+    let length = password.chars().count();
+
+    if length < MIN_PASSWORD_LENGTH {
+        return Err(TodoApiError::BadRequest(format!(
+            "Password has to be at least {} characters",
+            MIN_PASSWORD_LENGTH
+        )));
+    }
+
+    if length > MAX_PASSWORD_LENGTH {
+        return Err(TodoApiError::BadRequest(format!(
+            "Password can not be longer than {} characters",
+            MAX_PASSWORD_LENGTH
+        )));
+    }
+
+    if password.trim().is_empty() {
+        return Err(TodoApiError::BadRequest(
+            "Password can not be only spaces".into(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Checks that `email` looks like an address mail can be sent to, something
+/// before the `@` and a domain with a dot after it. Whether it really receives
+/// mail is only known once the verification mail is followed
+pub fn validate_email(email: &str) -> Result<(), TodoApiError> {
+    let invalid = || TodoApiError::BadRequest(format!("{} is not a valid email", email));
+
+    if email.chars().count() > MAX_EMAIL_LENGTH {
+        return Err(TodoApiError::BadRequest(format!(
+            "Email can not be longer than {} characters",
+            MAX_EMAIL_LENGTH
+        )));
+    }
+
+    if email.chars().any(char::is_whitespace) {
+        return Err(invalid());
+    }
+
+    let (local, domain) = email.rsplit_once('@').ok_or_else(invalid)?;
+
+    let domain_is_valid = domain
+        .split('.')
+        .all(|label| !label.is_empty() && !label.starts_with('-') && !label.ends_with('-'))
+        && domain.contains('.');
+
+    if local.is_empty() || local.contains('@') || !domain_is_valid {
+        return Err(invalid());
+    }
+
+    Ok(())
+}
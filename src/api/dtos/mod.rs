@@ -1,3 +1,5 @@
 pub mod auth;
+pub mod project;
+pub mod subtask;
 pub mod todo;
 pub mod user;
@@ -0,0 +1,32 @@
+use serde::{Deserialize, Serialize};
+
+use crate::api::errors::TodoApiError;
+
+/// Project names are stored in a `VARCHAR(100)`
+const MAX_NAME_LENGTH: usize = 100;
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ProjectDTO {
+    pub name: String,
+}
+
+impl ProjectDTO {
+    pub fn validate(&self) -> Result<(), TodoApiError> {
+        let name = self.name.trim();
+
+        if name.is_empty() {
+            return Err(TodoApiError::BadRequest(
+                "Project name can not be empty".into(),
+            ));
+        }
+
+        if name.chars().count() > MAX_NAME_LENGTH {
+            return Err(TodoApiError::BadRequest(format!(
+                "Project name can not be longer than {} characters",
+                MAX_NAME_LENGTH
+            )));
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,37 @@
+use serde::{Deserialize, Serialize};
+
+use crate::api::{dtos::todo::validate_title, errors::TodoApiError};
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SubtaskDTO {
+    pub title: String,
+}
+
+impl SubtaskDTO {
+    pub fn validate(&self) -> Result<(), TodoApiError> {
+        validate_title(&self.title)
+    }
+}
+
+/// New order of the subtasks of a todo, every subtask has to be listed once
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ReorderSubtasksDTO {
+    pub ids: Vec<uuid::Uuid>,
+}
+
+impl ReorderSubtasksDTO {
+    pub fn validate(&self) -> Result<(), TodoApiError> {
+        let mut ids = self.ids.clone();
+
+        ids.sort();
+        ids.dedup();
+
+        if ids.len() != self.ids.len() {
+            return Err(TodoApiError::BadRequest(
+                "Subtasks can only be listed once".into(),
+            ));
+        }
+
+        Ok(())
+    }
+}
@@ -1,11 +1,754 @@
-use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+use chrono::{Datelike, NaiveDateTime};
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::{
+    api::errors::TodoApiError,
+    models::todo_model::{Priority, Recurrence, Todo, TodoChanges},
+};
+
+/// Titles are stored in a `VARCHAR(200)`
+const MAX_TITLE_LENGTH: usize = 200;
+
+/// Tag names are stored in a `VARCHAR(50)`
+const MAX_TAG_LENGTH: usize = 50;
+
+/// Notes are stored in a `TEXT`, this only keeps them reasonable
+const MAX_NOTES_LENGTH: usize = 10_000;
+
+/// Todos returned in a page of `GET /api/todo` when no `limit` is given
+const DEFAULT_PAGE_SIZE: i64 = 100;
+
+/// Most todos returned in a single page of `GET /api/todo`
+const MAX_PAGE_SIZE: i64 = 500;
+
+/// Todos returned by a search when no `limit` is given
+const DEFAULT_SEARCH_LIMIT: i64 = 20;
+
+/// Most operations in a single bulk request
+const MAX_BULK_OPERATIONS: usize = 500;
+
+/// Most days an archive request can look back, about a hundred years
+const MAX_ARCHIVE_DAYS: u32 = 36_500;
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct CreateTodoDTO {
+    /// Id picked by the client, sending the same create again
+    /// returns the todo it created the first time
+    #[serde(default)]
+    pub id: Option<uuid::Uuid>,
     pub title: String,
+    #[serde(default)]
+    pub due_at: Option<chrono::NaiveDateTime>,
+    #[serde(default)]
+    pub priority: Priority,
+    #[serde(default)]
+    pub project_id: Option<uuid::Uuid>,
+    #[serde(default, deserialize_with = "tag_names")]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub recurrence: Option<Recurrence>,
+    /// Markdown
+    #[serde(default)]
+    pub notes: Option<String>,
+}
+
+impl CreateTodoDTO {
+    pub fn validate(&self) -> Result<(), TodoApiError> {
+        validate_title(&self.title)?;
+
+        if let Some(notes) = &self.notes {
+            validate_notes(notes)?;
+        }
+
+        validate_tags(&self.tags)
+    }
+}
+
+/// Partial update of a todo, fields left out of the request are not changed
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct UpdateTodoDTO {
+    pub title: Option<String>,
+    pub completed: Option<bool>,
+    /// `null` clears the due date
+    #[serde(default, deserialize_with = "double_option")]
+    pub due_at: Option<Option<chrono::NaiveDateTime>>,
+    pub priority: Option<Priority>,
+    /// `null` removes the todo from its project
+    #[serde(default, deserialize_with = "double_option")]
+    pub project_id: Option<Option<uuid::Uuid>>,
+    /// Replaces all the tags of the todo
+    #[serde(default, deserialize_with = "optional_tag_names")]
+    pub tags: Option<Vec<String>>,
+    /// `null` stops the todo from recurring
+    #[serde(default, deserialize_with = "double_option")]
+    pub recurrence: Option<Option<Recurrence>>,
+    /// Markdown, `null` or blank notes remove them
+    #[serde(default, deserialize_with = "double_option")]
+    pub notes: Option<Option<String>>,
+    /// Reopened todos are taken out of the archive unless this says otherwise
+    pub archived: Option<bool>,
+}
+
+impl UpdateTodoDTO {
+    pub fn validate(&self) -> Result<(), TodoApiError> {
+        if let Some(title) = &self.title {
+            validate_title(title)?;
+        }
+
+        if let Some(tags) = &self.tags {
+            validate_tags(tags)?;
+        }
+
+        if let Some(Some(notes)) = &self.notes {
+            validate_notes(notes)?;
+        }
+
+        if self.title.is_none()
+            && self.completed.is_none()
+            && self.due_at.is_none()
+            && self.priority.is_none()
+            && self.project_id.is_none()
+            && self.tags.is_none()
+            && self.recurrence.is_none()
+            && self.notes.is_none()
+            && self.archived.is_none()
+        {
+            return Err(TodoApiError::BadRequest("Nothing to update".into()));
+        }
+
+        Ok(())
+    }
+}
+
+impl From<&UpdateTodoDTO> for TodoChanges {
+    fn from(dto: &UpdateTodoDTO) -> Self {
+        let now = chrono::Local::now().naive_local();
+
+        let archived_at = match (dto.archived, dto.completed) {
+            (Some(true), _) => Some(Some(now)),
+            (Some(false), _) | (None, Some(false)) => Some(None),
+            (None, _) => None,
+        };
+
+        TodoChanges {
+            title: dto.title.as_ref().map(|title| title.trim().to_string()),
+            completed: dto.completed,
+            due_at: dto.due_at,
+            priority: dto.priority,
+            project_id: dto.project_id,
+            recurrence: dto.recurrence.clone(),
+            notes: dto
+                .notes
+                .as_ref()
+                .map(|notes| notes.as_deref().and_then(normalize_notes)),
+            archived_at,
+            updated_at: now,
+        }
+    }
+}
+
+pub fn validate_title(title: &str) -> Result<(), TodoApiError> {
+    let title = title.trim();
+
+    if title.is_empty() {
+        return Err(TodoApiError::BadRequest("Title can not be empty".into()));
+    }
+
+    if title.chars().count() > MAX_TITLE_LENGTH {
+        return Err(TodoApiError::BadRequest(format!(
+            "Title can not be longer than {} characters",
+            MAX_TITLE_LENGTH
+        )));
+    }
+
+    // Search highlights are marked with control characters
+    if title.chars().any(char::is_control) {
+        return Err(TodoApiError::BadRequest(
+            "Title can not contain control characters".into(),
+        ));
+    }
+
+    Ok(())
+}
+
+fn validate_tags(tags: &[String]) -> Result<(), TodoApiError> {
+    for tag in tags {
+        if tag.is_empty() {
+            return Err(TodoApiError::BadRequest("Tag can not be empty".into()));
+        }
+
+        if tag.chars().count() > MAX_TAG_LENGTH {
+            return Err(TodoApiError::BadRequest(format!(
+                "Tag can not be longer than {} characters",
+                MAX_TAG_LENGTH
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+fn validate_notes(notes: &str) -> Result<(), TodoApiError> {
+    if notes.chars().count() > MAX_NOTES_LENGTH {
+        return Err(TodoApiError::BadRequest(format!(
+            "Notes can not be longer than {} characters",
+            MAX_NOTES_LENGTH
+        )));
+    }
+
+    if notes
+        .chars()
+        .any(|c| c.is_control() && !matches!(c, '\n' | '\r' | '\t'))
+    {
+        return Err(TodoApiError::BadRequest(
+            "Notes can not contain control characters other than line breaks and tabs".into(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Trailing whitespace is dropped, notes left blank are stored as `NULL`
+pub fn normalize_notes(notes: &str) -> Option<String> {
+    let notes = notes.trim_end();
+
+    if notes.trim().is_empty() {
+        None
+    } else {
+        Some(notes.to_string())
+    }
+}
+
+/// Tags are matched case insensitively and may be sent with a leading `#`
+pub fn normalize_tag(tag: &str) -> String {
+    tag.trim().trim_start_matches('#').to_lowercase()
+}
+
+/// Deserializes a list of tags, normalized and without duplicates
+fn tag_names<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let mut names: Vec<String> = Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|tag| normalize_tag(tag))
+        .collect();
+
+    names.sort();
+    names.dedup();
+
+    Ok(names)
+}
+
+fn optional_tag_names<'de, D>(deserializer: D) -> Result<Option<Vec<String>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    tag_names(deserializer).map(Some)
+}
+
+/// Deserializes a field that is present in the request,
+/// `null` included, as `Some`. Missing fields fall back to `None`
+/// through `#[serde(default)]`
+fn double_option<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SetPriorityDTO {
+    pub priority: Priority,
+}
+
+/// Where to move a todo to, either `before` or `after` another todo
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MoveTodoDTO {
+    pub before: Option<uuid::Uuid>,
+    pub after: Option<uuid::Uuid>,
+}
+
+impl MoveTodoDTO {
+    pub fn validate(&self) -> Result<(), TodoApiError> {
+        match (self.before, self.after) {
+            (Some(_), None) | (None, Some(_)) => Ok(()),
+            _ => Err(TodoApiError::BadRequest(
+                "Either before or after has to be given".into(),
+            )),
+        }
+    }
+}
+
+/// A change applied to one todo as part of a bulk request
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum BulkOperation {
+    Complete {
+        id: uuid::Uuid,
+    },
+    Incomplete {
+        id: uuid::Uuid,
+    },
+    /// Moves the todo to the trash
+    Delete {
+        id: uuid::Uuid,
+    },
+    /// Adds and removes tags, the other tags of the todo are kept
+    Retag {
+        id: uuid::Uuid,
+        #[serde(default, deserialize_with = "tag_names")]
+        add: Vec<String>,
+        #[serde(default, deserialize_with = "tag_names")]
+        remove: Vec<String>,
+    },
+    /// Moves the todo to another project, `null` takes it out of its project
+    Move {
+        id: uuid::Uuid,
+        project_id: Option<uuid::Uuid>,
+    },
+}
+
+impl BulkOperation {
+    /// Id of the todo the operation applies to
+    pub fn todo_id(&self) -> uuid::Uuid {
+        match self {
+            BulkOperation::Complete { id }
+            | BulkOperation::Incomplete { id }
+            | BulkOperation::Delete { id }
+            | BulkOperation::Retag { id, .. }
+            | BulkOperation::Move { id, .. } => *id,
+        }
+    }
+}
+
+/// Operations applied together in one transaction by `POST /api/todo/bulk`
+#[derive(Debug, Deserialize, Serialize)]
+pub struct BulkDTO {
+    pub operations: Vec<BulkOperation>,
+}
+
+impl BulkDTO {
+    pub fn validate(&self) -> Result<(), TodoApiError> {
+        if self.operations.is_empty() {
+            return Err(TodoApiError::BadRequest("No operations to apply".into()));
+        }
+
+        if self.operations.len() > MAX_BULK_OPERATIONS {
+            return Err(TodoApiError::BadRequest(format!(
+                "Can not apply more than {} operations at once",
+                MAX_BULK_OPERATIONS
+            )));
+        }
+
+        for operation in &self.operations {
+            if let BulkOperation::Retag { add, remove, .. } = operation {
+                if add.is_empty() && remove.is_empty() {
+                    return Err(TodoApiError::BadRequest(
+                        "Retag needs tags to add or remove".into(),
+                    ));
+                }
+
+                validate_tags(add)?;
+                validate_tags(remove)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Archives the completed todos that were completed
+/// at least `older_than_days` days ago
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ArchiveTodosDTO {
+    #[serde(default)]
+    pub older_than_days: u32,
+}
+
+impl ArchiveTodosDTO {
+    pub fn validate(&self) -> Result<(), TodoApiError> {
+        if self.older_than_days > MAX_ARCHIVE_DAYS {
+            return Err(TodoApiError::BadRequest(format!(
+                "Archiving can not look back more than {} days",
+                MAX_ARCHIVE_DAYS
+            )));
+        }
+
+        Ok(())
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct CompleteTodoDTO {
     pub id: String,
 }
+
+/// Query parameters accepted by `GET /api/todo/search`
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SearchQueryDTO {
+    pub q: String,
+    #[serde(default = "default_search_limit")]
+    pub limit: i64,
+}
+
+fn default_search_limit() -> i64 {
+    DEFAULT_SEARCH_LIMIT
+}
+
+impl SearchQueryDTO {
+    pub fn validate(&self) -> Result<(), TodoApiError> {
+        if self.words().next().is_none() {
+            return Err(TodoApiError::BadRequest(
+                "Search query needs at least one word".into(),
+            ));
+        }
+
+        if !(1..=MAX_PAGE_SIZE).contains(&self.limit) {
+            return Err(TodoApiError::BadRequest(format!(
+                "Invalid limit {}, expected a number from 1 to {}",
+                self.limit, MAX_PAGE_SIZE
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Postgres `tsquery` matching the todos that have every word of `q`,
+    /// words also match longer ones they start so results show up while typing
+    pub fn to_tsquery(&self) -> String {
+        self.words()
+            .map(|word| format!("{}:*", word.to_lowercase()))
+            .collect::<Vec<String>>()
+            .join(" & ")
+    }
+
+    /// SQLite FTS5 query matching the todos that have every word of `q`,
+    /// the same as [`Self::to_tsquery`]
+    pub fn to_fts5_query(&self) -> String {
+        self.words()
+            .map(|word| format!("\"{}\"*", word.to_lowercase()))
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+
+    /// Words of `q`, anything that isn't a letter or a digit separates
+    /// words so that no `tsquery` operator gets through
+    pub fn words(&self) -> impl Iterator<Item = &str> {
+        self.q
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+    }
+}
+
+/// Query parameters accepted by `GET /api/todo/changes`
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ChangesQueryDTO {
+    /// `next_token` of the previous sync, everything is sent without it
+    pub since: Option<String>,
+}
+
+impl ChangesQueryDTO {
+    /// Transaction id the token stands for
+    pub fn since_txid(&self) -> Result<Option<i64>, TodoApiError> {
+        match &self.since {
+            None => Ok(None),
+            Some(token) => token
+                .parse::<i64>()
+                .map(Some)
+                .map_err(|_| TodoApiError::BadRequest("Invalid sync token".into())),
+        }
+    }
+}
+
+/// Due date windows a todo list can be filtered by
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DueFilter {
+    /// Open todos whose due date has already passed
+    Overdue,
+    /// Todos due before the end of the current day
+    Today,
+    /// Todos due before the end of the current week (Sunday)
+    Week,
+}
+
+impl DueFilter {
+    /// Due dates that fall in the window, from the start when there is one
+    /// up to the end excluded, overdue todos also have to be open
+    pub fn window(self, now: NaiveDateTime) -> (Option<NaiveDateTime>, NaiveDateTime) {
+        let start_of_today = now.date().and_hms_opt(0, 0, 0).unwrap();
+
+        match self {
+            DueFilter::Overdue => (None, now),
+            DueFilter::Today => (
+                Some(start_of_today),
+                start_of_today + chrono::Duration::days(1),
+            ),
+            DueFilter::Week => {
+                // Weeks end on Sunday, so count the days left until next Monday
+                let days_left = 7 - i64::from(now.weekday().num_days_from_monday());
+
+                (
+                    Some(start_of_today),
+                    start_of_today + chrono::Duration::days(days_left),
+                )
+            }
+        }
+    }
+}
+
+impl FromStr for DueFilter {
+    type Err = TodoApiError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "overdue" => Ok(DueFilter::Overdue),
+            "today" => Ok(DueFilter::Today),
+            "week" => Ok(DueFilter::Week),
+            _ => Err(TodoApiError::BadRequest(format!(
+                "Unknown due filter {}, expected overdue, today or week",
+                s
+            ))),
+        }
+    }
+}
+
+/// How todos are matched against the tags of a list query
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TagMatch {
+    /// Todos having at least one of the tags
+    #[default]
+    Any,
+    /// Todos having every one of the tags
+    All,
+}
+
+impl FromStr for TagMatch {
+    type Err = TodoApiError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "any" => Ok(TagMatch::Any),
+            "all" => Ok(TagMatch::All),
+            _ => Err(TodoApiError::BadRequest(format!(
+                "Unknown tag match {}, expected any or all",
+                s
+            ))),
+        }
+    }
+}
+
+/// Order todo lists are returned in, open todos always come first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TodoSort {
+    /// The order the user arranged the todos in
+    #[default]
+    Manual,
+    /// Most important and soonest due at the top
+    Priority,
+    /// Last created at the top
+    Created,
+    /// Last updated at the top
+    Updated,
+    /// Soonest due at the top, todos without a due date last
+    Due,
+    /// Alphabetically by title
+    Title,
+}
+
+impl FromStr for TodoSort {
+    type Err = TodoApiError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "manual" => Ok(TodoSort::Manual),
+            "priority" => Ok(TodoSort::Priority),
+            "created" => Ok(TodoSort::Created),
+            "updated" => Ok(TodoSort::Updated),
+            "due" => Ok(TodoSort::Due),
+            "title" => Ok(TodoSort::Title),
+            _ => Err(TodoApiError::BadRequest(format!(
+                "Unknown sort {}, expected manual, priority, created, updated, due or title",
+                s
+            ))),
+        }
+    }
+}
+
+/// Query parameters accepted by `GET /api/todo`
+#[derive(Debug)]
+pub struct TodoListQuery {
+    pub due: Option<DueFilter>,
+    /// Id or name of the project to list todos of
+    pub project: Option<String>,
+    /// `tag` can be repeated to filter by several tags
+    pub tags: Vec<String>,
+    pub tag_match: TagMatch,
+    /// Id of a recurring series to list the occurrences of
+    pub series: Option<uuid::Uuid>,
+    pub sort: TodoSort,
+    /// Archived todos are only listed with `include=archived`
+    pub include_archived: bool,
+    pub completed: Option<bool>,
+    pub created_before: Option<chrono::NaiveDateTime>,
+    pub created_after: Option<chrono::NaiveDateTime>,
+    pub updated_since: Option<chrono::NaiveDateTime>,
+    /// Only todos with this text in their title or notes, ignoring case
+    pub text: Option<String>,
+    /// Most todos in the page, between 1 and [`MAX_PAGE_SIZE`]
+    pub limit: i64,
+    /// `next_cursor` of the previous page, the page starts right after its last todo
+    pub cursor: Option<TodoCursor>,
+}
+
+impl Default for TodoListQuery {
+    fn default() -> Self {
+        Self {
+            due: None,
+            project: None,
+            tags: vec![],
+            tag_match: TagMatch::default(),
+            series: None,
+            sort: TodoSort::default(),
+            include_archived: false,
+            completed: None,
+            created_before: None,
+            created_after: None,
+            updated_since: None,
+            text: None,
+            limit: DEFAULT_PAGE_SIZE,
+            cursor: None,
+        }
+    }
+}
+
+/// Builds the list query from raw query pairs,
+/// as the urlencoded deserializer can't collect repeated keys
+impl TryFrom<Vec<(String, String)>> for TodoListQuery {
+    type Error = TodoApiError;
+
+    fn try_from(pairs: Vec<(String, String)>) -> Result<Self, Self::Error> {
+        let mut query = TodoListQuery::default();
+
+        for (key, value) in pairs {
+            match key.as_str() {
+                "due" => query.due = Some(value.parse()?),
+                "project" => query.project = Some(value),
+                "tag" => query.tags.push(normalize_tag(&value)),
+                "tag_match" => query.tag_match = value.parse()?,
+                "series" => query.series = Some(uuid::Uuid::parse_str(&value)?),
+                "sort" => query.sort = value.parse()?,
+                "include" => match value.as_str() {
+                    "archived" => query.include_archived = true,
+                    _ => {
+                        return Err(TodoApiError::BadRequest(format!(
+                            "Unknown include {}, expected archived",
+                            value
+                        )))
+                    }
+                },
+                "completed" => match value.as_str() {
+                    "true" => query.completed = Some(true),
+                    "false" => query.completed = Some(false),
+                    _ => {
+                        return Err(TodoApiError::BadRequest(format!(
+                            "Invalid completed {}, expected true or false",
+                            value
+                        )))
+                    }
+                },
+                "created_before" => query.created_before = Some(parse_timestamp(&key, &value)?),
+                "created_after" => query.created_after = Some(parse_timestamp(&key, &value)?),
+                "updated_since" => query.updated_since = Some(parse_timestamp(&key, &value)?),
+                "text" => {
+                    let text = value.trim();
+
+                    if !text.is_empty() {
+                        query.text = Some(text.to_string());
+                    }
+                }
+                "limit" => match value.parse::<i64>() {
+                    Ok(limit) if (1..=MAX_PAGE_SIZE).contains(&limit) => query.limit = limit,
+                    _ => {
+                        return Err(TodoApiError::BadRequest(format!(
+                            "Invalid limit {}, expected a number from 1 to {}",
+                            value, MAX_PAGE_SIZE
+                        )))
+                    }
+                },
+                "cursor" => query.cursor = Some(TodoCursor::decode(&value)?),
+                _ => {}
+            }
+        }
+
+        Ok(query)
+    }
+}
+
+/// Where a page of todos ended, the sort keys the last todo of the page had
+/// when it was served. The next page starts after these values, so changing
+/// or deleting that todo in the meantime doesn't move the page boundary
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TodoCursor {
+    pub id: uuid::Uuid,
+    pub completed: bool,
+    pub position: i64,
+    pub priority: Priority,
+    pub due_at: Option<chrono::NaiveDateTime>,
+    pub created_at: chrono::NaiveDateTime,
+    pub updated_at: chrono::NaiveDateTime,
+    pub title: String,
+}
+
+impl TodoCursor {
+    /// Cursor of a page that ends with `todo`
+    pub fn after(todo: &Todo) -> Self {
+        Self {
+            id: todo.id,
+            completed: todo.completed,
+            position: todo.position,
+            priority: todo.priority,
+            due_at: todo.due_at,
+            created_at: todo.created_at,
+            updated_at: todo.updated_at,
+            title: todo.title.clone(),
+        }
+    }
+
+    /// The opaque `next_cursor` handed to clients
+    pub fn encode(&self) -> String {
+        base64::encode_config(
+            serde_json::to_vec(self).unwrap_or_default(),
+            base64::URL_SAFE_NO_PAD,
+        )
+    }
+
+    pub fn decode(value: &str) -> Result<Self, TodoApiError> {
+        base64::decode_config(value, base64::URL_SAFE_NO_PAD)
+            .ok()
+            .and_then(|json| serde_json::from_slice(&json).ok())
+            .ok_or_else(|| TodoApiError::BadRequest("Invalid cursor".into()))
+    }
+}
+
+/// Parses a timestamp query parameter, either a date and time
+/// like `2022-10-01T09:30:00` or a date alone for its midnight
+fn parse_timestamp(key: &str, value: &str) -> Result<chrono::NaiveDateTime, TodoApiError> {
+    value
+        .parse::<chrono::NaiveDateTime>()
+        .or_else(|_| {
+            value
+                .parse::<chrono::NaiveDate>()
+                .map(|date| date.and_hms_opt(0, 0, 0).unwrap())
+        })
+        .map_err(|_| {
+            TodoApiError::BadRequest(format!(
+                "Invalid {} {}, expected a date like 2022-10-01 or 2022-10-01T09:30:00",
+                key, value
+            ))
+        })
+}
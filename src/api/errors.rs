@@ -22,6 +22,10 @@ pub enum AuthError {
     InvalidAuthorizationHeader,
     TokenExpired,
     Unauthorized,
+    /// The session of the token was logged out or has expired
+    SessionEnded,
+    InvalidRefreshToken,
+    EmailNotVerified,
 }
 
 impl std::fmt::Display for AuthError {
@@ -35,6 +39,9 @@ impl std::fmt::Display for AuthError {
             Self::InvalidToken => write!(f, "Invalid JWT Token"),
             Self::TokenExpired => write!(f, "Token Expired"),
             Self::Unauthorized => write!(f, "Unauthorized"),
+            Self::SessionEnded => write!(f, "Session Ended"),
+            Self::InvalidRefreshToken => write!(f, "Invalid Refresh Token"),
+            Self::EmailNotVerified => write!(f, "Email Not Verified, check your mail"),
         }
     }
 }
@@ -134,3 +141,68 @@ impl From<DBError> for TodoApiError {
         }
     }
 }
+
+#[cfg(test)]
+mod errors_test {
+    use super::{AuthError, TodoApiError};
+    use actix_web::{body::MessageBody, http::StatusCode, ResponseError};
+    use diesel::result::{DatabaseErrorKind, Error as DBError};
+
+    #[test]
+    fn test_error_responses() {
+        let cases = vec![
+            (
+                TodoApiError::InternalServerError,
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ),
+            (
+                TodoApiError::BadRequest("Bad".into()),
+                StatusCode::BAD_REQUEST,
+            ),
+            (
+                TodoApiError::DatabaseConnectionError,
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ),
+            (
+                TodoApiError::AuthError(AuthError::TokenExpired),
+                StatusCode::UNAUTHORIZED,
+            ),
+            (TodoApiError::NotFound("Todo".into()), StatusCode::NOT_FOUND),
+        ];
+
+        for (error, status) in cases {
+            let response = error.error_response();
+
+            assert_eq!(response.status(), status);
+
+            let body = response.into_body().try_into_bytes().unwrap();
+
+            assert_eq!(
+                body,
+                serde_json::json!({ "error": error.to_string() }).to_string()
+            );
+        }
+    }
+
+    #[test]
+    fn test_error_conversions() {
+        let error = TodoApiError::from(uuid::Uuid::parse_str("not-a-uuid").unwrap_err());
+
+        assert_eq!(error.status_code(), StatusCode::BAD_REQUEST);
+
+        // Unique violations are mistakes of the client, other database errors are not
+        let error = TodoApiError::from(DBError::DatabaseError(
+            DatabaseErrorKind::UniqueViolation,
+            Box::new(String::from("duplicate key")),
+        ));
+
+        assert_eq!(
+            error.to_string(),
+            "BadRequest: DatabaseError: duplicate key"
+        );
+
+        let error = TodoApiError::from(DBError::NotFound);
+
+        assert_eq!(error.status_code(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
@@ -0,0 +1,117 @@
+//! Mails sent to the users, through SparkPost when `SPARKPOST_API_KEY` is set.
+//! Without it the mails are written to `MAIL_LOG`, or printed, for development
+
+use std::{io::Write, path::PathBuf, sync::Arc};
+
+use sparkpost::transmission::{Message, Transmission, TransmissionResponse};
+
+use super::errors::TodoApiError;
+
+lazy_static::lazy_static! {
+    /// Sender of the mails
+    pub static ref MAIL_FROM: String = std::env::var("MAIL_FROM").unwrap_or_else(|_| String::from("todo@localhost"));
+
+    /// Address of the server the links in the mails lead to
+    pub static ref PUBLIC_URL: String = std::env::var("PUBLIC_URL").unwrap_or_else(|_| {
+        format!(
+            "http://{}",
+            std::env::var("API_URL").unwrap_or_else(|_| String::from("localhost:9000"))
+        )
+    });
+}
+
+#[derive(Debug)]
+pub struct Mail {
+    pub to: String,
+    pub subject: String,
+    pub text: String,
+}
+
+/// Sends mails, blocks until the mail is handed over so call it from `web::block`
+pub trait Mailer: Send + Sync {
+    fn send(&self, mail: &Mail) -> Result<(), TodoApiError>;
+}
+
+pub struct SparkPostMailer {
+    api_key: String,
+    from: String,
+}
+
+impl SparkPostMailer {
+    pub fn new(api_key: String, from: String) -> Self {
+        Self { api_key, from }
+    }
+}
+
+impl Mailer for SparkPostMailer {
+    fn send(&self, mail: &Mail) -> Result<(), TodoApiError> {
+        // The transmission holds a blocking http client, which can't be
+        // created on the server's async threads, only where the mail is sent
+        let transmission = Transmission::new(self.api_key.as_str());
+
+        let mut message = Message::new(self.from.as_str());
+
+        message
+            .add_recipient(mail.to.as_str())
+            .subject(mail.subject.as_str())
+            .text(mail.text.as_str());
+
+        match transmission.send(&message) {
+            Ok(TransmissionResponse::ApiResponse(_)) => Ok(()),
+            Ok(TransmissionResponse::ApiError(errors)) => {
+                eprintln!("SparkPost rejected the mail: {:?}", errors);
+                Err(TodoApiError::InternalServerError)
+            }
+            Err(e) => {
+                eprintln!("Failed to reach SparkPost: {}", e);
+                Err(TodoApiError::InternalServerError)
+            }
+        }
+    }
+}
+
+/// Appends the mails to a file, or prints them without one
+pub struct LogMailer {
+    path: Option<PathBuf>,
+}
+
+impl LogMailer {
+    pub fn new(path: Option<PathBuf>) -> Self {
+        Self { path }
+    }
+}
+
+impl Mailer for LogMailer {
+    fn send(&self, mail: &Mail) -> Result<(), TodoApiError> {
+        let entry = format!(
+            "To: {}\nSubject: {}\n\n{}\n\n",
+            mail.to, mail.subject, mail.text
+        );
+
+        match &self.path {
+            Some(path) => std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .and_then(|mut file| file.write_all(entry.as_bytes()))
+                .map_err(|e| {
+                    eprintln!("Failed to write the mail to {}: {}", path.display(), e);
+                    TodoApiError::InternalServerError
+                }),
+            None => {
+                print!("{}", entry);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// The mailer configured by the environment
+pub fn from_env() -> Arc<dyn Mailer> {
+    match std::env::var("SPARKPOST_API_KEY") {
+        Ok(api_key) => Arc::new(SparkPostMailer::new(api_key, MAIL_FROM.clone())),
+        Err(_) => Arc::new(LogMailer::new(
+            std::env::var("MAIL_LOG").ok().map(PathBuf::from),
+        )),
+    }
+}
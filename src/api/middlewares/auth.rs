@@ -1,20 +1,24 @@
+use std::rc::Rc;
+
 use futures::{
-    future::{ok, ready, LocalBoxFuture, Ready},
+    future::{ok, LocalBoxFuture, Ready},
     FutureExt,
 };
 
 use actix_web::{
     body::EitherBody,
     dev::{Service, ServiceRequest, ServiceResponse, Transform},
-    FromRequest, HttpMessage,
+    web, FromRequest, HttpMessage,
 };
 use chrono::{serde::ts_seconds, DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
 use crate::{
     api::{
-        auth_utils::{decode_token, Claimable},
+        auth_utils::{decode_token, Claimable, ACCESS_TOKEN_MINUTES},
         errors::{AuthError, TodoApiError},
+        repository::Repository,
     },
     models::user_model::SlimUser,
 };
@@ -23,33 +27,22 @@ use crate::{
 pub struct Claims {
     pub email: String,
     pub id: String,
+    /// Id of the session the token was issued for
+    pub jti: String,
     #[serde(with = "ts_seconds")]
     pub exp: DateTime<Utc>,
 }
 
-impl<'a> Claimable<'a> for Claims {}
+impl Claimable for Claims {}
 
-impl From<SlimUser> for Claims {
-    fn from(user: SlimUser) -> Self {
-        use std::ops::Add;
+impl Claims {
+    /// Short lived claims of `user` for the session with `session_id`
+    pub fn new(user: &SlimUser, session_id: Uuid) -> Self {
         Claims {
-            email: user.email,
+            email: user.email.clone(),
             id: user.id.to_string(),
-            exp: Utc::now().add(chrono::Duration::days(1)),
-        }
-    }
-}
-
-impl From<&SlimUser> for Claims {
-    fn from(user: &SlimUser) -> Self {
-        use std::ops::Add;
-
-        let u = user.clone();
-
-        Claims {
-            email: u.email.clone(),
-            id: u.id.to_string(),
-            exp: Utc::now().add(chrono::Duration::days(1)),
+            jti: session_id.to_string(),
+            exp: Utc::now() + chrono::Duration::minutes(*ACCESS_TOKEN_MINUTES),
         }
     }
 }
@@ -58,15 +51,7 @@ impl From<&SlimUser> for Claims {
 pub struct DecodedUser {
     pub email: String,
     pub id: String,
-}
-
-impl From<Claims> for DecodedUser {
-    fn from(c: Claims) -> Self {
-        DecodedUser {
-            email: c.email,
-            id: c.id,
-        }
-    }
+    pub session_id: Uuid,
 }
 
 pub struct Authenticated(DecodedUser);
@@ -96,7 +81,7 @@ impl FromRequest for Authenticated {
 /// directly refer to `DecodedUser` when using `.` notation for `Authenticated`
 /// Example
 /// ```rust
-///  let a = Authenticated(DecodedUser{id: "".to_string(), email: String::new()});
+///  let a = Authenticated(DecodedUser{id: "".to_string(), email: String::new(), session_id: Uuid::nil()});
 ///
 ///  assert_eq!(a.email, String::new()); // refering to `DecodedUser` email directly
 /// ```
@@ -111,13 +96,13 @@ impl std::ops::Deref for Authenticated {
 pub struct BasicAuth;
 
 pub struct AuthMiddleware<S> {
-    service: S,
+    service: Rc<S>,
 }
 
 /// Implement `Transform` for Convert `BasicAuth` struct to `AuthMiddleware`
 impl<S, B> Transform<S, ServiceRequest> for BasicAuth
 where
-    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
     S::Future: 'static,
     B: 'static,
 {
@@ -131,14 +116,16 @@ where
     type Future = Ready<Result<Self::Transform, Self::InitError>>;
 
     fn new_transform(&self, service: S) -> Self::Future {
-        ok(AuthMiddleware { service })
+        ok(AuthMiddleware {
+            service: Rc::new(service),
+        })
     }
 }
 
 /// Implement Service
 impl<S, B> Service<ServiceRequest> for AuthMiddleware<S>
 where
-    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
     S::Future: 'static,
     B: 'static,
 {
@@ -151,35 +138,51 @@ where
     actix_web::dev::forward_ready!(service);
 
     fn call(&self, req: ServiceRequest) -> Self::Future {
-        let error: TodoApiError;
+        let service = Rc::clone(&self.service);
 
-        match req.headers().get("Authorization") {
-            Some(auth_header) => match decode_token::<Claims>(auth_header) {
-                Ok(token) => {
-                    {
-                        let mut extensions = req.extensions_mut();
-
-                        extensions.insert::<DecodedUser>(token.into());
-                    }
+        async move {
+            match authenticate(&req).await {
+                Ok(user) => {
+                    req.extensions_mut().insert::<DecodedUser>(user);
 
                     // Return to next middleware/handler on appending to extensions
-                    return Box::pin(
-                        self.service
-                            .call(req)
-                            .map(|res| res.map(|res| res.map_into_left_body())),
-                    );
-                }
-                Err(err) => {
-                    error = TodoApiError::AuthError(err);
+                    service.call(req).await.map(|res| res.map_into_left_body())
                 }
-            },
-            None => {
-                error = TodoApiError::AuthError(AuthError::NoAuthorizationHeader);
+                Err(error) => Ok(req.into_response(error.to_response().map_into_right_body())),
             }
         }
+        .boxed_local()
+    }
+}
 
-        return Box::pin(ready(Ok(
-            req.into_response(error.to_response().map_into_right_body())
-        )));
+/// Decodes the token of the request and checks that its session is still active,
+/// so tokens of logged out sessions are rejected before they expire
+async fn authenticate(req: &ServiceRequest) -> Result<DecodedUser, TodoApiError> {
+    let auth_header = req
+        .headers()
+        .get("Authorization")
+        .ok_or(TodoApiError::AuthError(AuthError::NoAuthorizationHeader))?;
+
+    let claims = decode_token::<Claims>(auth_header).map_err(TodoApiError::AuthError)?;
+
+    let session_id = Uuid::parse_str(&claims.jti)
+        .map_err(|_| TodoApiError::AuthError(AuthError::InvalidToken))?;
+
+    let repository = req
+        .app_data::<web::Data<dyn Repository>>()
+        .cloned()
+        .ok_or(TodoApiError::InternalServerError)?;
+
+    let session = web::block(move || repository.get_session(session_id))
+        .await
+        .map_err(|_| TodoApiError::InternalServerError)??;
+
+    match session {
+        Some(session) if session.is_active(chrono::Local::now().naive_local()) => Ok(DecodedUser {
+            email: claims.email,
+            id: claims.id,
+            session_id,
+        }),
+        _ => Err(TodoApiError::AuthError(AuthError::SessionEnded)),
     }
 }
@@ -1,7 +1,16 @@
 pub(crate) mod api;
 mod auth_handler;
 mod auth_utils;
-mod dtos;
+pub(crate) mod dtos;
 pub(crate) mod errors;
+mod jwt_keys;
+mod mailer;
 mod middlewares;
+mod projects_handler;
+mod repository;
+mod subtasks_handler;
+mod sync_handler;
+#[cfg(test)]
+mod test_utils;
 mod todos_handler;
+pub(crate) mod trash;
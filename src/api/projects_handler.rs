@@ -0,0 +1,236 @@
+use actix_web::{web, HttpResponse};
+use serde_json::json;
+
+use super::middlewares::auth::Authenticated;
+use super::repository::Repository;
+use crate::api::dtos::project::ProjectDTO;
+
+/// Api handler for getting all projects of a user
+pub async fn get_projects(
+    auth: Authenticated,
+    repository: web::Data<dyn Repository>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let list = web::block(move || repository.projects(&auth.id)).await??;
+
+    Ok(HttpResponse::Ok().json(json!({ "projects": list })))
+}
+
+/// Create a new project
+pub async fn create_project(
+    auth: Authenticated,
+    request_data: web::Json<ProjectDTO>,
+    repository: web::Data<dyn Repository>,
+) -> Result<HttpResponse, actix_web::Error> {
+    request_data.validate()?;
+
+    let inserted =
+        web::block(move || repository.insert_project(request_data.into_inner(), &auth.id))
+            .await??;
+
+    Ok(HttpResponse::Ok().json(&inserted))
+}
+
+/// Api handler for getting a single project
+pub async fn get_project(
+    auth: Authenticated,
+    project_id: web::Path<String>,
+    repository: web::Data<dyn Repository>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let project =
+        web::block(move || repository.get_project(project_id.as_str(), &auth.id)).await??;
+
+    Ok(HttpResponse::Ok().json(&project))
+}
+
+/// Rename a project
+pub async fn rename_project(
+    auth: Authenticated,
+    project_id: web::Path<String>,
+    request_data: web::Json<ProjectDTO>,
+    repository: web::Data<dyn Repository>,
+) -> Result<HttpResponse, actix_web::Error> {
+    request_data.validate()?;
+
+    let updated = web::block(move || {
+        repository.rename_project(project_id.as_str(), request_data.into_inner(), &auth.id)
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().json(&updated))
+}
+
+/// Delete a project, its todos are kept without a project
+pub async fn delete_project(
+    auth: Authenticated,
+    project_id: web::Path<String>,
+    repository: web::Data<dyn Repository>,
+) -> Result<HttpResponse, actix_web::Error> {
+    web::block(move || repository.remove_project(project_id.as_str(), &auth.id)).await??;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[cfg(test)]
+mod test {
+    use actix_web::{http::StatusCode, test};
+    use serde_json::{json, Value};
+
+    use crate::api::test_utils::{
+        authorized, signup_request, test_app, test_mailer, test_repositories,
+    };
+
+    #[actix_web::test]
+    async fn test_projects() {
+        for repository in test_repositories() {
+            let app = test::init_service(test_app(repository, test_mailer(None))).await;
+
+            let user: Value =
+                test::call_and_read_body_json(&app, signup_request().to_request()).await;
+            let token = user["token"].as_str().unwrap();
+
+            let project: Value = test::call_and_read_body_json(
+                &app,
+                authorized(test::TestRequest::post().uri("/api/projects"), token)
+                    .set_json(json!({ "name": "Home" }))
+                    .to_request(),
+            )
+            .await;
+            let project_url = format!("/api/projects/{}", project["id"].as_str().unwrap());
+
+            assert_eq!(project["name"], "Home");
+
+            let renamed: Value = test::call_and_read_body_json(
+                &app,
+                authorized(test::TestRequest::patch().uri(&project_url), token)
+                    .set_json(json!({ "name": "House" }))
+                    .to_request(),
+            )
+            .await;
+
+            assert_eq!(renamed["name"], "House");
+
+            for (title, project_id) in [
+                ("Paint the fence", project["id"].clone()),
+                ("Buy milk", Value::Null),
+            ] {
+                test::call_service(
+                    &app,
+                    authorized(test::TestRequest::post().uri("/api/todo"), token)
+                        .set_json(json!({ "title": title, "project_id": project_id }))
+                        .to_request(),
+                )
+                .await;
+            }
+
+            // Both the id and the name of a project select its todos
+            for project in [project["id"].as_str().unwrap(), "House"] {
+                let list: Value = test::call_and_read_body_json(
+                    &app,
+                    authorized(
+                        test::TestRequest::get().uri(&format!("/api/todo?project={}", project)),
+                        token,
+                    )
+                    .to_request(),
+                )
+                .await;
+
+                assert_eq!(list["todos"].as_array().unwrap().len(), 1);
+                assert_eq!(list["todos"][0]["title"], "Paint the fence");
+            }
+
+            let response = test::call_service(
+                &app,
+                authorized(test::TestRequest::delete().uri(&project_url), token).to_request(),
+            )
+            .await;
+
+            assert_eq!(response.status(), StatusCode::OK);
+
+            // The todos of a deleted project are kept without a project
+            let list: Value = test::call_and_read_body_json(
+                &app,
+                authorized(test::TestRequest::get().uri("/api/todo"), token).to_request(),
+            )
+            .await;
+
+            assert_eq!(list["todos"].as_array().unwrap().len(), 2);
+            assert!(list["todos"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .all(|todo| todo["project_id"].is_null()));
+
+            let projects: Value = test::call_and_read_body_json(
+                &app,
+                authorized(test::TestRequest::get().uri("/api/projects"), token).to_request(),
+            )
+            .await;
+
+            assert_eq!(projects["projects"], json!([]));
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_projects_of_other_users_are_not_found() {
+        for repository in test_repositories() {
+            let app = test::init_service(test_app(repository, test_mailer(None))).await;
+
+            let owner: Value =
+                test::call_and_read_body_json(&app, signup_request().to_request()).await;
+            let owner_token = owner["token"].as_str().unwrap();
+
+            let intruder: Value =
+                test::call_and_read_body_json(&app, signup_request().to_request()).await;
+            let intruder_token = intruder["token"].as_str().unwrap();
+
+            let project: Value = test::call_and_read_body_json(
+                &app,
+                authorized(test::TestRequest::post().uri("/api/projects"), owner_token)
+                    .set_json(json!({ "name": "Owned" }))
+                    .to_request(),
+            )
+            .await;
+            let project_url = format!("/api/projects/{}", project["id"].as_str().unwrap());
+
+            let foreign_requests = vec![
+                test::TestRequest::get().uri(&project_url),
+                test::TestRequest::patch()
+                    .uri(&project_url)
+                    .set_json(json!({ "name": "Taken over" })),
+                test::TestRequest::delete().uri(&project_url),
+                test::TestRequest::post()
+                    .uri("/api/todo")
+                    .set_json(json!({ "title": "Sneaked in", "project_id": project["id"] })),
+            ];
+
+            for request in foreign_requests {
+                let response =
+                    test::call_service(&app, authorized(request, intruder_token).to_request())
+                        .await;
+
+                assert_eq!(response.status(), StatusCode::NOT_FOUND);
+            }
+
+            let projects: Value = test::call_and_read_body_json(
+                &app,
+                authorized(
+                    test::TestRequest::get().uri("/api/projects"),
+                    intruder_token,
+                )
+                .to_request(),
+            )
+            .await;
+
+            assert_eq!(projects["projects"], json!([]));
+
+            // Nothing was changed by the other user
+            let project: Value = test::call_and_read_body_json(
+                &app,
+                authorized(test::TestRequest::get().uri(&project_url), owner_token).to_request(),
+            )
+            .await;
+
+            assert_eq!(project["name"], "Owned");
+        }
+    }
+}
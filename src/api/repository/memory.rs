@@ -0,0 +1,1434 @@
+//! Repository keeping everything in memory, for testing the api
+//! without a database. It behaves like the database backed ones,
+//! down to the errors, so the tests hold for those too
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+
+use chrono::NaiveDateTime;
+use uuid::Uuid;
+
+use super::{
+    bulk_result, ensure_subtask_found, move_anchor, new_todo, next_occurrence, position_next_to,
+    retagged, EmailVerificationRepository, PasswordResetRepository, ProjectRepository,
+    SessionRepository, SubtaskRepository, TodoRepository, UserRepository,
+};
+use crate::api::dtos::{
+    project::ProjectDTO,
+    subtask::{ReorderSubtasksDTO, SubtaskDTO},
+    todo::{
+        BulkDTO, BulkOperation, CreateTodoDTO, DueFilter, MoveTodoDTO, SearchQueryDTO, TagMatch,
+        TodoCursor, TodoListQuery, TodoSort, UpdateTodoDTO,
+    },
+};
+use crate::api::errors::TodoApiError;
+use crate::models::{
+    email_verification_model::EmailVerification,
+    password_reset_model::PasswordReset,
+    project_model::Project,
+    session_model::Session,
+    subtask_model::{Progress, Subtask},
+    todo_model::{
+        BulkResult, Priority, SearchHit, Todo, TodoChanges, TodoDelta, TodoItem, Tombstone,
+        HIGHLIGHT_END, HIGHLIGHT_START, POSITION_GAP,
+    },
+    user_model::User,
+};
+
+#[derive(Default)]
+pub struct MemoryRepository {
+    store: Mutex<Store>,
+}
+
+/// Everything a [`MemoryRepository`] holds, cloned to roll back failed bulk operations
+#[derive(Clone, Default)]
+struct Store {
+    users: Vec<User>,
+    sessions: Vec<Session>,
+    email_verifications: Vec<EmailVerification>,
+    password_resets: Vec<PasswordReset>,
+    todos: Vec<Todo>,
+    /// Tag names of each todo
+    tags: HashMap<Uuid, Vec<String>>,
+    projects: Vec<Project>,
+    subtasks: Vec<Subtask>,
+    /// Last value of the sync sequence, every write to a todo takes the next one
+    sync_seq: i64,
+    /// Value of the sync sequence each todo was last written at
+    written_at: HashMap<Uuid, i64>,
+    /// Todos deleted for good with their owner and when they were deleted in the sequence
+    tombstones: Vec<(Uuid, Tombstone, i64)>,
+}
+
+impl MemoryRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn store(&self) -> Result<MutexGuard<Store>, TodoApiError> {
+        self.store
+            .lock()
+            .map_err(|_| TodoApiError::InternalServerError)
+    }
+}
+
+impl UserRepository for MemoryRepository {
+    fn find_user_by_email(&self, email: &str) -> Result<Option<User>, TodoApiError> {
+        let store = self.store()?;
+
+        Ok(store.users.iter().find(|user| user.email == email).cloned())
+    }
+
+    fn find_user_by_id(&self, user_id: Uuid) -> Result<Option<User>, TodoApiError> {
+        let store = self.store()?;
+
+        Ok(store.users.iter().find(|user| user.id == user_id).cloned())
+    }
+
+    fn insert_user(&self, user: &User) -> Result<(), TodoApiError> {
+        let mut store = self.store()?;
+
+        if store.users.iter().any(|other| other.email == user.email) {
+            return Err(TodoApiError::BadRequest(
+                "DatabaseError: Key (email) already exists.".into(),
+            ));
+        }
+
+        store.users.push(user.clone());
+
+        Ok(())
+    }
+
+    fn update_password(&self, user_id: Uuid, password_hash: &str) -> Result<(), TodoApiError> {
+        let mut store = self.store()?;
+
+        for user in store.users.iter_mut().filter(|user| user.id == user_id) {
+            user.password = password_hash.to_string();
+            user.updated_at = chrono::Local::now().naive_local();
+        }
+
+        Ok(())
+    }
+}
+
+impl SessionRepository for MemoryRepository {
+    fn insert_session(&self, session: &Session) -> Result<(), TodoApiError> {
+        let mut store = self.store()?;
+
+        store.sessions.push(session.clone());
+
+        Ok(())
+    }
+
+    fn get_session(&self, session_id: Uuid) -> Result<Option<Session>, TodoApiError> {
+        let store = self.store()?;
+
+        Ok(store
+            .sessions
+            .iter()
+            .find(|session| session.id == session_id)
+            .cloned())
+    }
+
+    fn find_session_by_refresh_token(
+        &self,
+        refresh_token_hash: &str,
+    ) -> Result<Option<Session>, TodoApiError> {
+        let store = self.store()?;
+
+        Ok(store
+            .sessions
+            .iter()
+            .find(|session| session.refresh_token_hash == refresh_token_hash)
+            .cloned())
+    }
+
+    fn rotate_refresh_token(
+        &self,
+        session_id: Uuid,
+        old_hash: &str,
+        new_hash: &str,
+        expires_at: NaiveDateTime,
+    ) -> Result<bool, TodoApiError> {
+        let mut store = self.store()?;
+
+        let session = store.sessions.iter_mut().find(|session| {
+            session.id == session_id
+                && session.refresh_token_hash == old_hash
+                && session.revoked_at.is_none()
+        });
+
+        Ok(match session {
+            Some(session) => {
+                session.refresh_token_hash = new_hash.to_string();
+                session.expires_at = expires_at;
+
+                true
+            }
+            None => false,
+        })
+    }
+
+    fn revoke_session(&self, session_id: Uuid) -> Result<(), TodoApiError> {
+        let mut store = self.store()?;
+
+        for session in store
+            .sessions
+            .iter_mut()
+            .filter(|session| session.id == session_id && session.revoked_at.is_none())
+        {
+            session.revoked_at = Some(chrono::Local::now().naive_local());
+        }
+
+        Ok(())
+    }
+}
+
+impl EmailVerificationRepository for MemoryRepository {
+    fn insert_email_verification(
+        &self,
+        verification: &EmailVerification,
+        sent_after: NaiveDateTime,
+    ) -> Result<bool, TodoApiError> {
+        let mut store = self.store()?;
+
+        if store
+            .email_verifications
+            .iter()
+            .any(|other| other.user_id == verification.user_id && other.created_at > sent_after)
+        {
+            return Ok(false);
+        }
+
+        store.email_verifications.push(verification.clone());
+
+        Ok(true)
+    }
+
+    fn verify_email(&self, token_hash: &str, now: NaiveDateTime) -> Result<bool, TodoApiError> {
+        let mut store = self.store()?;
+
+        let user_id = match store.email_verifications.iter().find(|verification| {
+            verification.token_hash == token_hash && verification.expires_at > now
+        }) {
+            Some(verification) => verification.user_id,
+            None => return Ok(false),
+        };
+
+        for user in store
+            .users
+            .iter_mut()
+            .filter(|user| user.id == user_id && user.email_verified_at.is_none())
+        {
+            user.email_verified_at = Some(now);
+        }
+
+        store
+            .email_verifications
+            .retain(|verification| verification.user_id != user_id);
+
+        Ok(true)
+    }
+}
+
+impl PasswordResetRepository for MemoryRepository {
+    fn insert_password_reset(
+        &self,
+        reset: &PasswordReset,
+        sent_after: NaiveDateTime,
+    ) -> Result<bool, TodoApiError> {
+        let mut store = self.store()?;
+
+        if store
+            .password_resets
+            .iter()
+            .any(|other| other.user_id == reset.user_id && other.created_at > sent_after)
+        {
+            return Ok(false);
+        }
+
+        store.password_resets.push(reset.clone());
+
+        Ok(true)
+    }
+
+    fn reset_password(
+        &self,
+        token_hash: &str,
+        password_hash: &str,
+        now: NaiveDateTime,
+    ) -> Result<bool, TodoApiError> {
+        let mut store = self.store()?;
+
+        let user_id = match store
+            .password_resets
+            .iter()
+            .find(|reset| reset.token_hash == token_hash && reset.is_usable(now))
+        {
+            Some(reset) => reset.user_id,
+            None => return Ok(false),
+        };
+
+        for user in store.users.iter_mut().filter(|user| user.id == user_id) {
+            user.password = password_hash.to_string();
+            user.updated_at = now;
+        }
+
+        for reset in store
+            .password_resets
+            .iter_mut()
+            .filter(|reset| reset.user_id == user_id && reset.used_at.is_none())
+        {
+            reset.used_at = Some(now);
+        }
+
+        for session in store
+            .sessions
+            .iter_mut()
+            .filter(|session| session.user_id == user_id && session.revoked_at.is_none())
+        {
+            session.revoked_at = Some(now);
+        }
+
+        Ok(true)
+    }
+}
+
+impl TodoRepository for MemoryRepository {
+    fn insert_todo(
+        &self,
+        todo: CreateTodoDTO,
+        requester_id: &str,
+    ) -> Result<TodoItem, TodoApiError> {
+        let mut store = self.store()?;
+
+        if let Some(project) = todo.project_id {
+            store.owned_project(requester_id, project.to_string().as_str())?;
+        }
+
+        let mut new_todo = new_todo(&todo, Uuid::parse_str(requester_id)?);
+
+        // The databases refuse a second todo with the same id
+        if store.todos.iter().any(|other| other.id == new_todo.id) {
+            return Err(TodoApiError::BadRequest("Todo id is already taken".into()));
+        }
+
+        // New todos go to the top of the list
+        new_todo.position = store
+            .todos
+            .iter()
+            .filter(|other| other.user_id == new_todo.user_id)
+            .map(|other| other.position)
+            .min()
+            .map_or(0, |top| top - POSITION_GAP);
+
+        store.set_tags(new_todo.id, &todo.tags);
+        store.save_todo(new_todo.clone());
+
+        Ok(store.item(new_todo))
+    }
+
+    fn get_todo(&self, todo_id: &str, requester_id: &str) -> Result<TodoItem, TodoApiError> {
+        let store = self.store()?;
+
+        let todo = store.owned_todo(requester_id, todo_id)?.clone();
+
+        Ok(store.item(todo))
+    }
+
+    fn update_todo(
+        &self,
+        todo_id: &str,
+        update: UpdateTodoDTO,
+        requester_id: &str,
+    ) -> Result<TodoItem, TodoApiError> {
+        let mut store = self.store()?;
+
+        let changes = TodoChanges::from(&update);
+
+        if let Some(Some(project)) = changes.project_id {
+            store.owned_project(requester_id, project.to_string().as_str())?;
+        }
+
+        let mut updated = store.owned_todo(requester_id, todo_id)?.clone();
+
+        let was_complete = updated.completed;
+
+        changes.apply_to(&mut updated);
+
+        if let Some(tags) = &update.tags {
+            store.set_tags(updated.id, tags);
+        }
+
+        store.save_todo(updated.clone());
+
+        if updated.completed && !was_complete {
+            store.spawn_next_occurrence(&updated);
+        }
+
+        Ok(store.item(updated))
+    }
+
+    fn list_todos(
+        &self,
+        list_query: TodoListQuery,
+        requester_id: &str,
+    ) -> Result<(Vec<TodoItem>, Option<TodoCursor>), TodoApiError> {
+        let store = self.store()?;
+
+        let requester_id = Uuid::parse_str(requester_id)?;
+
+        // Projects can be referred to by id or by name
+        let project = match &list_query.project {
+            Some(project) => match Uuid::parse_str(project) {
+                Ok(project_id) => Some(Some(project_id)),
+                Err(_) => Some(
+                    store
+                        .projects
+                        .iter()
+                        .find(|other| other.user_id == requester_id && &other.name == project)
+                        .map(|found| found.id),
+                ),
+            },
+            None => None,
+        };
+
+        // A todo with the sort keys of the cursor, the page starts after it
+        let last_seen = list_query.cursor.as_ref().map(|cursor| Todo {
+            id: cursor.id,
+            completed: cursor.completed,
+            position: cursor.position,
+            priority: cursor.priority,
+            due_at: cursor.due_at,
+            created_at: cursor.created_at,
+            updated_at: cursor.updated_at,
+            ..Todo::from(cursor.title.clone(), requester_id)
+        });
+
+        let due_window = list_query
+            .due
+            .map(|due| due.window(chrono::Local::now().naive_local()));
+
+        let text = list_query.text.as_ref().map(|text| text.to_lowercase());
+
+        let mut todos_list: Vec<&Todo> = store
+            .todos
+            .iter()
+            .filter(|todo| todo.user_id == requester_id && todo.deleted_at.is_none())
+            .filter(|todo| {
+                project.is_none_or(|project| project.is_some() && todo.project_id == project)
+            })
+            .filter(|todo| {
+                let todo_tags = store.tags.get(&todo.id).map(Vec::as_slice).unwrap_or(&[]);
+
+                match list_query.tag_match {
+                    _ if list_query.tags.is_empty() => true,
+                    TagMatch::Any => list_query.tags.iter().any(|tag| todo_tags.contains(tag)),
+                    TagMatch::All => list_query.tags.iter().all(|tag| todo_tags.contains(tag)),
+                }
+            })
+            .filter(|todo| list_query.include_archived || todo.archived_at.is_none())
+            .filter(|todo| {
+                list_query
+                    .series
+                    .is_none_or(|series| todo.series_id == Some(series))
+            })
+            .filter(|todo| {
+                list_query
+                    .completed
+                    .is_none_or(|completed| todo.completed == completed)
+            })
+            .filter(|todo| {
+                list_query
+                    .created_before
+                    .is_none_or(|before| todo.created_at < before)
+            })
+            .filter(|todo| {
+                list_query
+                    .created_after
+                    .is_none_or(|after| todo.created_at > after)
+            })
+            .filter(|todo| {
+                list_query
+                    .updated_since
+                    .is_none_or(|since| todo.updated_at >= since)
+            })
+            .filter(|todo| {
+                text.as_ref().is_none_or(|text| {
+                    todo.title.to_lowercase().contains(text)
+                        || todo
+                            .notes
+                            .as_ref()
+                            .is_some_and(|notes| notes.to_lowercase().contains(text))
+                })
+            })
+            .filter(|todo| {
+                let (from, until) = match due_window {
+                    Some(window) => window,
+                    None => return true,
+                };
+
+                let overdue_done =
+                    matches!(list_query.due, Some(DueFilter::Overdue)) && todo.completed;
+
+                todo.due_at
+                    .is_some_and(|due| due < until && from.is_none_or(|from| due >= from))
+                    && !overdue_done
+            })
+            .filter(|todo| {
+                last_seen.as_ref().is_none_or(|last_seen| {
+                    compare_todos(list_query.sort, todo, last_seen) == Ordering::Greater
+                })
+            })
+            .collect();
+
+        todos_list.sort_by(|a, b| compare_todos(list_query.sort, a, b));
+
+        let next_cursor = if todos_list.len() as i64 > list_query.limit {
+            todos_list.truncate(list_query.limit as usize);
+            todos_list.last().copied().map(TodoCursor::after)
+        } else {
+            None
+        };
+
+        let items = todos_list
+            .into_iter()
+            .map(|todo| store.item(todo.clone()))
+            .collect();
+
+        Ok((items, next_cursor))
+    }
+
+    fn set_todo_completeness(
+        &self,
+        todo_id: &str,
+        is_complete: bool,
+        requester_id: &str,
+    ) -> Result<(), TodoApiError> {
+        let mut store = self.store()?;
+
+        store
+            .set_todo_completeness(todo_id, is_complete, requester_id)
+            .map(|_| ())
+    }
+
+    fn set_todo_priority(
+        &self,
+        todo_id: &str,
+        priority: Priority,
+        requester_id: &str,
+    ) -> Result<(), TodoApiError> {
+        let mut store = self.store()?;
+
+        let mut updated = store.owned_todo(requester_id, todo_id)?.clone();
+
+        updated.priority = priority;
+        updated.updated_at = chrono::Local::now().naive_local();
+
+        store.save_todo(updated);
+
+        Ok(())
+    }
+
+    fn move_todo(
+        &self,
+        todo_id: &str,
+        target: MoveTodoDTO,
+        requester_id: &str,
+    ) -> Result<TodoItem, TodoApiError> {
+        let mut store = self.store()?;
+
+        let (anchor_id, before) = move_anchor(&target)?;
+
+        let mut todo = store.owned_todo(requester_id, todo_id)?.clone();
+
+        if todo.id == anchor_id {
+            return Err(TodoApiError::BadRequest(
+                "A todo can not be moved next to itself".into(),
+            ));
+        }
+
+        let anchor_id = anchor_id.to_string();
+
+        let anchor = store.owned_todo(requester_id, &anchor_id)?.clone();
+
+        todo.position = match store.free_position_next_to(&todo, &anchor, before) {
+            Some(new_position) => new_position,
+            None => {
+                // Only when the gap is used up are all positions of the user rewritten
+                store.rebalance_positions(todo.user_id);
+
+                let anchor = store.owned_todo(requester_id, &anchor_id)?.clone();
+
+                store
+                    .free_position_next_to(&todo, &anchor, before)
+                    .ok_or_else(|| TodoApiError::BadRequest("Todo could not be moved".into()))?
+            }
+        };
+        todo.updated_at = chrono::Local::now().naive_local();
+
+        store.save_todo(todo.clone());
+
+        Ok(store.item(todo))
+    }
+
+    fn trash_todo(&self, todo_id: &str, requester_id: &str) -> Result<(), TodoApiError> {
+        let mut store = self.store()?;
+
+        store.trash_todo(todo_id, requester_id)
+    }
+
+    fn trashed_todos(&self, requester_id: &str) -> Result<Vec<TodoItem>, TodoApiError> {
+        let store = self.store()?;
+
+        let requester_id = Uuid::parse_str(requester_id)?;
+
+        let mut trashed: Vec<&Todo> = store
+            .todos
+            .iter()
+            .filter(|todo| todo.user_id == requester_id && todo.deleted_at.is_some())
+            .collect();
+
+        trashed.sort_by(|a, b| b.deleted_at.cmp(&a.deleted_at));
+
+        Ok(trashed
+            .into_iter()
+            .map(|todo| store.item(todo.clone()))
+            .collect())
+    }
+
+    fn restore_todo(&self, todo_id: &str, requester_id: &str) -> Result<TodoItem, TodoApiError> {
+        let mut store = self.store()?;
+
+        let todo_id = Uuid::parse_str(todo_id)?;
+        let requester_id = Uuid::parse_str(requester_id)?;
+
+        let mut restored = store
+            .todos
+            .iter()
+            .find(|todo| {
+                todo.id == todo_id && todo.user_id == requester_id && todo.deleted_at.is_some()
+            })
+            .cloned()
+            .ok_or_else(|| TodoApiError::NotFound("Todo".to_string()))?;
+
+        restored.deleted_at = None;
+        restored.updated_at = chrono::Local::now().naive_local();
+
+        store.save_todo(restored.clone());
+
+        Ok(store.item(restored))
+    }
+
+    fn archive_completed_todos(
+        &self,
+        older_than_days: u32,
+        requester_id: &str,
+    ) -> Result<usize, TodoApiError> {
+        let mut store = self.store()?;
+
+        let requester_id = Uuid::parse_str(requester_id)?;
+
+        let now = chrono::Local::now().naive_local();
+
+        let completed_before = now - chrono::Duration::days(i64::from(older_than_days));
+
+        let archived: Vec<Todo> = store
+            .todos
+            .iter()
+            .filter(|todo| {
+                todo.user_id == requester_id
+                    && todo.completed
+                    && todo.completed_at.is_some_and(|at| at <= completed_before)
+                    && todo.archived_at.is_none()
+                    && todo.deleted_at.is_none()
+            })
+            .cloned()
+            .collect();
+
+        let archived_count = archived.len();
+
+        for mut todo in archived {
+            todo.archived_at = Some(now);
+
+            store.save_todo(todo);
+        }
+
+        Ok(archived_count)
+    }
+
+    fn search_todos(
+        &self,
+        search: SearchQueryDTO,
+        requester_id: &str,
+    ) -> Result<Vec<SearchHit>, TodoApiError> {
+        let store = self.store()?;
+
+        let requester_id = Uuid::parse_str(requester_id)?;
+
+        let words: Vec<String> = search.words().map(str::to_lowercase).collect();
+
+        let mut hits: Vec<SearchHit> = store
+            .todos
+            .iter()
+            .filter(|todo| todo.user_id == requester_id && todo.deleted_at.is_none())
+            .filter_map(|todo| {
+                let notes = todo.notes.as_deref().unwrap_or_default();
+
+                let (highlighted_title, title_matches) = highlight(&todo.title, &words);
+                let (snippet, notes_matches) = highlight(notes, &words);
+
+                // Every word has to be found in the title or the notes
+                let found = words
+                    .iter()
+                    .all(|word| starts_any_word(&todo.title, word) || starts_any_word(notes, word));
+
+                found.then(|| SearchHit {
+                    todo: store.item(todo.clone()),
+                    // Title matches weigh more, as they do with the databases
+                    rank: title_matches as f32 + 0.4 * notes_matches as f32,
+                    highlighted_title,
+                    snippet: (notes_matches > 0).then(|| snippet),
+                })
+            })
+            .collect();
+
+        hits.sort_by(|a, b| {
+            b.rank
+                .partial_cmp(&a.rank)
+                .unwrap_or(Ordering::Equal)
+                .then_with(|| b.todo.updated_at.cmp(&a.todo.updated_at))
+        });
+
+        hits.truncate(search.limit as usize);
+
+        Ok(hits)
+    }
+
+    fn apply_bulk_operations(
+        &self,
+        bulk: BulkDTO,
+        requester_id: &str,
+    ) -> Result<Vec<BulkResult>, TodoApiError> {
+        let mut store = self.store()?;
+
+        let results = bulk
+            .operations
+            .iter()
+            .map(|operation| {
+                let before = store.clone();
+
+                let outcome = store.apply_bulk_operation(operation, requester_id);
+
+                // A failed operation leaves nothing behind
+                if outcome.is_err() {
+                    *store = before;
+                }
+
+                bulk_result(operation, outcome)
+            })
+            .collect();
+
+        Ok(results)
+    }
+
+    fn todo_changes(
+        &self,
+        since: Option<i64>,
+        requester_id: &str,
+    ) -> Result<TodoDelta, TodoApiError> {
+        let store = self.store()?;
+
+        let requester_id = Uuid::parse_str(requester_id)?;
+
+        let next_seq = store.sync_seq + 1;
+
+        let written_at = |todo: &Todo| store.written_at.get(&todo.id).copied().unwrap_or(0);
+
+        let mut todos_list: Vec<&Todo> = store
+            .todos
+            .iter()
+            .filter(|todo| todo.user_id == requester_id)
+            .filter(|todo| match since {
+                Some(since) => written_at(todo) >= since,
+                None => todo.deleted_at.is_none(),
+            })
+            .collect();
+
+        todos_list.sort_by_key(|todo| written_at(todo));
+
+        let (trashed, changed): (Vec<&Todo>, Vec<&Todo>) = todos_list
+            .into_iter()
+            .partition(|todo| todo.deleted_at.is_some());
+
+        let mut deleted: Vec<Tombstone> = trashed.into_iter().map(Tombstone::from).collect();
+
+        if let Some(since) = since {
+            // Todos purged from the trash are only left as tombstones
+            deleted.extend(
+                store
+                    .tombstones
+                    .iter()
+                    .filter(|(owner_id, _, seq)| *owner_id == requester_id && *seq >= since)
+                    .map(|(_, tombstone, _)| tombstone.clone()),
+            );
+        }
+
+        Ok(TodoDelta {
+            changed: changed
+                .into_iter()
+                .map(|todo| store.item(todo.clone()))
+                .collect(),
+            deleted,
+            next_token: next_seq.to_string(),
+        })
+    }
+
+    fn purge_trash(&self, expired_before: NaiveDateTime) -> Result<usize, TodoApiError> {
+        let mut store = self.store()?;
+
+        let (expired, kept): (Vec<Todo>, Vec<Todo>) = std::mem::take(&mut store.todos)
+            .into_iter()
+            .partition(|todo| {
+                todo.deleted_at
+                    .is_some_and(|deleted| deleted < expired_before)
+            });
+
+        store.todos = kept;
+
+        let now = chrono::Local::now().naive_local();
+
+        for todo in &expired {
+            store.tags.remove(&todo.id);
+            store.written_at.remove(&todo.id);
+            store.subtasks.retain(|subtask| subtask.todo_id != todo.id);
+
+            store.sync_seq += 1;
+
+            let seq = store.sync_seq;
+
+            store
+                .tombstones
+                .retain(|(_, tombstone, _)| tombstone.id != todo.id);
+            store.tombstones.push((
+                todo.user_id,
+                Tombstone {
+                    id: todo.id,
+                    deleted_at: now,
+                },
+                seq,
+            ));
+        }
+
+        Ok(expired.len())
+    }
+}
+
+impl ProjectRepository for MemoryRepository {
+    fn projects(&self, requester_id: &str) -> Result<Vec<Project>, TodoApiError> {
+        let store = self.store()?;
+
+        let requester_id = Uuid::parse_str(requester_id)?;
+
+        let mut projects_list: Vec<Project> = store
+            .projects
+            .iter()
+            .filter(|project| project.user_id == requester_id)
+            .cloned()
+            .collect();
+
+        projects_list.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(projects_list)
+    }
+
+    fn insert_project(
+        &self,
+        project: ProjectDTO,
+        requester_id: &str,
+    ) -> Result<Project, TodoApiError> {
+        let mut store = self.store()?;
+
+        let new_project = Project::from(
+            project.name.trim().to_string(),
+            Uuid::parse_str(requester_id)?,
+        );
+
+        store.ensure_project_name_free(&new_project)?;
+
+        store.projects.push(new_project.clone());
+
+        Ok(new_project)
+    }
+
+    fn get_project(&self, project_id: &str, requester_id: &str) -> Result<Project, TodoApiError> {
+        let store = self.store()?;
+
+        store.owned_project(requester_id, project_id).cloned()
+    }
+
+    fn rename_project(
+        &self,
+        project_id: &str,
+        project: ProjectDTO,
+        requester_id: &str,
+    ) -> Result<Project, TodoApiError> {
+        let mut store = self.store()?;
+
+        let mut renamed = store.owned_project(requester_id, project_id)?.clone();
+
+        renamed.name = project.name.trim().to_string();
+        renamed.updated_at = chrono::Local::now().naive_local();
+
+        store.ensure_project_name_free(&renamed)?;
+
+        for stored in store
+            .projects
+            .iter_mut()
+            .filter(|other| other.id == renamed.id)
+        {
+            *stored = renamed.clone();
+        }
+
+        Ok(renamed)
+    }
+
+    fn remove_project(&self, project_id: &str, requester_id: &str) -> Result<(), TodoApiError> {
+        let mut store = self.store()?;
+
+        let removed = store.owned_project(requester_id, project_id)?.id;
+
+        store.projects.retain(|project| project.id != removed);
+
+        let orphans: Vec<Todo> = store
+            .todos
+            .iter()
+            .filter(|todo| todo.project_id == Some(removed))
+            .cloned()
+            .collect();
+
+        for mut todo in orphans {
+            todo.project_id = None;
+
+            store.save_todo(todo);
+        }
+
+        Ok(())
+    }
+}
+
+impl SubtaskRepository for MemoryRepository {
+    fn subtasks(&self, todo_id: &str, requester_id: &str) -> Result<Vec<Subtask>, TodoApiError> {
+        let store = self.store()?;
+
+        let todo_id = store.owned_todo(requester_id, todo_id)?.id;
+
+        Ok(store.subtasks_of(todo_id))
+    }
+
+    fn insert_subtask(
+        &self,
+        todo_id: &str,
+        subtask: SubtaskDTO,
+        requester_id: &str,
+    ) -> Result<Subtask, TodoApiError> {
+        let mut store = self.store()?;
+
+        let todo_id = store.owned_todo(requester_id, todo_id)?.id;
+
+        let last_position = store
+            .subtasks_of(todo_id)
+            .last()
+            .map(|subtask| subtask.position);
+
+        let new_subtask = Subtask::from(
+            subtask.title.trim().to_string(),
+            todo_id,
+            last_position.map_or(0, |position| position + 1),
+        );
+
+        store.subtasks.push(new_subtask.clone());
+        store.touch(todo_id);
+
+        Ok(new_subtask)
+    }
+
+    fn reorder_subtasks(
+        &self,
+        todo_id: &str,
+        order: ReorderSubtasksDTO,
+        requester_id: &str,
+    ) -> Result<Vec<Subtask>, TodoApiError> {
+        let mut store = self.store()?;
+
+        let todo_id = store.owned_todo(requester_id, todo_id)?.id;
+
+        let mut current_ids: Vec<Uuid> = store
+            .subtasks_of(todo_id)
+            .into_iter()
+            .map(|subtask| subtask.id)
+            .collect();
+
+        let mut requested_ids = order.ids.clone();
+
+        current_ids.sort();
+        requested_ids.sort();
+
+        if current_ids != requested_ids {
+            return Err(TodoApiError::BadRequest(
+                "Every subtask of the todo has to be listed".into(),
+            ));
+        }
+
+        let now = chrono::Local::now().naive_local();
+
+        for subtask in store.subtasks.iter_mut() {
+            if let Some(index) = order.ids.iter().position(|id| *id == subtask.id) {
+                subtask.position = index as i32;
+                subtask.updated_at = now;
+            }
+        }
+
+        store.touch(todo_id);
+
+        Ok(store.subtasks_of(todo_id))
+    }
+
+    fn set_subtask_completeness(
+        &self,
+        todo_id: &str,
+        subtask_id: &str,
+        is_complete: bool,
+        requester_id: &str,
+    ) -> Result<(), TodoApiError> {
+        let mut store = self.store()?;
+
+        let todo_id = store.owned_todo(requester_id, todo_id)?.id;
+
+        let subtask_id = Uuid::parse_str(subtask_id)?;
+
+        let now = chrono::Local::now().naive_local();
+
+        let mut update_count = 0;
+
+        for subtask in store
+            .subtasks
+            .iter_mut()
+            .filter(|subtask| subtask.id == subtask_id && subtask.todo_id == todo_id)
+        {
+            subtask.completed = is_complete;
+            subtask.updated_at = now;
+
+            update_count += 1;
+        }
+
+        ensure_subtask_found(update_count)?;
+
+        store.touch(todo_id);
+
+        Ok(())
+    }
+
+    fn remove_subtask(
+        &self,
+        todo_id: &str,
+        subtask_id: &str,
+        requester_id: &str,
+    ) -> Result<(), TodoApiError> {
+        let mut store = self.store()?;
+
+        let todo_id = store.owned_todo(requester_id, todo_id)?.id;
+
+        let subtask_id = Uuid::parse_str(subtask_id)?;
+
+        let count_before = store.subtasks.len();
+
+        store
+            .subtasks
+            .retain(|subtask| !(subtask.id == subtask_id && subtask.todo_id == todo_id));
+
+        ensure_subtask_found(count_before - store.subtasks.len())?;
+
+        store.touch(todo_id);
+
+        Ok(())
+    }
+}
+
+impl Store {
+    /// The todo with `todo_id` owned by `requester_id`, todos in the trash are not found
+    fn owned_todo(&self, requester_id: &str, todo_id: &str) -> Result<&Todo, TodoApiError> {
+        let todo_id = Uuid::parse_str(todo_id)?;
+
+        let requester_id = Uuid::parse_str(requester_id)?;
+
+        self.todos
+            .iter()
+            .find(|todo| {
+                todo.id == todo_id && todo.user_id == requester_id && todo.deleted_at.is_none()
+            })
+            .ok_or_else(|| TodoApiError::NotFound("Todo".to_string()))
+    }
+
+    /// The project with `project_id` owned by `requester_id`
+    fn owned_project(
+        &self,
+        requester_id: &str,
+        project_id: &str,
+    ) -> Result<&Project, TodoApiError> {
+        let project_id = Uuid::parse_str(project_id)?;
+
+        let requester_id = Uuid::parse_str(requester_id)?;
+
+        self.projects
+            .iter()
+            .find(|project| project.id == project_id && project.user_id == requester_id)
+            .ok_or_else(|| TodoApiError::NotFound("Project".to_string()))
+    }
+
+    /// Project names are unique per user, like the unique index of the databases
+    fn ensure_project_name_free(&self, project: &Project) -> Result<(), TodoApiError> {
+        let taken = self.projects.iter().any(|other| {
+            other.id != project.id && other.user_id == project.user_id && other.name == project.name
+        });
+
+        if taken {
+            return Err(TodoApiError::BadRequest(
+                "DatabaseError: Key (user_id, name) already exists.".into(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Take the next value of the sync sequence for a todo
+    fn touch(&mut self, todo_id: Uuid) {
+        self.sync_seq += 1;
+
+        self.written_at.insert(todo_id, self.sync_seq);
+    }
+
+    /// Store a new or changed todo
+    fn save_todo(&mut self, todo: Todo) {
+        self.touch(todo.id);
+
+        match self.todos.iter_mut().find(|stored| stored.id == todo.id) {
+            Some(stored) => *stored = todo,
+            None => self.todos.push(todo),
+        }
+    }
+
+    /// Replace the tags of a todo with `names`
+    fn set_tags(&mut self, todo_id: Uuid, names: &[String]) {
+        let mut names = names.to_vec();
+
+        names.sort();
+        names.dedup();
+
+        self.tags.insert(todo_id, names);
+    }
+
+    /// Subtasks of a todo in checklist order
+    fn subtasks_of(&self, todo_id: Uuid) -> Vec<Subtask> {
+        let mut list: Vec<Subtask> = self
+            .subtasks
+            .iter()
+            .filter(|subtask| subtask.todo_id == todo_id)
+            .cloned()
+            .collect();
+
+        list.sort_by_key(|subtask| subtask.position);
+
+        list
+    }
+
+    /// Attach the tags and subtask progress to a todo
+    fn item(&self, todo: Todo) -> TodoItem {
+        let mut progress = Progress::default();
+
+        for subtask in self
+            .subtasks
+            .iter()
+            .filter(|subtask| subtask.todo_id == todo.id)
+        {
+            progress.total += 1;
+
+            if subtask.completed {
+                progress.done += 1;
+            }
+        }
+
+        TodoItem {
+            tags: self.tags.get(&todo.id).cloned().unwrap_or_default(),
+            progress,
+            todo,
+        }
+    }
+
+    fn trash_todo(&mut self, todo_id: &str, requester_id: &str) -> Result<(), TodoApiError> {
+        let mut trashed = self.owned_todo(requester_id, todo_id)?.clone();
+
+        trashed.deleted_at = Some(chrono::Local::now().naive_local());
+
+        self.save_todo(trashed);
+
+        Ok(())
+    }
+
+    fn set_todo_completeness(
+        &mut self,
+        todo_id: &str,
+        is_complete: bool,
+        requester_id: &str,
+    ) -> Result<Todo, TodoApiError> {
+        let mut updated = self.owned_todo(requester_id, todo_id)?.clone();
+
+        let was_complete = updated.completed;
+
+        let now = chrono::Local::now().naive_local();
+
+        updated.set_completed(is_complete, now);
+        updated.updated_at = now;
+
+        // Reopened todos can't stay hidden in the archive
+        if !is_complete {
+            updated.archived_at = None;
+        }
+
+        self.save_todo(updated.clone());
+
+        if updated.completed && !was_complete {
+            self.spawn_next_occurrence(&updated);
+        }
+
+        Ok(updated)
+    }
+
+    /// Create the todo that follows `completed` in its recurring series,
+    /// a series only ever has one open todo
+    fn spawn_next_occurrence(&mut self, completed: &Todo) {
+        let recurrence = match &completed.recurrence {
+            Some(recurrence) => recurrence,
+            None => return,
+        };
+
+        // Todos that were made recurring after they were created start their series here
+        let series = match completed.series_id {
+            Some(series) => series,
+            None => {
+                let mut first = completed.clone();
+                first.series_id = Some(completed.id);
+
+                self.save_todo(first);
+
+                completed.id
+            }
+        };
+
+        let open = self.todos.iter().any(|todo| {
+            todo.series_id == Some(series) && !todo.completed && todo.deleted_at.is_none()
+        });
+
+        if open {
+            return;
+        }
+
+        let next = next_occurrence(completed, recurrence, series);
+
+        let tags = self.tags.get(&completed.id).cloned().unwrap_or_default();
+
+        self.set_tags(next.id, &tags);
+
+        for subtask in self.subtasks_of(completed.id) {
+            self.subtasks
+                .push(Subtask::from(subtask.title, next.id, subtask.position));
+        }
+
+        self.save_todo(next);
+    }
+
+    /// Position between `anchor` and its neighbour on the side `todo` is moved to,
+    /// `None` when there is no room left between them
+    fn free_position_next_to(&self, todo: &Todo, anchor: &Todo, before: bool) -> Option<i64> {
+        let others = || {
+            self.todos
+                .iter()
+                .filter(move |other| other.user_id == anchor.user_id && other.id != todo.id)
+        };
+
+        // Todos sharing a position can't be told apart, so they need rebalancing
+        if others().any(|other| other.id != anchor.id && other.position == anchor.position) {
+            return None;
+        }
+
+        let neighbour = if before {
+            others()
+                .map(|other| other.position)
+                .filter(|position| *position < anchor.position)
+                .max()
+        } else {
+            others()
+                .map(|other| other.position)
+                .filter(|position| *position > anchor.position)
+                .min()
+        };
+
+        position_next_to(anchor.position, neighbour, before)
+    }
+
+    /// Space the positions of all todos of a user evenly, keeping their order
+    fn rebalance_positions(&mut self, owner_id: Uuid) {
+        let mut owned: Vec<Todo> = self
+            .todos
+            .iter()
+            .filter(|todo| todo.user_id == owner_id)
+            .cloned()
+            .collect();
+
+        owned.sort_by(|a, b| {
+            a.position
+                .cmp(&b.position)
+                .then_with(|| b.created_at.cmp(&a.created_at))
+        });
+
+        for (index, mut todo) in owned.into_iter().enumerate() {
+            todo.position = (index as i64 + 1) * POSITION_GAP;
+
+            self.save_todo(todo);
+        }
+    }
+
+    /// Apply a single operation of a bulk request,
+    /// returns the todo as it is afterwards unless it was deleted
+    fn apply_bulk_operation(
+        &mut self,
+        operation: &BulkOperation,
+        requester_id: &str,
+    ) -> Result<Option<TodoItem>, TodoApiError> {
+        let todo_id = operation.todo_id().to_string();
+
+        let updated = match operation {
+            BulkOperation::Complete { .. } => {
+                self.set_todo_completeness(&todo_id, true, requester_id)?
+            }
+            BulkOperation::Incomplete { .. } => {
+                self.set_todo_completeness(&todo_id, false, requester_id)?
+            }
+            BulkOperation::Delete { .. } => {
+                self.trash_todo(&todo_id, requester_id)?;
+
+                return Ok(None);
+            }
+            BulkOperation::Retag { add, remove, .. } => {
+                let mut todo = self.owned_todo(requester_id, &todo_id)?.clone();
+
+                let names = self.tags.get(&todo.id).cloned().unwrap_or_default();
+
+                self.set_tags(todo.id, &retagged(names, add, remove));
+
+                todo.updated_at = chrono::Local::now().naive_local();
+
+                self.save_todo(todo.clone());
+
+                todo
+            }
+            BulkOperation::Move { project_id, .. } => {
+                if let Some(project) = project_id {
+                    self.owned_project(requester_id, project.to_string().as_str())?;
+                }
+
+                let mut todo = self.owned_todo(requester_id, &todo_id)?.clone();
+
+                todo.project_id = *project_id;
+                todo.updated_at = chrono::Local::now().naive_local();
+
+                self.save_todo(todo.clone());
+
+                todo
+            }
+        };
+
+        Ok(Some(self.item(updated)))
+    }
+}
+
+/// Order of two todos in a list sorted by `sort`, the same order the databases return
+fn compare_todos(sort: TodoSort, a: &Todo, b: &Todo) -> Ordering {
+    // Todos without a due date come last
+    let due = |a: &Todo, b: &Todo| match (a.due_at, b.due_at) {
+        (Some(a), Some(b)) => a.cmp(&b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    };
+
+    let by_sort = match sort {
+        TodoSort::Manual => a
+            .position
+            .cmp(&b.position)
+            .then_with(|| b.created_at.cmp(&a.created_at)),
+        TodoSort::Priority => b
+            .priority
+            .cmp(&a.priority)
+            .then_with(|| due(a, b))
+            .then_with(|| b.created_at.cmp(&a.created_at)),
+        TodoSort::Created => b.created_at.cmp(&a.created_at),
+        TodoSort::Updated => b.updated_at.cmp(&a.updated_at),
+        TodoSort::Due => due(a, b),
+        TodoSort::Title => a.title.cmp(&b.title),
+    };
+
+    a.completed
+        .cmp(&b.completed)
+        .then(by_sort)
+        .then_with(|| a.id.cmp(&b.id))
+}
+
+/// Whether a word of `text` starts with `prefix`, which is lowercase
+fn starts_any_word(text: &str, prefix: &str) -> bool {
+    text.split(|c: char| !c.is_alphanumeric())
+        .any(|word| word.to_lowercase().starts_with(prefix))
+}
+
+/// `text` with the words starting with one of `words` between the highlight markers,
+/// along with how many words were highlighted
+fn highlight(text: &str, words: &[String]) -> (String, usize) {
+    let mut highlighted = String::with_capacity(text.len());
+    let mut matches = 0;
+    let mut rest = text;
+
+    while !rest.is_empty() {
+        let word_len = rest
+            .find(|c: char| !c.is_alphanumeric())
+            .unwrap_or(rest.len());
+
+        if word_len == 0 {
+            let separator_len = rest.chars().next().map_or(1, char::len_utf8);
+
+            highlighted.push_str(&rest[..separator_len]);
+            rest = &rest[separator_len..];
+
+            continue;
+        }
+
+        let word = &rest[..word_len];
+
+        if words
+            .iter()
+            .any(|prefix| word.to_lowercase().starts_with(prefix.as_str()))
+        {
+            highlighted.push(HIGHLIGHT_START);
+            highlighted.push_str(word);
+            highlighted.push(HIGHLIGHT_END);
+
+            matches += 1;
+        } else {
+            highlighted.push_str(word);
+        }
+
+        rest = &rest[word_len..];
+    }
+
+    (highlighted, matches)
+}
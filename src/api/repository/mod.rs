@@ -0,0 +1,439 @@
+use std::{collections::HashMap, sync::Arc};
+
+use chrono::NaiveDateTime;
+use uuid::Uuid;
+
+use super::dtos::{
+    project::ProjectDTO,
+    subtask::{ReorderSubtasksDTO, SubtaskDTO},
+    todo::{
+        normalize_notes, BulkDTO, BulkOperation, CreateTodoDTO, MoveTodoDTO, SearchQueryDTO,
+        TodoCursor, TodoListQuery, UpdateTodoDTO,
+    },
+};
+use super::errors::TodoApiError;
+use crate::models::{
+    email_verification_model::EmailVerification,
+    password_reset_model::PasswordReset,
+    project_model::Project,
+    session_model::Session,
+    subtask_model::Subtask,
+    todo_model::{
+        BulkResult, Priority, Recurrence, SearchHit, Todo, TodoDelta, TodoItem, POSITION_GAP,
+    },
+    user_model::User,
+};
+
+#[cfg(test)]
+mod memory;
+mod postgres;
+mod sqlite;
+
+#[cfg(test)]
+pub use memory::MemoryRepository;
+pub use postgres::PgRepository;
+pub use sqlite::SqliteRepository;
+
+/// Accounts of the users, found by email when logging in
+pub trait UserRepository {
+    fn find_user_by_email(&self, email: &str) -> Result<Option<User>, TodoApiError>;
+
+    fn find_user_by_id(&self, user_id: Uuid) -> Result<Option<User>, TodoApiError>;
+
+    /// Emails are unique, a taken one is a bad request
+    fn insert_user(&self, user: &User) -> Result<(), TodoApiError>;
+
+    /// Replace the password hash of a user, keeping their sessions
+    fn update_password(&self, user_id: Uuid, password_hash: &str) -> Result<(), TodoApiError>;
+}
+
+/// Logins of the users, found by the hash of their refresh token
+pub trait SessionRepository {
+    fn insert_session(&self, session: &Session) -> Result<(), TodoApiError>;
+
+    fn get_session(&self, session_id: Uuid) -> Result<Option<Session>, TodoApiError>;
+
+    /// The session a refresh token belongs to, whether it is still active or not
+    fn find_session_by_refresh_token(
+        &self,
+        refresh_token_hash: &str,
+    ) -> Result<Option<Session>, TodoApiError>;
+
+    /// Replace the refresh token of an active session, returns `false` when
+    /// `old_hash` isn't its refresh token anymore, as when it was used already
+    fn rotate_refresh_token(
+        &self,
+        session_id: Uuid,
+        old_hash: &str,
+        new_hash: &str,
+        expires_at: NaiveDateTime,
+    ) -> Result<bool, TodoApiError>;
+
+    /// End a session, none of its tokens are accepted afterwards
+    fn revoke_session(&self, session_id: Uuid) -> Result<(), TodoApiError>;
+}
+
+/// Verification mails sent to the users, found by the hash of their token
+pub trait EmailVerificationRepository {
+    /// Keep the verification unless the user got another one after `sent_after`,
+    /// returns `false` then, so that no one gets flooded with verification mails
+    fn insert_email_verification(
+        &self,
+        verification: &EmailVerification,
+        sent_after: NaiveDateTime,
+    ) -> Result<bool, TodoApiError>;
+
+    /// Mark the email the token with `token_hash` was sent to as verified, as long
+    /// as the token hasn't expired at `now`. The tokens of the user are dropped with
+    /// it, returns `false` when there is no such token
+    fn verify_email(&self, token_hash: &str, now: NaiveDateTime) -> Result<bool, TodoApiError>;
+}
+
+/// Password resets requested by the users, found by the hash of their token
+pub trait PasswordResetRepository {
+    /// Keep the reset unless the user got another one after `sent_after`,
+    /// returns `false` then, like [`EmailVerificationRepository::insert_email_verification`]
+    fn insert_password_reset(
+        &self,
+        reset: &PasswordReset,
+        sent_after: NaiveDateTime,
+    ) -> Result<bool, TodoApiError>;
+
+    /// Set the password of the user the reset token with `token_hash` was sent to,
+    /// as long as the token is still usable at `now`. All the reset tokens and
+    /// sessions of the user end with it, returns `false` when the token is unusable
+    fn reset_password(
+        &self,
+        token_hash: &str,
+        password_hash: &str,
+        now: NaiveDateTime,
+    ) -> Result<bool, TodoApiError>;
+}
+
+/// Todos of the users. Every method working on a single todo only finds
+/// the todos of the requester that aren't in the trash, any other todo is
+/// reported as not found
+pub trait TodoRepository {
+    fn insert_todo(
+        &self,
+        todo: CreateTodoDTO,
+        requester_id: &str,
+    ) -> Result<TodoItem, TodoApiError>;
+
+    fn get_todo(&self, todo_id: &str, requester_id: &str) -> Result<TodoItem, TodoApiError>;
+
+    /// Apply the requested changes, only the fields sent are changed
+    fn update_todo(
+        &self,
+        todo_id: &str,
+        update: UpdateTodoDTO,
+        requester_id: &str,
+    ) -> Result<TodoItem, TodoApiError>;
+
+    /// A page of todos along with the cursor of the next page, if there is one
+    fn list_todos(
+        &self,
+        list_query: TodoListQuery,
+        requester_id: &str,
+    ) -> Result<(Vec<TodoItem>, Option<TodoCursor>), TodoApiError>;
+
+    /// Complete or reopen a todo, completing a recurring todo
+    /// creates its next occurrence
+    fn set_todo_completeness(
+        &self,
+        todo_id: &str,
+        is_complete: bool,
+        requester_id: &str,
+    ) -> Result<(), TodoApiError>;
+
+    fn set_todo_priority(
+        &self,
+        todo_id: &str,
+        priority: Priority,
+        requester_id: &str,
+    ) -> Result<(), TodoApiError>;
+
+    /// Give a todo a position right before or after another todo
+    fn move_todo(
+        &self,
+        todo_id: &str,
+        target: MoveTodoDTO,
+        requester_id: &str,
+    ) -> Result<TodoItem, TodoApiError>;
+
+    /// Move a todo to the trash, it is only deleted for good by [`Self::purge_trash`]
+    fn trash_todo(&self, todo_id: &str, requester_id: &str) -> Result<(), TodoApiError>;
+
+    /// The todos in the trash, last deleted first
+    fn trashed_todos(&self, requester_id: &str) -> Result<Vec<TodoItem>, TodoApiError>;
+
+    fn restore_todo(&self, todo_id: &str, requester_id: &str) -> Result<TodoItem, TodoApiError>;
+
+    /// Archive the completed todos that were completed at least
+    /// `older_than_days` ago, by their `completed_at`
+    fn archive_completed_todos(
+        &self,
+        older_than_days: u32,
+        requester_id: &str,
+    ) -> Result<usize, TodoApiError>;
+
+    /// Todos matching the search, best matches first. Completed and
+    /// archived todos are searched too, deleted ones are not
+    fn search_todos(
+        &self,
+        search: SearchQueryDTO,
+        requester_id: &str,
+    ) -> Result<Vec<SearchHit>, TodoApiError>;
+
+    /// Apply the operations of a bulk request in one transaction, one that
+    /// fails is rolled back and reported without undoing the others
+    fn apply_bulk_operations(
+        &self,
+        bulk: BulkDTO,
+        requester_id: &str,
+    ) -> Result<Vec<BulkResult>, TodoApiError>;
+
+    /// Changes to the todos since a sync token, every todo without one
+    fn todo_changes(
+        &self,
+        since: Option<i64>,
+        requester_id: &str,
+    ) -> Result<TodoDelta, TodoApiError>;
+
+    /// Delete the todos of every user that were moved to the trash
+    /// before `expired_before`, returns how many were deleted
+    fn purge_trash(&self, expired_before: NaiveDateTime) -> Result<usize, TodoApiError>;
+}
+
+/// Projects of the users, names are unique per user
+pub trait ProjectRepository {
+    /// Projects sorted by name
+    fn projects(&self, requester_id: &str) -> Result<Vec<Project>, TodoApiError>;
+
+    fn insert_project(
+        &self,
+        project: ProjectDTO,
+        requester_id: &str,
+    ) -> Result<Project, TodoApiError>;
+
+    fn get_project(&self, project_id: &str, requester_id: &str) -> Result<Project, TodoApiError>;
+
+    fn rename_project(
+        &self,
+        project_id: &str,
+        project: ProjectDTO,
+        requester_id: &str,
+    ) -> Result<Project, TodoApiError>;
+
+    /// The todos of the project are kept without a project
+    fn remove_project(&self, project_id: &str, requester_id: &str) -> Result<(), TodoApiError>;
+}
+
+/// Checklists of the todos, only reachable through a todo of the requester
+pub trait SubtaskRepository {
+    /// Subtasks in checklist order
+    fn subtasks(&self, todo_id: &str, requester_id: &str) -> Result<Vec<Subtask>, TodoApiError>;
+
+    /// Add a subtask at the end of the checklist
+    fn insert_subtask(
+        &self,
+        todo_id: &str,
+        subtask: SubtaskDTO,
+        requester_id: &str,
+    ) -> Result<Subtask, TodoApiError>;
+
+    /// Give the subtasks the positions they have in `order.ids`
+    fn reorder_subtasks(
+        &self,
+        todo_id: &str,
+        order: ReorderSubtasksDTO,
+        requester_id: &str,
+    ) -> Result<Vec<Subtask>, TodoApiError>;
+
+    fn set_subtask_completeness(
+        &self,
+        todo_id: &str,
+        subtask_id: &str,
+        is_complete: bool,
+        requester_id: &str,
+    ) -> Result<(), TodoApiError>;
+
+    fn remove_subtask(
+        &self,
+        todo_id: &str,
+        subtask_id: &str,
+        requester_id: &str,
+    ) -> Result<(), TodoApiError>;
+}
+
+/// Everything the api stores, handlers get it as `web::Data<dyn Repository>`
+pub trait Repository:
+    UserRepository
+    + SessionRepository
+    + EmailVerificationRepository
+    + PasswordResetRepository
+    + TodoRepository
+    + ProjectRepository
+    + SubtaskRepository
+    + Send
+    + Sync
+{
+}
+
+impl<T> Repository for T where
+    T: UserRepository
+        + SessionRepository
+        + EmailVerificationRepository
+        + PasswordResetRepository
+        + TodoRepository
+        + ProjectRepository
+        + SubtaskRepository
+        + Send
+        + Sync
+{
+}
+
+/// Repository for `database_url`, `sqlite://path/to/todo.db` opens an SQLite
+/// database file, created when it doesn't exist, anything else is Postgres
+pub fn connect(database_url: &str) -> Result<Arc<dyn Repository>, TodoApiError> {
+    match database_url.strip_prefix("sqlite://") {
+        Some(path) => Ok(Arc::new(SqliteRepository::open(path)?)),
+        None => Ok(Arc::new(PgRepository::connect(database_url)?)),
+    }
+}
+
+/// Todo created from a create request, the caller gives it a position
+fn new_todo(todo: &CreateTodoDTO, owner_id: Uuid) -> Todo {
+    let mut new_todo = Todo::from(todo.title.trim().to_string(), owner_id);
+    new_todo.id = todo.id.unwrap_or(new_todo.id);
+    new_todo.due_at = todo.due_at;
+    new_todo.priority = todo.priority;
+    new_todo.project_id = todo.project_id;
+    new_todo.notes = todo.notes.as_deref().and_then(normalize_notes);
+
+    if todo.recurrence.is_some() {
+        new_todo.series_id = Some(new_todo.id);
+        new_todo.recurrence = todo.recurrence.clone();
+    }
+
+    new_todo
+}
+
+/// Todo that follows `completed` in the recurring series `series`
+fn next_occurrence(completed: &Todo, recurrence: &Recurrence, series: Uuid) -> Todo {
+    let mut next = Todo::from(completed.title.clone(), completed.user_id);
+    next.due_at = Some(recurrence.next_due(completed.due_at, completed.updated_at));
+    next.priority = completed.priority;
+    next.project_id = completed.project_id;
+    next.recurrence = Some(recurrence.clone());
+    next.series_id = Some(series);
+    next.notes = completed.notes.clone();
+    // The next occurrence takes the place of the completed one
+    next.position = completed.position;
+
+    next
+}
+
+/// Id of the todo to move next to, and whether to move before it
+fn move_anchor(target: &MoveTodoDTO) -> Result<(Uuid, bool), TodoApiError> {
+    match (target.before, target.after) {
+        (Some(anchor_id), None) => Ok((anchor_id, true)),
+        (None, Some(anchor_id)) => Ok((anchor_id, false)),
+        _ => Err(TodoApiError::BadRequest(
+            "Either before or after has to be given".into(),
+        )),
+    }
+}
+
+/// Position between the anchor and its `neighbour` on the side a todo is
+/// moved to, `None` when there is no room left between them
+fn position_next_to(anchor_position: i64, neighbour: Option<i64>, before: bool) -> Option<i64> {
+    match neighbour {
+        None if before => Some(anchor_position - POSITION_GAP),
+        None => Some(anchor_position + POSITION_GAP),
+        Some(neighbour) if (neighbour - anchor_position).abs() > 1 => {
+            Some(anchor_position + (neighbour - anchor_position) / 2)
+        }
+        Some(_) => None,
+    }
+}
+
+/// Tag names of a todo after a retag operation
+fn retagged(mut names: Vec<String>, add: &[String], remove: &[String]) -> Vec<String> {
+    names.retain(|name| !remove.contains(name));
+
+    for name in add {
+        if !names.contains(name) {
+            names.push(name.clone());
+        }
+    }
+
+    names
+}
+
+/// Report of one operation of a bulk request
+fn bulk_result(
+    operation: &BulkOperation,
+    outcome: Result<Option<TodoItem>, TodoApiError>,
+) -> BulkResult {
+    match outcome {
+        Ok(todo) => BulkResult {
+            id: operation.todo_id(),
+            ok: true,
+            error: None,
+            todo,
+        },
+        Err(e) => BulkResult {
+            id: operation.todo_id(),
+            ok: false,
+            error: Some(e.to_string()),
+            todo: None,
+        },
+    }
+}
+
+/// `LIKE` pattern for todos containing `text`,
+/// `%` and `_` typed by the user are matched literally
+fn contains_pattern(text: &str) -> String {
+    format!(
+        "%{}%",
+        text.replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_")
+    )
+}
+
+/// Search match as found by a search query, the todo itself is loaded afterwards
+struct SearchMatch {
+    id: Uuid,
+    rank: f32,
+    highlighted_title: String,
+    snippet: Option<String>,
+}
+
+/// Pair the matches of a search with their todos, keeping the order of the matches
+fn search_hits(matches: Vec<SearchMatch>, items: Vec<TodoItem>) -> Vec<SearchHit> {
+    let mut items: HashMap<Uuid, TodoItem> =
+        items.into_iter().map(|item| (item.id, item)).collect();
+
+    matches
+        .into_iter()
+        .filter_map(|found| {
+            items.remove(&found.id).map(|item| SearchHit {
+                todo: item,
+                rank: found.rank,
+                highlighted_title: found.highlighted_title,
+                snippet: found.snippet,
+            })
+        })
+        .collect()
+}
+
+/// Maps the number of subtasks touched by a scoped write to a result
+fn ensure_subtask_found(affected_rows: usize) -> Result<(), TodoApiError> {
+    if affected_rows == 0 {
+        return Err(TodoApiError::NotFound("Subtask".to_string()));
+    }
+
+    Ok(())
+}
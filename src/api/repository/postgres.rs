@@ -0,0 +1,1448 @@
+use chrono::NaiveDateTime;
+use diesel::dsl::{sql, Filter, IsNull};
+use diesel::pg::Pg;
+use diesel::prelude::*;
+use diesel::r2d2::ConnectionManager;
+use diesel::sql_types::{BigInt, Bool};
+use uuid::Uuid;
+
+use super::{
+    bulk_result, contains_pattern, ensure_subtask_found, move_anchor, new_todo, next_occurrence,
+    position_next_to, retagged, search_hits, EmailVerificationRepository, PasswordResetRepository,
+    ProjectRepository, SearchMatch, SessionRepository, SubtaskRepository, TodoRepository,
+    UserRepository,
+};
+use crate::api::auth_utils::ensure_todo_found;
+use crate::api::dtos::{
+    project::ProjectDTO,
+    subtask::{ReorderSubtasksDTO, SubtaskDTO},
+    todo::{
+        BulkDTO, BulkOperation, CreateTodoDTO, DueFilter, MoveTodoDTO, SearchQueryDTO, TagMatch,
+        TodoCursor, TodoListQuery, TodoSort, UpdateTodoDTO,
+    },
+};
+use crate::api::errors::TodoApiError;
+use crate::models::{
+    email_verification_model::EmailVerification,
+    password_reset_model::PasswordReset,
+    project_model::Project,
+    session_model::Session,
+    subtask_model::{copy_subtasks, progress_for_todos, Progress, Subtask},
+    tag_model::{set_todo_tags, tags_for_todos},
+    todo_model::{
+        BulkResult, Priority, SearchHit, Todo, TodoChanges, TodoDelta, TodoItem, Tombstone,
+        HIGHLIGHT_END, HIGHLIGHT_START, POSITION_GAP,
+    },
+    user_model::User,
+    Pool,
+};
+
+/// Repository storing everything in Postgres, the schema
+/// is set up by the migrations in `migrations/`
+pub struct PgRepository {
+    pool: Pool,
+}
+
+impl PgRepository {
+    pub fn new(pool: Pool) -> Self {
+        Self { pool }
+    }
+
+    pub fn connect(database_url: &str) -> Result<Self, TodoApiError> {
+        let manager = ConnectionManager::<PgConnection>::new(database_url);
+
+        Ok(Self::new(r2d2::Pool::builder().build(manager)?))
+    }
+}
+
+impl UserRepository for PgRepository {
+    fn find_user_by_email(&self, user_email: &str) -> Result<Option<User>, TodoApiError> {
+        use crate::schema::users::dsl::*;
+        let conn = &self.pool.get()?;
+
+        let user = users
+            .filter(email.eq(user_email))
+            .first::<User>(conn)
+            .optional()?;
+
+        Ok(user)
+    }
+
+    fn find_user_by_id(&self, user_id: Uuid) -> Result<Option<User>, TodoApiError> {
+        use crate::schema::users::dsl::*;
+        let conn = &self.pool.get()?;
+
+        let user = users.find(user_id).first::<User>(conn).optional()?;
+
+        Ok(user)
+    }
+
+    fn insert_user(&self, user: &User) -> Result<(), TodoApiError> {
+        use crate::schema::users::dsl::*;
+        let conn = &self.pool.get()?;
+
+        diesel::insert_into(users).values(user).execute(conn)?;
+
+        Ok(())
+    }
+
+    fn update_password(&self, user_id: Uuid, password_hash: &str) -> Result<(), TodoApiError> {
+        use crate::schema::users::dsl::*;
+        let conn = &self.pool.get()?;
+
+        diesel::update(users.find(user_id))
+            .set((
+                password.eq(password_hash),
+                updated_at.eq(chrono::Local::now().naive_local()),
+            ))
+            .execute(conn)?;
+
+        Ok(())
+    }
+}
+
+impl SessionRepository for PgRepository {
+    fn insert_session(&self, session: &Session) -> Result<(), TodoApiError> {
+        use crate::schema::sessions::dsl::*;
+        let conn = &self.pool.get()?;
+
+        diesel::insert_into(sessions)
+            .values(session)
+            .execute(conn)?;
+
+        Ok(())
+    }
+
+    fn get_session(&self, session_id: Uuid) -> Result<Option<Session>, TodoApiError> {
+        use crate::schema::sessions::dsl::*;
+        let conn = &self.pool.get()?;
+
+        let session = sessions
+            .find(session_id)
+            .first::<Session>(conn)
+            .optional()?;
+
+        Ok(session)
+    }
+
+    fn find_session_by_refresh_token(
+        &self,
+        token_hash: &str,
+    ) -> Result<Option<Session>, TodoApiError> {
+        use crate::schema::sessions::dsl::*;
+        let conn = &self.pool.get()?;
+
+        let session = sessions
+            .filter(refresh_token_hash.eq(token_hash))
+            .first::<Session>(conn)
+            .optional()?;
+
+        Ok(session)
+    }
+
+    fn rotate_refresh_token(
+        &self,
+        session_id: Uuid,
+        old_hash: &str,
+        new_hash: &str,
+        new_expires_at: NaiveDateTime,
+    ) -> Result<bool, TodoApiError> {
+        use crate::schema::sessions::dsl::*;
+        let conn = &self.pool.get()?;
+
+        // Checking the old hash in the update itself lets only one of two
+        // refreshes racing with the same token through
+        let update_count = diesel::update(
+            sessions
+                .filter(id.eq(session_id))
+                .filter(refresh_token_hash.eq(old_hash))
+                .filter(revoked_at.is_null()),
+        )
+        .set((
+            refresh_token_hash.eq(new_hash),
+            expires_at.eq(new_expires_at),
+        ))
+        .execute(conn)?;
+
+        Ok(update_count > 0)
+    }
+
+    fn revoke_session(&self, session_id: Uuid) -> Result<(), TodoApiError> {
+        use crate::schema::sessions::dsl::*;
+        let conn = &self.pool.get()?;
+
+        diesel::update(
+            sessions
+                .filter(id.eq(session_id))
+                .filter(revoked_at.is_null()),
+        )
+        .set(revoked_at.eq(chrono::Local::now().naive_local()))
+        .execute(conn)?;
+
+        Ok(())
+    }
+}
+
+impl EmailVerificationRepository for PgRepository {
+    fn insert_email_verification(
+        &self,
+        verification: &EmailVerification,
+        sent_after: NaiveDateTime,
+    ) -> Result<bool, TodoApiError> {
+        use crate::schema::email_verifications::dsl::*;
+        let conn = &self.pool.get()?;
+
+        conn.transaction(|| {
+            let recent = diesel::select(diesel::dsl::exists(
+                email_verifications
+                    .filter(user_id.eq(verification.user_id))
+                    .filter(created_at.gt(sent_after)),
+            ))
+            .get_result::<bool>(conn)?;
+
+            if recent {
+                return Ok(false);
+            }
+
+            diesel::insert_into(email_verifications)
+                .values(verification)
+                .execute(conn)?;
+
+            Ok(true)
+        })
+    }
+
+    fn verify_email(
+        &self,
+        verification_token_hash: &str,
+        now: NaiveDateTime,
+    ) -> Result<bool, TodoApiError> {
+        use crate::schema::{email_verifications, users};
+        let conn = &self.pool.get()?;
+
+        conn.transaction(|| {
+            let verification = email_verifications::table
+                .filter(email_verifications::token_hash.eq(verification_token_hash))
+                .filter(email_verifications::expires_at.gt(now))
+                .first::<EmailVerification>(conn)
+                .optional()?;
+
+            let verification = match verification {
+                Some(verification) => verification,
+                None => return Ok(false),
+            };
+
+            diesel::update(
+                users::table
+                    .find(verification.user_id)
+                    .filter(users::email_verified_at.is_null()),
+            )
+            .set(users::email_verified_at.eq(now))
+            .execute(conn)?;
+
+            diesel::delete(
+                email_verifications::table
+                    .filter(email_verifications::user_id.eq(verification.user_id)),
+            )
+            .execute(conn)?;
+
+            Ok(true)
+        })
+    }
+}
+
+impl PasswordResetRepository for PgRepository {
+    fn insert_password_reset(
+        &self,
+        reset: &PasswordReset,
+        sent_after: NaiveDateTime,
+    ) -> Result<bool, TodoApiError> {
+        use crate::schema::password_resets::dsl::*;
+        let conn = &self.pool.get()?;
+
+        conn.transaction(|| {
+            let recent = diesel::select(diesel::dsl::exists(
+                password_resets
+                    .filter(user_id.eq(reset.user_id))
+                    .filter(created_at.gt(sent_after)),
+            ))
+            .get_result::<bool>(conn)?;
+
+            if recent {
+                return Ok(false);
+            }
+
+            diesel::insert_into(password_resets)
+                .values(reset)
+                .execute(conn)?;
+
+            Ok(true)
+        })
+    }
+
+    fn reset_password(
+        &self,
+        reset_token_hash: &str,
+        password_hash: &str,
+        now: NaiveDateTime,
+    ) -> Result<bool, TodoApiError> {
+        use crate::schema::{password_resets, sessions, users};
+        let conn = &self.pool.get()?;
+
+        conn.transaction(|| {
+            let reset = password_resets::table
+                .filter(password_resets::token_hash.eq(reset_token_hash))
+                .for_update()
+                .first::<PasswordReset>(conn)
+                .optional()?;
+
+            let reset = match reset {
+                Some(reset) if reset.is_usable(now) => reset,
+                _ => return Ok(false),
+            };
+
+            diesel::update(users::table.find(reset.user_id))
+                .set((users::password.eq(password_hash), users::updated_at.eq(now)))
+                .execute(conn)?;
+
+            diesel::update(
+                password_resets::table
+                    .filter(password_resets::user_id.eq(reset.user_id))
+                    .filter(password_resets::used_at.is_null()),
+            )
+            .set(password_resets::used_at.eq(now))
+            .execute(conn)?;
+
+            // Whoever knew the old password is logged out
+            diesel::update(
+                sessions::table
+                    .filter(sessions::user_id.eq(reset.user_id))
+                    .filter(sessions::revoked_at.is_null()),
+            )
+            .set(sessions::revoked_at.eq(now))
+            .execute(conn)?;
+
+            Ok(true)
+        })
+    }
+}
+
+impl TodoRepository for PgRepository {
+    fn insert_todo(
+        &self,
+        todo: CreateTodoDTO,
+        requester_id: &str,
+    ) -> Result<TodoItem, TodoApiError> {
+        use crate::schema::todos::dsl::*;
+        let conn = &self.pool.get()?;
+
+        if let Some(project) = todo.project_id {
+            verify_project_owner(conn, requester_id, project.to_string().as_str())?;
+        }
+
+        let requester_id = Uuid::parse_str(requester_id)?;
+
+        let mut new_todo = new_todo(&todo, requester_id);
+
+        conn.transaction(|| {
+            // New todos go to the top of the list
+            new_todo.position = todos
+                .filter(user_id.eq(requester_id))
+                .select(diesel::dsl::min(position))
+                .first::<Option<i64>>(conn)?
+                .map_or(0, |top| top - POSITION_GAP);
+
+            let inserted: Todo = diesel::insert_into(todos)
+                .values(&new_todo)
+                .get_result(conn)?;
+
+            set_todo_tags(conn, requester_id, inserted.id, &todo.tags)?;
+
+            Ok(TodoItem {
+                todo: inserted,
+                tags: todo.tags,
+                progress: Progress::default(),
+            })
+        })
+    }
+
+    fn get_todo(&self, todo_id: &str, requester_id: &str) -> Result<TodoItem, TodoApiError> {
+        let conn = &self.pool.get()?;
+
+        let todo = verify_todo_owner(conn, requester_id, todo_id)?;
+
+        Ok(into_items(conn, vec![todo])?.remove(0))
+    }
+
+    fn update_todo(
+        &self,
+        todo_id: &str,
+        update: UpdateTodoDTO,
+        requester_id: &str,
+    ) -> Result<TodoItem, TodoApiError> {
+        let conn = &self.pool.get()?;
+
+        let changes = TodoChanges::from(&update);
+
+        if let Some(Some(project)) = changes.project_id {
+            verify_project_owner(conn, requester_id, project.to_string().as_str())?;
+        }
+
+        conn.transaction(|| {
+            let previous = verify_todo_owner(conn, requester_id, todo_id)?;
+
+            let completed_at = previous.completed_at_after(
+                changes.completed.unwrap_or(previous.completed),
+                changes.updated_at,
+            );
+
+            let updated = diesel::update(owned_todo(requester_id, todo_id)?)
+                .set((
+                    &changes,
+                    crate::schema::todos::completed_at.eq(completed_at),
+                ))
+                .get_result::<Todo>(conn)?;
+
+            if let Some(tags) = &update.tags {
+                set_todo_tags(conn, updated.user_id, updated.id, tags)?;
+            }
+
+            if updated.completed && !previous.completed {
+                spawn_next_occurrence(conn, &updated)?;
+            }
+
+            Ok(into_items(conn, vec![updated])?.remove(0))
+        })
+    }
+
+    fn list_todos(
+        &self,
+        list_query: TodoListQuery,
+        requester_id: &str,
+    ) -> Result<(Vec<TodoItem>, Option<TodoCursor>), TodoApiError> {
+        use crate::schema::todos::dsl::*;
+        let conn = &self.pool.get()?;
+
+        let requester_id = Uuid::parse_str(requester_id)?;
+
+        let mut query = todos
+            .filter(user_id.eq(requester_id))
+            .filter(deleted_at.is_null())
+            .into_boxed();
+
+        if let Some(project) = list_query.project {
+            use crate::schema::projects;
+
+            // Projects can be referred to by id or by name
+            query = match Uuid::parse_str(project.as_str()) {
+                Ok(project) => query.filter(project_id.eq(project)),
+                Err(_) => query.filter(
+                    project_id.eq_any(
+                        projects::table
+                            .select(projects::id.nullable())
+                            .filter(projects::user_id.eq(requester_id))
+                            .filter(projects::name.eq(project)),
+                    ),
+                ),
+            };
+        }
+
+        if !list_query.tags.is_empty() {
+            use crate::schema::{tags, todo_tags};
+
+            // Ids of the requester's todos tagged with any of `names`
+            let tagged_with = |names: Vec<String>| {
+                todo_tags::table
+                    .inner_join(tags::table)
+                    .filter(tags::user_id.eq(requester_id))
+                    .filter(tags::name.eq_any(names))
+                    .select(todo_tags::todo_id)
+            };
+
+            match list_query.tag_match {
+                TagMatch::Any => query = query.filter(id.eq_any(tagged_with(list_query.tags))),
+                TagMatch::All => {
+                    for tag in list_query.tags {
+                        query = query.filter(id.eq_any(tagged_with(vec![tag])));
+                    }
+                }
+            }
+        }
+
+        if !list_query.include_archived {
+            query = query.filter(archived_at.is_null());
+        }
+
+        if let Some(series) = list_query.series {
+            query = query.filter(series_id.eq(series));
+        }
+
+        if let Some(is_completed) = list_query.completed {
+            query = query.filter(completed.eq(is_completed));
+        }
+
+        if let Some(before) = list_query.created_before {
+            query = query.filter(created_at.lt(before));
+        }
+
+        if let Some(after) = list_query.created_after {
+            query = query.filter(created_at.gt(after));
+        }
+
+        if let Some(since) = list_query.updated_since {
+            query = query.filter(updated_at.ge(since));
+        }
+
+        if let Some(text) = list_query.text {
+            let pattern = contains_pattern(&text);
+
+            query = query.filter(title.ilike(pattern.clone()).or(notes.ilike(pattern)));
+        }
+
+        if let Some(cursor) = &list_query.cursor {
+            query = query.filter(after_cursor(list_query.sort, cursor));
+        }
+
+        if let Some(due) = list_query.due {
+            let (from, until) = due.window(chrono::Local::now().naive_local());
+
+            query = query.filter(due_at.lt(until));
+
+            if let Some(from) = from {
+                query = query.filter(due_at.ge(from));
+            }
+
+            if let DueFilter::Overdue = due {
+                query = query.filter(completed.eq(false));
+            }
+        }
+
+        // Ties are broken by id so that pages never overlap,
+        // the keys have to match the ones of `after_cursor`
+        query = match list_query.sort {
+            TodoSort::Manual => {
+                query.order((completed.asc(), position.asc(), created_at.desc(), id.asc()))
+            }
+            // Open todos first, most important and soonest due at the top
+            TodoSort::Priority => query.order((
+                completed.asc(),
+                priority.desc(),
+                due_at.asc(),
+                created_at.desc(),
+                id.asc(),
+            )),
+            TodoSort::Created => query.order((completed.asc(), created_at.desc(), id.asc())),
+            TodoSort::Updated => query.order((completed.asc(), updated_at.desc(), id.asc())),
+            // Postgres sorts nulls last in ascending order
+            TodoSort::Due => query.order((completed.asc(), due_at.asc(), id.asc())),
+            TodoSort::Title => query.order((completed.asc(), title.asc(), id.asc())),
+        };
+
+        // One more than asked for tells if there is a next page
+        let mut todos_list = query.limit(list_query.limit + 1).load::<Todo>(conn)?;
+
+        let next_cursor = if todos_list.len() as i64 > list_query.limit {
+            todos_list.truncate(list_query.limit as usize);
+            todos_list.last().map(TodoCursor::after)
+        } else {
+            None
+        };
+
+        Ok((into_items(conn, todos_list)?, next_cursor))
+    }
+
+    fn set_todo_completeness(
+        &self,
+        todo_id: &str,
+        is_complete: bool,
+        requester_id: &str,
+    ) -> Result<(), TodoApiError> {
+        let conn = &self.pool.get()?;
+
+        set_todo_completeness(conn, todo_id, is_complete, requester_id).map(|_| ())
+    }
+
+    fn set_todo_priority(
+        &self,
+        todo_id: &str,
+        new_priority: Priority,
+        requester_id: &str,
+    ) -> Result<(), TodoApiError> {
+        use crate::schema::todos::dsl::*;
+        let conn = &self.pool.get()?;
+
+        let update_count = diesel::update(owned_todo(requester_id, todo_id)?)
+            .set((
+                priority.eq(new_priority),
+                updated_at.eq(chrono::Local::now().naive_local()),
+            ))
+            .execute(conn)?;
+
+        ensure_todo_found(update_count)
+    }
+
+    fn move_todo(
+        &self,
+        todo_id: &str,
+        target: MoveTodoDTO,
+        requester_id: &str,
+    ) -> Result<TodoItem, TodoApiError> {
+        use crate::schema::todos::dsl::*;
+        let conn = &self.pool.get()?;
+
+        let (anchor_id, before) = move_anchor(&target)?;
+
+        conn.transaction(|| {
+            let todo = verify_todo_owner(conn, requester_id, todo_id)?;
+
+            if todo.id == anchor_id {
+                return Err(TodoApiError::BadRequest(
+                    "A todo can not be moved next to itself".into(),
+                ));
+            }
+
+            let anchor = verify_todo_owner(conn, requester_id, anchor_id.to_string().as_str())?;
+
+            let new_position = match free_position_next_to(conn, &todo, &anchor, before)? {
+                Some(new_position) => new_position,
+                None => {
+                    // Only when the gap is used up are all positions of the user rewritten
+                    rebalance_positions(conn, todo.user_id)?;
+
+                    let anchor =
+                        verify_todo_owner(conn, requester_id, anchor_id.to_string().as_str())?;
+
+                    free_position_next_to(conn, &todo, &anchor, before)?
+                        .ok_or_else(|| TodoApiError::BadRequest("Todo could not be moved".into()))?
+                }
+            };
+
+            let moved = diesel::update(todos.filter(id.eq(todo.id)))
+                .set((
+                    position.eq(new_position),
+                    updated_at.eq(chrono::Local::now().naive_local()),
+                ))
+                .get_result::<Todo>(conn)?;
+
+            Ok(into_items(conn, vec![moved])?.remove(0))
+        })
+    }
+
+    fn trash_todo(&self, todo_id: &str, requester_id: &str) -> Result<(), TodoApiError> {
+        let conn = &self.pool.get()?;
+
+        trash_todo(conn, todo_id, requester_id)
+    }
+
+    fn trashed_todos(&self, requester_id: &str) -> Result<Vec<TodoItem>, TodoApiError> {
+        use crate::schema::todos::dsl::*;
+        let conn = &self.pool.get()?;
+
+        let todos_list = todos
+            .filter(user_id.eq(Uuid::parse_str(requester_id)?))
+            .filter(deleted_at.is_not_null())
+            .order(deleted_at.desc())
+            .load::<Todo>(conn)?;
+
+        into_items(conn, todos_list)
+    }
+
+    fn restore_todo(&self, todo_id: &str, requester_id: &str) -> Result<TodoItem, TodoApiError> {
+        use crate::schema::todos::dsl::*;
+        let conn = &self.pool.get()?;
+
+        let restored = diesel::update(
+            todos
+                .filter(id.eq(Uuid::parse_str(todo_id)?))
+                .filter(user_id.eq(Uuid::parse_str(requester_id)?))
+                .filter(deleted_at.is_not_null()),
+        )
+        .set((
+            deleted_at.eq(None::<NaiveDateTime>),
+            updated_at.eq(chrono::Local::now().naive_local()),
+        ))
+        .get_result::<Todo>(conn)
+        .optional()?
+        .ok_or_else(|| TodoApiError::NotFound("Todo".to_string()))?;
+
+        Ok(into_items(conn, vec![restored])?.remove(0))
+    }
+
+    fn archive_completed_todos(
+        &self,
+        older_than_days: u32,
+        requester_id: &str,
+    ) -> Result<usize, TodoApiError> {
+        use crate::schema::todos::dsl::*;
+        let conn = &self.pool.get()?;
+
+        let now = chrono::Local::now().naive_local();
+
+        let completed_before = now - chrono::Duration::days(i64::from(older_than_days));
+
+        let archived_count = diesel::update(
+            todos
+                .filter(user_id.eq(Uuid::parse_str(requester_id)?))
+                .filter(completed.eq(true))
+                .filter(completed_at.le(completed_before))
+                .filter(archived_at.is_null())
+                .filter(deleted_at.is_null()),
+        )
+        .set(archived_at.eq(now))
+        .execute(conn)?;
+
+        Ok(archived_count)
+    }
+
+    fn search_todos(
+        &self,
+        search: SearchQueryDTO,
+        requester_id: &str,
+    ) -> Result<Vec<SearchHit>, TodoApiError> {
+        use crate::schema::todos::dsl::*;
+        let conn = &self.pool.get()?;
+
+        // `search_vector` isn't in the schema, it is generated by Postgres
+        let rows = diesel::sql_query(
+            "SELECT id, \
+                ts_rank(search_vector, query) AS rank, \
+                ts_headline('english', title, query, $4) AS highlighted_title, \
+                CASE WHEN to_tsvector('english', coalesce(notes, '')) @@ query \
+                    THEN ts_headline('english', notes, query, $5) \
+                END AS snippet \
+            FROM todos, to_tsquery('english', $1) AS query \
+            WHERE user_id = $2 AND deleted_at IS NULL AND search_vector @@ query \
+            ORDER BY rank DESC, updated_at DESC \
+            LIMIT $3",
+        )
+        .bind::<diesel::sql_types::Text, _>(search.to_tsquery())
+        .bind::<diesel::sql_types::Uuid, _>(Uuid::parse_str(requester_id)?)
+        .bind::<diesel::sql_types::BigInt, _>(search.limit)
+        .bind::<diesel::sql_types::Text, _>(format!(
+            "HighlightAll=true, StartSel={}, StopSel={}",
+            HIGHLIGHT_START, HIGHLIGHT_END
+        ))
+        .bind::<diesel::sql_types::Text, _>(format!(
+            "MaxFragments=2, MinWords=5, MaxWords=15, StartSel={}, StopSel={}",
+            HIGHLIGHT_START, HIGHLIGHT_END
+        ))
+        .load::<SearchRow>(conn)?;
+
+        let ids: Vec<Uuid> = rows.iter().map(|row| row.id).collect();
+
+        let todos_list = todos.filter(id.eq_any(&ids)).load::<Todo>(conn)?;
+
+        let matches = rows
+            .into_iter()
+            .map(|row| SearchMatch {
+                id: row.id,
+                rank: row.rank,
+                highlighted_title: row.highlighted_title,
+                snippet: row.snippet,
+            })
+            .collect();
+
+        Ok(search_hits(matches, into_items(conn, todos_list)?))
+    }
+
+    fn apply_bulk_operations(
+        &self,
+        bulk: BulkDTO,
+        requester_id: &str,
+    ) -> Result<Vec<BulkResult>, TodoApiError> {
+        let conn = &self.pool.get()?;
+
+        // Each operation runs in a savepoint of its own
+        conn.transaction(|| {
+            let results = bulk
+                .operations
+                .iter()
+                .map(|operation| {
+                    let outcome =
+                        conn.transaction(|| apply_bulk_operation(conn, operation, requester_id));
+
+                    bulk_result(operation, outcome)
+                })
+                .collect();
+
+            Ok(results)
+        })
+    }
+
+    /// Todos written by the transactions from `since` up to the oldest one
+    /// still running, whose id becomes the next token. Every transaction
+    /// before that one has finished, so a change that commits late is
+    /// sent by a later sync instead of being skipped
+    fn todo_changes(
+        &self,
+        since: Option<i64>,
+        requester_id: &str,
+    ) -> Result<TodoDelta, TodoApiError> {
+        use crate::schema::todos::dsl::*;
+        let conn = &self.pool.get()?;
+
+        let requester_id = Uuid::parse_str(requester_id)?;
+
+        let next_txid =
+            diesel::select(sql::<BigInt>("txid_snapshot_xmin(txid_current_snapshot())"))
+                .get_result::<i64>(conn)?;
+
+        // `sync_txid` is kept up to date by triggers and isn't in the schema
+        let mut query = todos
+            .filter(user_id.eq(requester_id))
+            .order(sql::<BigInt>("sync_txid").asc())
+            .into_boxed();
+
+        query = match since {
+            Some(since) => query
+                .filter(sql::<Bool>("sync_txid >= ").bind::<BigInt, _>(since))
+                .filter(sql::<Bool>("sync_txid < ").bind::<BigInt, _>(next_txid)),
+            None => query.filter(deleted_at.is_null()),
+        };
+
+        let (trashed, changed): (Vec<Todo>, Vec<Todo>) = query
+            .load::<Todo>(conn)?
+            .into_iter()
+            .partition(|todo| todo.deleted_at.is_some());
+
+        let mut deleted: Vec<Tombstone> = trashed.iter().map(Tombstone::from).collect();
+
+        if let Some(since) = since {
+            use crate::schema::todo_tombstones::dsl as tombstones;
+
+            // Todos purged from the trash, their rows are gone
+            let purged = tombstones::todo_tombstones
+                .filter(tombstones::user_id.eq(requester_id))
+                .filter(tombstones::sync_txid.ge(since))
+                .filter(tombstones::sync_txid.lt(next_txid))
+                .select((tombstones::todo_id, tombstones::deleted_at))
+                .load::<(Uuid, NaiveDateTime)>(conn)?;
+
+            deleted.extend(purged.into_iter().map(|(todo_id, purged_at)| Tombstone {
+                id: todo_id,
+                deleted_at: purged_at,
+            }));
+        }
+
+        Ok(TodoDelta {
+            changed: into_items(conn, changed)?,
+            deleted,
+            next_token: next_txid.to_string(),
+        })
+    }
+
+    fn purge_trash(&self, expired_before: NaiveDateTime) -> Result<usize, TodoApiError> {
+        use crate::schema::todos::dsl::*;
+        let conn = &self.pool.get()?;
+
+        let purged = diesel::delete(todos.filter(deleted_at.lt(expired_before))).execute(conn)?;
+
+        Ok(purged)
+    }
+}
+
+impl ProjectRepository for PgRepository {
+    fn projects(&self, requester_id: &str) -> Result<Vec<Project>, TodoApiError> {
+        use crate::schema::projects::dsl::*;
+        let conn = &self.pool.get()?;
+
+        let projects_list = projects
+            .filter(user_id.eq(Uuid::parse_str(requester_id)?))
+            .order(name.asc())
+            .load::<Project>(conn)?;
+
+        Ok(projects_list)
+    }
+
+    fn insert_project(
+        &self,
+        project: ProjectDTO,
+        requester_id: &str,
+    ) -> Result<Project, TodoApiError> {
+        use crate::schema::projects::dsl::*;
+        let conn = &self.pool.get()?;
+
+        let new_project = Project::from(
+            project.name.trim().to_string(),
+            Uuid::parse_str(requester_id)?,
+        );
+
+        // Names are unique per user, so a duplicate is reported as a bad request
+        let inserted = diesel::insert_into(projects)
+            .values(&new_project)
+            .get_result(conn)?;
+
+        Ok(inserted)
+    }
+
+    fn get_project(&self, project_id: &str, requester_id: &str) -> Result<Project, TodoApiError> {
+        let conn = &self.pool.get()?;
+
+        verify_project_owner(conn, requester_id, project_id)
+    }
+
+    fn rename_project(
+        &self,
+        project_id: &str,
+        project: ProjectDTO,
+        requester_id: &str,
+    ) -> Result<Project, TodoApiError> {
+        use crate::schema::projects::dsl::*;
+        let conn = &self.pool.get()?;
+
+        diesel::update(owned_project(requester_id, project_id)?)
+            .set((
+                name.eq(project.name.trim()),
+                updated_at.eq(chrono::Local::now().naive_local()),
+            ))
+            .get_result::<Project>(conn)
+            .optional()?
+            .ok_or_else(|| TodoApiError::NotFound("Project".to_string()))
+    }
+
+    fn remove_project(&self, project_id: &str, requester_id: &str) -> Result<(), TodoApiError> {
+        let conn = &self.pool.get()?;
+
+        let delete_count =
+            diesel::delete(owned_project(requester_id, project_id)?).execute(conn)?;
+
+        if delete_count == 0 {
+            return Err(TodoApiError::NotFound("Project".to_string()));
+        }
+
+        Ok(())
+    }
+}
+
+impl SubtaskRepository for PgRepository {
+    fn subtasks(&self, todo_id: &str, requester_id: &str) -> Result<Vec<Subtask>, TodoApiError> {
+        use crate::schema::subtasks::dsl;
+        let conn = &self.pool.get()?;
+
+        let todo = verify_todo_owner(conn, requester_id, todo_id)?;
+
+        let list = dsl::subtasks
+            .filter(dsl::todo_id.eq(todo.id))
+            .order(dsl::position.asc())
+            .load::<Subtask>(conn)?;
+
+        Ok(list)
+    }
+
+    fn insert_subtask(
+        &self,
+        todo_id: &str,
+        subtask: SubtaskDTO,
+        requester_id: &str,
+    ) -> Result<Subtask, TodoApiError> {
+        use crate::schema::subtasks::dsl;
+        let conn = &self.pool.get()?;
+
+        let todo = verify_todo_owner(conn, requester_id, todo_id)?;
+
+        conn.transaction(|| {
+            let last_position = dsl::subtasks
+                .filter(dsl::todo_id.eq(todo.id))
+                .select(diesel::dsl::max(dsl::position))
+                .first::<Option<i32>>(conn)?;
+
+            let new_subtask = Subtask::from(
+                subtask.title.trim().to_string(),
+                todo.id,
+                last_position.map_or(0, |position| position + 1),
+            );
+
+            let inserted = diesel::insert_into(dsl::subtasks)
+                .values(&new_subtask)
+                .get_result(conn)?;
+
+            Ok(inserted)
+        })
+    }
+
+    fn reorder_subtasks(
+        &self,
+        todo_id: &str,
+        order: ReorderSubtasksDTO,
+        requester_id: &str,
+    ) -> Result<Vec<Subtask>, TodoApiError> {
+        use crate::schema::subtasks::dsl;
+        let conn = &self.pool.get()?;
+
+        let todo = verify_todo_owner(conn, requester_id, todo_id)?;
+
+        conn.transaction(|| {
+            let mut current_ids = dsl::subtasks
+                .filter(dsl::todo_id.eq(todo.id))
+                .select(dsl::id)
+                .load::<Uuid>(conn)?;
+
+            let mut requested_ids = order.ids.clone();
+
+            current_ids.sort();
+            requested_ids.sort();
+
+            if current_ids != requested_ids {
+                return Err(TodoApiError::BadRequest(
+                    "Every subtask of the todo has to be listed".into(),
+                ));
+            }
+
+            let now = chrono::Local::now().naive_local();
+
+            for (index, subtask_id) in order.ids.iter().enumerate() {
+                diesel::update(dsl::subtasks.filter(dsl::id.eq(subtask_id)))
+                    .set((dsl::position.eq(index as i32), dsl::updated_at.eq(now)))
+                    .execute(conn)?;
+            }
+
+            let list = dsl::subtasks
+                .filter(dsl::todo_id.eq(todo.id))
+                .order(dsl::position.asc())
+                .load::<Subtask>(conn)?;
+
+            Ok(list)
+        })
+    }
+
+    fn set_subtask_completeness(
+        &self,
+        todo_id: &str,
+        subtask_id: &str,
+        is_complete: bool,
+        requester_id: &str,
+    ) -> Result<(), TodoApiError> {
+        use crate::schema::subtasks::dsl;
+        let conn = &self.pool.get()?;
+
+        let todo = verify_todo_owner(conn, requester_id, todo_id)?;
+
+        let update_count = diesel::update(
+            dsl::subtasks
+                .filter(dsl::id.eq(Uuid::parse_str(subtask_id)?))
+                .filter(dsl::todo_id.eq(todo.id)),
+        )
+        .set((
+            dsl::completed.eq(is_complete),
+            dsl::updated_at.eq(chrono::Local::now().naive_local()),
+        ))
+        .execute(conn)?;
+
+        ensure_subtask_found(update_count)
+    }
+
+    fn remove_subtask(
+        &self,
+        todo_id: &str,
+        subtask_id: &str,
+        requester_id: &str,
+    ) -> Result<(), TodoApiError> {
+        use crate::schema::subtasks::dsl;
+        let conn = &self.pool.get()?;
+
+        let todo = verify_todo_owner(conn, requester_id, todo_id)?;
+
+        let delete_count = diesel::delete(
+            dsl::subtasks
+                .filter(dsl::id.eq(Uuid::parse_str(subtask_id)?))
+                .filter(dsl::todo_id.eq(todo.id)),
+        )
+        .execute(conn)?;
+
+        ensure_subtask_found(delete_count)
+    }
+}
+
+/// A single todo that belongs to the requester
+type OwnedTodo = Filter<
+    Filter<
+        Filter<crate::schema::todos::table, diesel::dsl::Eq<crate::schema::todos::id, Uuid>>,
+        diesel::dsl::Eq<crate::schema::todos::user_id, Uuid>,
+    >,
+    IsNull<crate::schema::todos::deleted_at>,
+>;
+
+/// Scopes a query to the todo with `todo_id` owned by `requester_id`.
+/// Every read and write of a single todo goes through this filter,
+/// so todos of other users and todos in the trash are never found
+fn owned_todo(requester_id: &str, todo_id: &str) -> Result<OwnedTodo, TodoApiError> {
+    use crate::schema::todos;
+
+    let todo_id = Uuid::parse_str(todo_id)?;
+
+    let requester_id = Uuid::parse_str(requester_id)?;
+
+    Ok(todos::table
+        .filter(todos::id.eq(todo_id))
+        .filter(todos::user_id.eq(requester_id))
+        .filter(todos::deleted_at.is_null()))
+}
+
+/// Gets the todo with `todo_id` owned by `requester_id`, todos in the trash are not found
+fn verify_todo_owner(
+    conn: &PgConnection,
+    requester_id: &str,
+    todo_id: &str,
+) -> Result<Todo, TodoApiError> {
+    owned_todo(requester_id, todo_id)?
+        .first::<Todo>(conn)
+        .optional()?
+        .ok_or_else(|| TodoApiError::NotFound("Todo".to_string()))
+}
+
+/// A single project that belongs to the requester
+type OwnedProject = Filter<
+    Filter<crate::schema::projects::table, diesel::dsl::Eq<crate::schema::projects::id, Uuid>>,
+    diesel::dsl::Eq<crate::schema::projects::user_id, Uuid>,
+>;
+
+/// Scopes a query to the project with `project_id` owned by `requester_id`
+fn owned_project(requester_id: &str, project_id: &str) -> Result<OwnedProject, TodoApiError> {
+    use crate::schema::projects;
+
+    let project_id = Uuid::parse_str(project_id)?;
+
+    let requester_id = Uuid::parse_str(requester_id)?;
+
+    Ok(projects::table
+        .filter(projects::id.eq(project_id))
+        .filter(projects::user_id.eq(requester_id)))
+}
+
+/// Gets the project with `project_id` owned by `requester_id`
+fn verify_project_owner(
+    conn: &PgConnection,
+    requester_id: &str,
+    project_id: &str,
+) -> Result<Project, TodoApiError> {
+    owned_project(requester_id, project_id)?
+        .first::<Project>(conn)
+        .optional()?
+        .ok_or_else(|| TodoApiError::NotFound("Project".to_string()))
+}
+
+/// Move a todo owned by the requester to the trash
+fn trash_todo(conn: &PgConnection, todo_id: &str, requester_id: &str) -> Result<(), TodoApiError> {
+    use crate::schema::todos::dsl::*;
+
+    let delete_count = diesel::update(owned_todo(requester_id, todo_id)?)
+        .set(deleted_at.eq(chrono::Local::now().naive_local()))
+        .execute(conn)?;
+
+    ensure_todo_found(delete_count)
+}
+
+/// Complete or reopen a todo owned by the requester, completing
+/// a recurring todo creates its next occurrence
+fn set_todo_completeness(
+    conn: &PgConnection,
+    todo_id: &str,
+    is_complete: bool,
+    requester_id: &str,
+) -> Result<Todo, TodoApiError> {
+    use crate::schema::todos::dsl::*;
+
+    conn.transaction(|| {
+        let previous = verify_todo_owner(conn, requester_id, todo_id)?;
+
+        // Reopened todos can't stay hidden in the archive
+        let archived = if is_complete {
+            previous.archived_at
+        } else {
+            None
+        };
+
+        let now = chrono::Local::now().naive_local();
+
+        let updated = diesel::update(owned_todo(requester_id, todo_id)?)
+            .set((
+                completed.eq(is_complete),
+                completed_at.eq(previous.completed_at_after(is_complete, now)),
+                archived_at.eq(archived),
+                updated_at.eq(now),
+            ))
+            .get_result::<Todo>(conn)?;
+
+        if updated.completed && !previous.completed {
+            spawn_next_occurrence(conn, &updated)?;
+        }
+
+        Ok(updated)
+    })
+}
+
+/// Apply a single operation of a bulk request,
+/// returns the todo as it is afterwards unless it was deleted
+fn apply_bulk_operation(
+    conn: &PgConnection,
+    operation: &BulkOperation,
+    requester_id: &str,
+) -> Result<Option<TodoItem>, TodoApiError> {
+    use crate::schema::todos::dsl::*;
+
+    let todo_id = operation.todo_id().to_string();
+
+    let updated = match operation {
+        BulkOperation::Complete { .. } => {
+            set_todo_completeness(conn, &todo_id, true, requester_id)?
+        }
+        BulkOperation::Incomplete { .. } => {
+            set_todo_completeness(conn, &todo_id, false, requester_id)?
+        }
+        BulkOperation::Delete { .. } => {
+            trash_todo(conn, &todo_id, requester_id)?;
+
+            return Ok(None);
+        }
+        BulkOperation::Retag { add, remove, .. } => {
+            let todo = verify_todo_owner(conn, requester_id, &todo_id)?;
+
+            let names = tags_for_todos(conn, &[todo.id])?
+                .remove(&todo.id)
+                .unwrap_or_default();
+
+            set_todo_tags(conn, todo.user_id, todo.id, &retagged(names, add, remove))?;
+
+            diesel::update(owned_todo(requester_id, &todo_id)?)
+                .set(updated_at.eq(chrono::Local::now().naive_local()))
+                .get_result::<Todo>(conn)?
+        }
+        BulkOperation::Move {
+            project_id: project,
+            ..
+        } => {
+            if let Some(project) = project {
+                verify_project_owner(conn, requester_id, project.to_string().as_str())?;
+            }
+
+            diesel::update(owned_todo(requester_id, &todo_id)?)
+                .set((
+                    project_id.eq(project),
+                    updated_at.eq(chrono::Local::now().naive_local()),
+                ))
+                .get_result::<Todo>(conn)
+                .optional()?
+                .ok_or_else(|| TodoApiError::NotFound("Todo".to_string()))?
+        }
+    };
+
+    Ok(Some(into_items(conn, vec![updated])?.remove(0)))
+}
+
+/// Create the todo that follows `completed` in its recurring series,
+/// a series only ever has one open todo
+fn spawn_next_occurrence(conn: &PgConnection, completed: &Todo) -> Result<(), TodoApiError> {
+    use crate::schema::todos::dsl;
+
+    let recurrence = match &completed.recurrence {
+        Some(recurrence) => recurrence,
+        None => return Ok(()),
+    };
+
+    // Todos that were made recurring after they were created start their series here
+    let series = match completed.series_id {
+        Some(series) => series,
+        None => {
+            diesel::update(dsl::todos.filter(dsl::id.eq(completed.id)))
+                .set(dsl::series_id.eq(completed.id))
+                .execute(conn)?;
+
+            completed.id
+        }
+    };
+
+    let open_count: i64 = dsl::todos
+        .filter(dsl::series_id.eq(series))
+        .filter(dsl::completed.eq(false))
+        .filter(dsl::deleted_at.is_null())
+        .count()
+        .get_result(conn)?;
+
+    if open_count > 0 {
+        return Ok(());
+    }
+
+    let next = next_occurrence(completed, recurrence, series);
+
+    diesel::insert_into(dsl::todos)
+        .values(&next)
+        .execute(conn)?;
+
+    let tags = tags_for_todos(conn, &[completed.id])?
+        .remove(&completed.id)
+        .unwrap_or_default();
+
+    set_todo_tags(conn, next.user_id, next.id, &tags)?;
+
+    copy_subtasks(conn, completed.id, next.id)
+}
+
+/// Position between `anchor` and its neighbour on the side `todo` is moved to,
+/// `None` when there is no room left between them
+fn free_position_next_to(
+    conn: &PgConnection,
+    todo: &Todo,
+    anchor: &Todo,
+    before: bool,
+) -> Result<Option<i64>, TodoApiError> {
+    use crate::schema::todos::dsl::*;
+
+    let others = || {
+        todos
+            .filter(user_id.eq(anchor.user_id))
+            .filter(id.ne(todo.id))
+    };
+
+    // Todos sharing a position can't be told apart, so they need rebalancing
+    let shared_count: i64 = others()
+        .filter(id.ne(anchor.id))
+        .filter(position.eq(anchor.position))
+        .count()
+        .get_result(conn)?;
+
+    if shared_count > 0 {
+        return Ok(None);
+    }
+
+    let neighbour = if before {
+        others()
+            .filter(position.lt(anchor.position))
+            .select(diesel::dsl::max(position))
+            .first::<Option<i64>>(conn)?
+    } else {
+        others()
+            .filter(position.gt(anchor.position))
+            .select(diesel::dsl::min(position))
+            .first::<Option<i64>>(conn)?
+    };
+
+    Ok(position_next_to(anchor.position, neighbour, before))
+}
+
+/// Space the positions of all todos of a user evenly, keeping their order
+fn rebalance_positions(conn: &PgConnection, owner_id: Uuid) -> Result<(), TodoApiError> {
+    use crate::schema::todos::dsl::*;
+
+    let ordered_ids = todos
+        .filter(user_id.eq(owner_id))
+        .order((position.asc(), created_at.desc()))
+        .select(id)
+        .load::<Uuid>(conn)?;
+
+    for (index, todo_id) in ordered_ids.iter().enumerate() {
+        diesel::update(todos.filter(id.eq(todo_id)))
+            .set(position.eq((index as i64 + 1) * POSITION_GAP))
+            .execute(conn)?;
+    }
+
+    Ok(())
+}
+
+type TodoPredicate = Box<dyn BoxableExpression<crate::schema::todos::table, Pg, SqlType = Bool>>;
+
+/// Matches the todos that come after the keys of `last_seen` in `sort` order
+///
+/// With sort keys `a, b, id` this is
+/// `a > a' OR (a = a' AND (b > b' OR (b = b' AND id > id')))`,
+/// where `>` means after in the direction of the key
+fn after_cursor(sort: TodoSort, last_seen: &TodoCursor) -> TodoPredicate {
+    use crate::schema::todos::dsl::*;
+
+    // Each key as (after, equal), `id` is the last key and is left out
+    let completed_key: (TodoPredicate, TodoPredicate) = (
+        Box::new(completed.gt(last_seen.completed)),
+        Box::new(completed.eq(last_seen.completed)),
+    );
+    let created_key = || -> (TodoPredicate, TodoPredicate) {
+        (
+            Box::new(created_at.lt(last_seen.created_at)),
+            Box::new(created_at.eq(last_seen.created_at)),
+        )
+    };
+    // Todos without a due date come last
+    let due_key = || -> (TodoPredicate, TodoPredicate) {
+        match last_seen.due_at {
+            Some(due) => (
+                Box::new(due_at.gt(due).or(due_at.is_null())),
+                Box::new(due_at.eq(due)),
+            ),
+            None => (
+                Box::new(diesel::dsl::sql::<Bool>("FALSE")),
+                Box::new(due_at.is_null()),
+            ),
+        }
+    };
+
+    let keys: Vec<(TodoPredicate, TodoPredicate)> = match sort {
+        TodoSort::Manual => vec![
+            completed_key,
+            (
+                Box::new(position.gt(last_seen.position)),
+                Box::new(position.eq(last_seen.position)),
+            ),
+            created_key(),
+        ],
+        TodoSort::Priority => vec![
+            completed_key,
+            (
+                Box::new(priority.lt(last_seen.priority)),
+                Box::new(priority.eq(last_seen.priority)),
+            ),
+            due_key(),
+            created_key(),
+        ],
+        TodoSort::Created => vec![completed_key, created_key()],
+        TodoSort::Updated => vec![
+            completed_key,
+            (
+                Box::new(updated_at.lt(last_seen.updated_at)),
+                Box::new(updated_at.eq(last_seen.updated_at)),
+            ),
+        ],
+        TodoSort::Due => vec![completed_key, due_key()],
+        TodoSort::Title => vec![
+            completed_key,
+            (
+                Box::new(title.gt(last_seen.title.clone())),
+                Box::new(title.eq(last_seen.title.clone())),
+            ),
+        ],
+    };
+
+    keys.into_iter()
+        .rev()
+        .fold(Box::new(id.gt(last_seen.id)), |rest, (after, equal)| {
+            Box::new(after.or(equal.and(rest)))
+        })
+}
+
+/// Search match as returned by the search query
+#[derive(QueryableByName)]
+struct SearchRow {
+    #[sql_type = "diesel::sql_types::Uuid"]
+    id: Uuid,
+    #[sql_type = "diesel::sql_types::Float"]
+    rank: f32,
+    #[sql_type = "diesel::sql_types::Text"]
+    highlighted_title: String,
+    #[sql_type = "diesel::sql_types::Nullable<diesel::sql_types::Text>"]
+    snippet: Option<String>,
+}
+
+/// Attach the data stored in other tables to `todos_list`
+fn into_items(conn: &PgConnection, todos_list: Vec<Todo>) -> Result<Vec<TodoItem>, TodoApiError> {
+    let ids: Vec<Uuid> = todos_list.iter().map(|todo| todo.id).collect();
+
+    let mut tags_by_todo = tags_for_todos(conn, &ids)?;
+
+    let mut progress_by_todo = progress_for_todos(conn, &ids)?;
+
+    Ok(todos_list
+        .into_iter()
+        .map(|todo| TodoItem {
+            tags: tags_by_todo.remove(&todo.id).unwrap_or_default(),
+            progress: progress_by_todo.remove(&todo.id).unwrap_or_default(),
+            todo,
+        })
+        .collect())
+}
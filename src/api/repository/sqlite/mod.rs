@@ -0,0 +1,1626 @@
+use std::collections::HashMap;
+
+use chrono::NaiveDateTime;
+use diesel::connection::SimpleConnection;
+use diesel::dsl::sql;
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, CustomizeConnection};
+use diesel::sql_types::{BigInt, Bool};
+use diesel::sqlite::Sqlite;
+use uuid::Uuid;
+
+use self::rows::{
+    from_rows, EmailVerificationRow, PasswordResetRow, ProjectRow, SessionRow, SubtaskRow, TagRow,
+    TodoRow, TodoTagRow, UserRow,
+};
+use self::schema::*;
+use super::{
+    bulk_result, contains_pattern, ensure_subtask_found, move_anchor, new_todo, next_occurrence,
+    position_next_to, retagged, search_hits, EmailVerificationRepository, PasswordResetRepository,
+    ProjectRepository, SearchMatch, SessionRepository, SubtaskRepository, TodoRepository,
+    UserRepository,
+};
+use crate::api::auth_utils::ensure_todo_found;
+use crate::api::dtos::{
+    project::ProjectDTO,
+    subtask::{ReorderSubtasksDTO, SubtaskDTO},
+    todo::{
+        BulkDTO, BulkOperation, CreateTodoDTO, DueFilter, MoveTodoDTO, SearchQueryDTO, TagMatch,
+        TodoCursor, TodoListQuery, TodoSort, UpdateTodoDTO,
+    },
+};
+use crate::api::errors::TodoApiError;
+use crate::models::{
+    email_verification_model::EmailVerification,
+    password_reset_model::PasswordReset,
+    project_model::Project,
+    session_model::Session,
+    subtask_model::{Progress, Subtask},
+    todo_model::{
+        BulkResult, Priority, SearchHit, Todo, TodoChanges, TodoDelta, TodoItem, Tombstone,
+        HIGHLIGHT_END, HIGHLIGHT_START, POSITION_GAP,
+    },
+    user_model::User,
+};
+
+mod rows;
+mod schema;
+
+type SqlitePool = r2d2::Pool<ConnectionManager<SqliteConnection>>;
+
+/// Repository keeping everything in a single SQLite file,
+/// for running the server without a Postgres database
+pub struct SqliteRepository {
+    pool: SqlitePool,
+}
+
+/// Settings SQLite needs on every connection, foreign keys are not enforced without them
+#[derive(Debug)]
+struct ConnectionSettings;
+
+impl CustomizeConnection<SqliteConnection, diesel::r2d2::Error> for ConnectionSettings {
+    fn on_acquire(&self, conn: &mut SqliteConnection) -> Result<(), diesel::r2d2::Error> {
+        conn.batch_execute("PRAGMA foreign_keys = ON; PRAGMA busy_timeout = 5000;")
+            .map_err(diesel::r2d2::Error::QueryError)
+    }
+}
+
+impl SqliteRepository {
+    /// Open the database at `path`, creating the file and the tables it lacks
+    pub fn open(path: &str) -> Result<Self, TodoApiError> {
+        let pool = r2d2::Pool::builder()
+            // SQLite only has one writer at a time, a single
+            // connection queues requests instead of failing them
+            .max_size(1)
+            .connection_customizer(Box::new(ConnectionSettings))
+            .build(ConnectionManager::<SqliteConnection>::new(path))?;
+
+        let conn = pool.get()?;
+
+        conn.batch_execute(include_str!("setup.sql"))?;
+
+        upgrade(&conn)?;
+
+        Ok(Self { pool })
+    }
+}
+
+/// Add the columns that files made by an older `setup.sql` lack,
+/// `CREATE TABLE IF NOT EXISTS` leaves their tables as they were
+fn upgrade(conn: &SqliteConnection) -> Result<(), TodoApiError> {
+    let has_email_verified_at = diesel::select(sql::<Bool>(
+        "EXISTS (SELECT 1 FROM pragma_table_info('users') WHERE name = 'email_verified_at')",
+    ))
+    .get_result::<bool>(conn)?;
+
+    if !has_email_verified_at {
+        // Accounts made before emails were verified keep working
+        conn.batch_execute(
+            "ALTER TABLE users ADD COLUMN email_verified_at TIMESTAMP;
+             UPDATE users SET email_verified_at = created_at;",
+        )?;
+    }
+
+    Ok(())
+}
+
+impl UserRepository for SqliteRepository {
+    fn find_user_by_email(&self, user_email: &str) -> Result<Option<User>, TodoApiError> {
+        use self::users::dsl::*;
+        let conn = &self.pool.get()?;
+
+        users
+            .filter(email.eq(user_email))
+            .first::<UserRow>(conn)
+            .optional()?
+            .map(User::try_from)
+            .transpose()
+    }
+
+    fn find_user_by_id(&self, user_id: Uuid) -> Result<Option<User>, TodoApiError> {
+        use self::users::dsl::*;
+        let conn = &self.pool.get()?;
+
+        users
+            .find(user_id.to_string())
+            .first::<UserRow>(conn)
+            .optional()?
+            .map(User::try_from)
+            .transpose()
+    }
+
+    fn insert_user(&self, user: &User) -> Result<(), TodoApiError> {
+        use self::users::dsl::*;
+        let conn = &self.pool.get()?;
+
+        diesel::insert_into(users)
+            .values(&UserRow::from(user))
+            .execute(conn)?;
+
+        Ok(())
+    }
+
+    fn update_password(&self, user_id: Uuid, password_hash: &str) -> Result<(), TodoApiError> {
+        use self::users::dsl::*;
+        let conn = &self.pool.get()?;
+
+        diesel::update(users.find(user_id.to_string()))
+            .set((
+                password.eq(password_hash),
+                updated_at.eq(chrono::Local::now().naive_local()),
+            ))
+            .execute(conn)?;
+
+        Ok(())
+    }
+}
+
+impl SessionRepository for SqliteRepository {
+    fn insert_session(&self, session: &Session) -> Result<(), TodoApiError> {
+        use self::sessions::dsl::*;
+        let conn = &self.pool.get()?;
+
+        diesel::insert_into(sessions)
+            .values(&SessionRow::from(session))
+            .execute(conn)?;
+
+        Ok(())
+    }
+
+    fn get_session(&self, session_id: Uuid) -> Result<Option<Session>, TodoApiError> {
+        use self::sessions::dsl::*;
+        let conn = &self.pool.get()?;
+
+        sessions
+            .find(session_id.to_string())
+            .first::<SessionRow>(conn)
+            .optional()?
+            .map(Session::try_from)
+            .transpose()
+    }
+
+    fn find_session_by_refresh_token(
+        &self,
+        token_hash: &str,
+    ) -> Result<Option<Session>, TodoApiError> {
+        use self::sessions::dsl::*;
+        let conn = &self.pool.get()?;
+
+        sessions
+            .filter(refresh_token_hash.eq(token_hash))
+            .first::<SessionRow>(conn)
+            .optional()?
+            .map(Session::try_from)
+            .transpose()
+    }
+
+    fn rotate_refresh_token(
+        &self,
+        session_id: Uuid,
+        old_hash: &str,
+        new_hash: &str,
+        new_expires_at: NaiveDateTime,
+    ) -> Result<bool, TodoApiError> {
+        use self::sessions::dsl::*;
+        let conn = &self.pool.get()?;
+
+        let update_count = diesel::update(
+            sessions
+                .filter(id.eq(session_id.to_string()))
+                .filter(refresh_token_hash.eq(old_hash))
+                .filter(revoked_at.is_null()),
+        )
+        .set((
+            refresh_token_hash.eq(new_hash),
+            expires_at.eq(new_expires_at),
+        ))
+        .execute(conn)?;
+
+        Ok(update_count > 0)
+    }
+
+    fn revoke_session(&self, session_id: Uuid) -> Result<(), TodoApiError> {
+        use self::sessions::dsl::*;
+        let conn = &self.pool.get()?;
+
+        diesel::update(
+            sessions
+                .filter(id.eq(session_id.to_string()))
+                .filter(revoked_at.is_null()),
+        )
+        .set(revoked_at.eq(chrono::Local::now().naive_local()))
+        .execute(conn)?;
+
+        Ok(())
+    }
+}
+
+impl EmailVerificationRepository for SqliteRepository {
+    fn insert_email_verification(
+        &self,
+        verification: &EmailVerification,
+        sent_after: NaiveDateTime,
+    ) -> Result<bool, TodoApiError> {
+        use self::email_verifications::dsl::*;
+        let conn = &self.pool.get()?;
+
+        let row = EmailVerificationRow::from(verification);
+
+        conn.transaction(|| {
+            let recent = diesel::select(diesel::dsl::exists(
+                email_verifications
+                    .filter(user_id.eq(&row.user_id))
+                    .filter(created_at.gt(sent_after)),
+            ))
+            .get_result::<bool>(conn)?;
+
+            if recent {
+                return Ok(false);
+            }
+
+            diesel::insert_into(email_verifications)
+                .values(&row)
+                .execute(conn)?;
+
+            Ok(true)
+        })
+    }
+
+    fn verify_email(
+        &self,
+        verification_token_hash: &str,
+        now: NaiveDateTime,
+    ) -> Result<bool, TodoApiError> {
+        let conn = &self.pool.get()?;
+
+        conn.transaction(|| {
+            let user_id = email_verifications::table
+                .filter(email_verifications::token_hash.eq(verification_token_hash))
+                .filter(email_verifications::expires_at.gt(now))
+                .select(email_verifications::user_id)
+                .first::<String>(conn)
+                .optional()?;
+
+            let user_id = match user_id {
+                Some(user_id) => user_id,
+                None => return Ok(false),
+            };
+
+            diesel::update(
+                users::table
+                    .find(&user_id)
+                    .filter(users::email_verified_at.is_null()),
+            )
+            .set(users::email_verified_at.eq(now))
+            .execute(conn)?;
+
+            diesel::delete(
+                email_verifications::table.filter(email_verifications::user_id.eq(&user_id)),
+            )
+            .execute(conn)?;
+
+            Ok(true)
+        })
+    }
+}
+
+impl PasswordResetRepository for SqliteRepository {
+    fn insert_password_reset(
+        &self,
+        reset: &PasswordReset,
+        sent_after: NaiveDateTime,
+    ) -> Result<bool, TodoApiError> {
+        use self::password_resets::dsl::*;
+        let conn = &self.pool.get()?;
+
+        let row = PasswordResetRow::from(reset);
+
+        conn.transaction(|| {
+            let recent = diesel::select(diesel::dsl::exists(
+                password_resets
+                    .filter(user_id.eq(&row.user_id))
+                    .filter(created_at.gt(sent_after)),
+            ))
+            .get_result::<bool>(conn)?;
+
+            if recent {
+                return Ok(false);
+            }
+
+            diesel::insert_into(password_resets)
+                .values(&row)
+                .execute(conn)?;
+
+            Ok(true)
+        })
+    }
+
+    fn reset_password(
+        &self,
+        reset_token_hash: &str,
+        password_hash: &str,
+        now: NaiveDateTime,
+    ) -> Result<bool, TodoApiError> {
+        let conn = &self.pool.get()?;
+
+        conn.transaction(|| {
+            let reset = password_resets::table
+                .filter(password_resets::token_hash.eq(reset_token_hash))
+                .first::<PasswordResetRow>(conn)
+                .optional()?
+                .map(PasswordReset::try_from)
+                .transpose()?;
+
+            let reset = match reset {
+                Some(reset) if reset.is_usable(now) => reset,
+                _ => return Ok(false),
+            };
+
+            let user_id = reset.user_id.to_string();
+
+            diesel::update(users::table.find(&user_id))
+                .set((users::password.eq(password_hash), users::updated_at.eq(now)))
+                .execute(conn)?;
+
+            diesel::update(
+                password_resets::table
+                    .filter(password_resets::user_id.eq(&user_id))
+                    .filter(password_resets::used_at.is_null()),
+            )
+            .set(password_resets::used_at.eq(now))
+            .execute(conn)?;
+
+            diesel::update(
+                sessions::table
+                    .filter(sessions::user_id.eq(&user_id))
+                    .filter(sessions::revoked_at.is_null()),
+            )
+            .set(sessions::revoked_at.eq(now))
+            .execute(conn)?;
+
+            Ok(true)
+        })
+    }
+}
+
+impl TodoRepository for SqliteRepository {
+    fn insert_todo(
+        &self,
+        todo: CreateTodoDTO,
+        requester_id: &str,
+    ) -> Result<TodoItem, TodoApiError> {
+        use self::todos::dsl::*;
+        let conn = &self.pool.get()?;
+
+        if let Some(project) = todo.project_id {
+            verify_project_owner(conn, requester_id, project.to_string().as_str())?;
+        }
+
+        let mut new_todo = new_todo(&todo, Uuid::parse_str(requester_id)?);
+
+        conn.transaction(|| {
+            // New todos go to the top of the list
+            new_todo.position = todos
+                .filter(user_id.eq(new_todo.user_id.to_string()))
+                .select(diesel::dsl::min(position))
+                .first::<Option<i64>>(conn)?
+                .map_or(0, |top| top - POSITION_GAP);
+
+            diesel::insert_into(todos)
+                .values(&TodoRow::from(&new_todo))
+                .execute(conn)?;
+
+            set_todo_tags(conn, new_todo.user_id, new_todo.id, &todo.tags)?;
+
+            Ok(TodoItem {
+                todo: new_todo,
+                tags: todo.tags,
+                progress: Progress::default(),
+            })
+        })
+    }
+
+    fn get_todo(&self, todo_id: &str, requester_id: &str) -> Result<TodoItem, TodoApiError> {
+        let conn = &self.pool.get()?;
+
+        let todo = verify_todo_owner(conn, requester_id, todo_id)?;
+
+        Ok(into_items(conn, vec![todo])?.remove(0))
+    }
+
+    fn update_todo(
+        &self,
+        todo_id: &str,
+        update: UpdateTodoDTO,
+        requester_id: &str,
+    ) -> Result<TodoItem, TodoApiError> {
+        let conn = &self.pool.get()?;
+
+        let changes = TodoChanges::from(&update);
+
+        if let Some(Some(project)) = changes.project_id {
+            verify_project_owner(conn, requester_id, project.to_string().as_str())?;
+        }
+
+        conn.transaction(|| {
+            let mut updated = verify_todo_owner(conn, requester_id, todo_id)?;
+
+            let was_complete = updated.completed;
+
+            changes.apply_to(&mut updated);
+
+            save_todo(conn, &updated)?;
+
+            if let Some(tags) = &update.tags {
+                set_todo_tags(conn, updated.user_id, updated.id, tags)?;
+            }
+
+            if updated.completed && !was_complete {
+                spawn_next_occurrence(conn, &updated)?;
+            }
+
+            Ok(into_items(conn, vec![updated])?.remove(0))
+        })
+    }
+
+    fn list_todos(
+        &self,
+        list_query: TodoListQuery,
+        requester_id: &str,
+    ) -> Result<(Vec<TodoItem>, Option<TodoCursor>), TodoApiError> {
+        use self::todos::dsl::*;
+        let conn = &self.pool.get()?;
+
+        let requester_id = id_param(requester_id)?;
+
+        let mut query = todos
+            .filter(user_id.eq(requester_id.clone()))
+            .filter(deleted_at.is_null())
+            .into_boxed();
+
+        if let Some(project) = list_query.project {
+            // Projects can be referred to by id or by name
+            query = match Uuid::parse_str(project.as_str()) {
+                Ok(project) => query.filter(project_id.eq(project.to_string())),
+                Err(_) => query.filter(
+                    project_id.eq_any(
+                        projects::table
+                            .select(projects::id.nullable())
+                            .filter(projects::user_id.eq(requester_id.clone()))
+                            .filter(projects::name.eq(project)),
+                    ),
+                ),
+            };
+        }
+
+        if !list_query.tags.is_empty() {
+            // Ids of the requester's todos tagged with any of `names`
+            let tagged_with = |names: Vec<String>| {
+                todo_tags::table
+                    .inner_join(tags::table)
+                    .filter(tags::user_id.eq(requester_id.clone()))
+                    .filter(tags::name.eq_any(names))
+                    .select(todo_tags::todo_id)
+            };
+
+            match list_query.tag_match {
+                TagMatch::Any => query = query.filter(id.eq_any(tagged_with(list_query.tags))),
+                TagMatch::All => {
+                    for tag in list_query.tags {
+                        query = query.filter(id.eq_any(tagged_with(vec![tag])));
+                    }
+                }
+            }
+        }
+
+        if !list_query.include_archived {
+            query = query.filter(archived_at.is_null());
+        }
+
+        if let Some(series) = list_query.series {
+            query = query.filter(series_id.eq(series.to_string()));
+        }
+
+        if let Some(is_completed) = list_query.completed {
+            query = query.filter(completed.eq(is_completed));
+        }
+
+        if let Some(before) = list_query.created_before {
+            query = query.filter(created_at.lt(before));
+        }
+
+        if let Some(after) = list_query.created_after {
+            query = query.filter(created_at.gt(after));
+        }
+
+        if let Some(since) = list_query.updated_since {
+            query = query.filter(updated_at.ge(since));
+        }
+
+        if let Some(text) = list_query.text {
+            // `LIKE` already ignores case in SQLite, but has no escape character by default
+            let pattern = contains_pattern(&text);
+
+            query = query.filter(
+                title
+                    .like(pattern.clone())
+                    .escape('\\')
+                    .or(notes.like(pattern).escape('\\')),
+            );
+        }
+
+        if let Some(cursor) = &list_query.cursor {
+            query = query.filter(after_cursor(list_query.sort, cursor));
+        }
+
+        if let Some(due) = list_query.due {
+            let (from, until) = due.window(chrono::Local::now().naive_local());
+
+            query = query.filter(due_at.lt(until));
+
+            if let Some(from) = from {
+                query = query.filter(due_at.ge(from));
+            }
+
+            if let DueFilter::Overdue = due {
+                query = query.filter(completed.eq(false));
+            }
+        }
+
+        // Same order as with Postgres, except that SQLite sorts nulls
+        // first, so todos without a due date are moved last explicitly
+        query = match list_query.sort {
+            TodoSort::Manual => {
+                query.order((completed.asc(), position.asc(), created_at.desc(), id.asc()))
+            }
+            TodoSort::Priority => query.order((
+                completed.asc(),
+                priority.desc(),
+                due_at.is_null().asc(),
+                due_at.asc(),
+                created_at.desc(),
+                id.asc(),
+            )),
+            TodoSort::Created => query.order((completed.asc(), created_at.desc(), id.asc())),
+            TodoSort::Updated => query.order((completed.asc(), updated_at.desc(), id.asc())),
+            TodoSort::Due => query.order((
+                completed.asc(),
+                due_at.is_null().asc(),
+                due_at.asc(),
+                id.asc(),
+            )),
+            TodoSort::Title => query.order((completed.asc(), title.asc(), id.asc())),
+        };
+
+        // One more than asked for tells if there is a next page
+        let mut todos_list: Vec<Todo> =
+            from_rows(query.limit(list_query.limit + 1).load::<TodoRow>(conn)?)?;
+
+        let next_cursor = if todos_list.len() as i64 > list_query.limit {
+            todos_list.truncate(list_query.limit as usize);
+            todos_list.last().map(TodoCursor::after)
+        } else {
+            None
+        };
+
+        Ok((into_items(conn, todos_list)?, next_cursor))
+    }
+
+    fn set_todo_completeness(
+        &self,
+        todo_id: &str,
+        is_complete: bool,
+        requester_id: &str,
+    ) -> Result<(), TodoApiError> {
+        let conn = &self.pool.get()?;
+
+        set_todo_completeness(conn, todo_id, is_complete, requester_id).map(|_| ())
+    }
+
+    fn set_todo_priority(
+        &self,
+        todo_id: &str,
+        new_priority: Priority,
+        requester_id: &str,
+    ) -> Result<(), TodoApiError> {
+        use self::todos::dsl::*;
+        let conn = &self.pool.get()?;
+
+        let update_count = diesel::update(
+            todos
+                .filter(id.eq(id_param(todo_id)?))
+                .filter(user_id.eq(id_param(requester_id)?))
+                .filter(deleted_at.is_null()),
+        )
+        .set((
+            priority.eq(new_priority),
+            updated_at.eq(chrono::Local::now().naive_local()),
+        ))
+        .execute(conn)?;
+
+        ensure_todo_found(update_count)
+    }
+
+    fn move_todo(
+        &self,
+        todo_id: &str,
+        target: MoveTodoDTO,
+        requester_id: &str,
+    ) -> Result<TodoItem, TodoApiError> {
+        let conn = &self.pool.get()?;
+
+        let (anchor_id, before) = move_anchor(&target)?;
+
+        conn.transaction(|| {
+            let mut todo = verify_todo_owner(conn, requester_id, todo_id)?;
+
+            if todo.id == anchor_id {
+                return Err(TodoApiError::BadRequest(
+                    "A todo can not be moved next to itself".into(),
+                ));
+            }
+
+            let anchor = verify_todo_owner(conn, requester_id, anchor_id.to_string().as_str())?;
+
+            todo.position = match free_position_next_to(conn, &todo, &anchor, before)? {
+                Some(new_position) => new_position,
+                None => {
+                    // Only when the gap is used up are all positions of the user rewritten
+                    rebalance_positions(conn, todo.user_id)?;
+
+                    let anchor =
+                        verify_todo_owner(conn, requester_id, anchor_id.to_string().as_str())?;
+
+                    free_position_next_to(conn, &todo, &anchor, before)?
+                        .ok_or_else(|| TodoApiError::BadRequest("Todo could not be moved".into()))?
+                }
+            };
+            todo.updated_at = chrono::Local::now().naive_local();
+
+            save_todo(conn, &todo)?;
+
+            Ok(into_items(conn, vec![todo])?.remove(0))
+        })
+    }
+
+    fn trash_todo(&self, todo_id: &str, requester_id: &str) -> Result<(), TodoApiError> {
+        let conn = &self.pool.get()?;
+
+        trash_todo(conn, todo_id, requester_id)
+    }
+
+    fn trashed_todos(&self, requester_id: &str) -> Result<Vec<TodoItem>, TodoApiError> {
+        use self::todos::dsl::*;
+        let conn = &self.pool.get()?;
+
+        let todos_list = todos
+            .filter(user_id.eq(id_param(requester_id)?))
+            .filter(deleted_at.is_not_null())
+            .order(deleted_at.desc())
+            .load::<TodoRow>(conn)?;
+
+        into_items(conn, from_rows(todos_list)?)
+    }
+
+    fn restore_todo(&self, todo_id: &str, requester_id: &str) -> Result<TodoItem, TodoApiError> {
+        use self::todos::dsl::*;
+        let conn = &self.pool.get()?;
+
+        let mut restored: Todo = todos
+            .filter(id.eq(id_param(todo_id)?))
+            .filter(user_id.eq(id_param(requester_id)?))
+            .filter(deleted_at.is_not_null())
+            .first::<TodoRow>(conn)
+            .optional()?
+            .ok_or_else(|| TodoApiError::NotFound("Todo".to_string()))?
+            .try_into()?;
+
+        restored.deleted_at = None;
+        restored.updated_at = chrono::Local::now().naive_local();
+
+        save_todo(conn, &restored)?;
+
+        Ok(into_items(conn, vec![restored])?.remove(0))
+    }
+
+    fn archive_completed_todos(
+        &self,
+        older_than_days: u32,
+        requester_id: &str,
+    ) -> Result<usize, TodoApiError> {
+        use self::todos::dsl::*;
+        let conn = &self.pool.get()?;
+
+        let now = chrono::Local::now().naive_local();
+
+        let completed_before = now - chrono::Duration::days(i64::from(older_than_days));
+
+        let archived_count = diesel::update(
+            todos
+                .filter(user_id.eq(id_param(requester_id)?))
+                .filter(completed.eq(true))
+                .filter(completed_at.le(completed_before))
+                .filter(archived_at.is_null())
+                .filter(deleted_at.is_null()),
+        )
+        .set(archived_at.eq(now))
+        .execute(conn)?;
+
+        Ok(archived_count)
+    }
+
+    fn search_todos(
+        &self,
+        search: SearchQueryDTO,
+        requester_id: &str,
+    ) -> Result<Vec<SearchHit>, TodoApiError> {
+        use self::todos::dsl::*;
+        let conn = &self.pool.get()?;
+
+        // `todos_search` is an FTS5 index of the todos, it isn't in the schema.
+        // Title matches weigh more, as they do with Postgres
+        let rows = diesel::sql_query(
+            "SELECT todos.id AS id, \
+                -bm25(todos_search, 1.0, 0.4) AS rank, \
+                highlight(todos_search, 0, ?, ?) AS highlighted_title, \
+                snippet(todos_search, 1, ?, ?, ' ... ', 15) AS snippet \
+            FROM todos_search JOIN todos ON todos.rowid = todos_search.rowid \
+            WHERE todos_search MATCH ? AND todos.user_id = ? AND todos.deleted_at IS NULL \
+            ORDER BY rank DESC, todos.updated_at DESC \
+            LIMIT ?",
+        )
+        .bind::<diesel::sql_types::Text, _>(HIGHLIGHT_START.to_string())
+        .bind::<diesel::sql_types::Text, _>(HIGHLIGHT_END.to_string())
+        .bind::<diesel::sql_types::Text, _>(HIGHLIGHT_START.to_string())
+        .bind::<diesel::sql_types::Text, _>(HIGHLIGHT_END.to_string())
+        .bind::<diesel::sql_types::Text, _>(search.to_fts5_query())
+        .bind::<diesel::sql_types::Text, _>(id_param(requester_id)?)
+        .bind::<diesel::sql_types::BigInt, _>(search.limit)
+        .load::<SearchRow>(conn)?;
+
+        let ids: Vec<&String> = rows.iter().map(|row| &row.id).collect();
+
+        let todos_list = todos.filter(id.eq_any(ids)).load::<TodoRow>(conn)?;
+
+        let matches = rows
+            .into_iter()
+            .map(|row| {
+                Ok(SearchMatch {
+                    id: Uuid::parse_str(&row.id).map_err(|_| TodoApiError::InternalServerError)?,
+                    rank: row.rank as f32,
+                    highlighted_title: row.highlighted_title,
+                    // Snippets of notes without a match have nothing highlighted
+                    snippet: row
+                        .snippet
+                        .filter(|snippet| snippet.contains(HIGHLIGHT_START)),
+                })
+            })
+            .collect::<Result<Vec<SearchMatch>, TodoApiError>>()?;
+
+        Ok(search_hits(
+            matches,
+            into_items(conn, from_rows(todos_list)?)?,
+        ))
+    }
+
+    fn apply_bulk_operations(
+        &self,
+        bulk: BulkDTO,
+        requester_id: &str,
+    ) -> Result<Vec<BulkResult>, TodoApiError> {
+        let conn = &self.pool.get()?;
+
+        // Each operation runs in a savepoint of its own
+        conn.transaction(|| {
+            let results = bulk
+                .operations
+                .iter()
+                .map(|operation| {
+                    let outcome =
+                        conn.transaction(|| apply_bulk_operation(conn, operation, requester_id));
+
+                    bulk_result(operation, outcome)
+                })
+                .collect();
+
+            Ok(results)
+        })
+    }
+
+    /// Todos written since `since`, a value of the sync sequence. There is
+    /// only one writer, so every write before the next value is already visible
+    fn todo_changes(
+        &self,
+        since: Option<i64>,
+        requester_id: &str,
+    ) -> Result<TodoDelta, TodoApiError> {
+        use self::todos::dsl::*;
+        let conn = &self.pool.get()?;
+
+        let requester_id = id_param(requester_id)?;
+
+        conn.transaction(|| {
+            let next_seq = sync_sequence::table
+                .select(sync_sequence::value)
+                .first::<i64>(conn)?
+                + 1;
+
+            // `sync_seq` is kept up to date by triggers and isn't in the schema
+            let mut query = todos
+                .filter(user_id.eq(requester_id.clone()))
+                .order(sql::<BigInt>("sync_seq").asc())
+                .into_boxed();
+
+            query = match since {
+                Some(since) => query
+                    .filter(sql::<Bool>("sync_seq >= ").bind::<BigInt, _>(since))
+                    .filter(sql::<Bool>("sync_seq < ").bind::<BigInt, _>(next_seq)),
+                None => query.filter(deleted_at.is_null()),
+            };
+
+            let (trashed, changed): (Vec<Todo>, Vec<Todo>) =
+                from_rows::<_, Todo>(query.load::<TodoRow>(conn)?)?
+                    .into_iter()
+                    .partition(|todo| todo.deleted_at.is_some());
+
+            let mut deleted: Vec<Tombstone> = trashed.iter().map(Tombstone::from).collect();
+
+            if let Some(since) = since {
+                use self::todo_tombstones::dsl as tombstones;
+
+                // Todos purged from the trash, their rows are gone
+                let purged = tombstones::todo_tombstones
+                    .filter(tombstones::user_id.eq(requester_id.clone()))
+                    .filter(tombstones::sync_seq.ge(since))
+                    .filter(tombstones::sync_seq.lt(next_seq))
+                    .select((tombstones::todo_id, tombstones::deleted_at))
+                    .load::<(String, NaiveDateTime)>(conn)?;
+
+                for (todo_id, purged_at) in purged {
+                    deleted.push(Tombstone {
+                        id: Uuid::parse_str(&todo_id)
+                            .map_err(|_| TodoApiError::InternalServerError)?,
+                        deleted_at: purged_at,
+                    });
+                }
+            }
+
+            Ok(TodoDelta {
+                changed: into_items(conn, changed)?,
+                deleted,
+                next_token: next_seq.to_string(),
+            })
+        })
+    }
+
+    fn purge_trash(&self, expired_before: NaiveDateTime) -> Result<usize, TodoApiError> {
+        use self::todos::dsl::*;
+        let conn = &self.pool.get()?;
+
+        let purged = diesel::delete(todos.filter(deleted_at.lt(expired_before))).execute(conn)?;
+
+        Ok(purged)
+    }
+}
+
+impl ProjectRepository for SqliteRepository {
+    fn projects(&self, requester_id: &str) -> Result<Vec<Project>, TodoApiError> {
+        use self::projects::dsl::*;
+        let conn = &self.pool.get()?;
+
+        let projects_list = projects
+            .filter(user_id.eq(id_param(requester_id)?))
+            .order(name.asc())
+            .load::<ProjectRow>(conn)?;
+
+        from_rows(projects_list)
+    }
+
+    fn insert_project(
+        &self,
+        project: ProjectDTO,
+        requester_id: &str,
+    ) -> Result<Project, TodoApiError> {
+        use self::projects::dsl::*;
+        let conn = &self.pool.get()?;
+
+        let new_project = Project::from(
+            project.name.trim().to_string(),
+            Uuid::parse_str(requester_id)?,
+        );
+
+        // Names are unique per user, so a duplicate is reported as a bad request
+        diesel::insert_into(projects)
+            .values(&ProjectRow::from(&new_project))
+            .execute(conn)?;
+
+        Ok(new_project)
+    }
+
+    fn get_project(&self, project_id: &str, requester_id: &str) -> Result<Project, TodoApiError> {
+        let conn = &self.pool.get()?;
+
+        verify_project_owner(conn, requester_id, project_id)
+    }
+
+    fn rename_project(
+        &self,
+        project_id: &str,
+        project: ProjectDTO,
+        requester_id: &str,
+    ) -> Result<Project, TodoApiError> {
+        use self::projects::dsl::*;
+        let conn = &self.pool.get()?;
+
+        let mut renamed = verify_project_owner(conn, requester_id, project_id)?;
+
+        renamed.name = project.name.trim().to_string();
+        renamed.updated_at = chrono::Local::now().naive_local();
+
+        diesel::update(projects.filter(id.eq(renamed.id.to_string())))
+            .set((name.eq(&renamed.name), updated_at.eq(renamed.updated_at)))
+            .execute(conn)?;
+
+        Ok(renamed)
+    }
+
+    fn remove_project(&self, project_id: &str, requester_id: &str) -> Result<(), TodoApiError> {
+        use self::projects::dsl::*;
+        let conn = &self.pool.get()?;
+
+        let delete_count = diesel::delete(
+            projects
+                .filter(id.eq(id_param(project_id)?))
+                .filter(user_id.eq(id_param(requester_id)?)),
+        )
+        .execute(conn)?;
+
+        if delete_count == 0 {
+            return Err(TodoApiError::NotFound("Project".to_string()));
+        }
+
+        Ok(())
+    }
+}
+
+impl SubtaskRepository for SqliteRepository {
+    fn subtasks(&self, todo_id: &str, requester_id: &str) -> Result<Vec<Subtask>, TodoApiError> {
+        let conn = &self.pool.get()?;
+
+        let todo = verify_todo_owner(conn, requester_id, todo_id)?;
+
+        subtasks_of(conn, todo.id)
+    }
+
+    fn insert_subtask(
+        &self,
+        todo_id: &str,
+        subtask: SubtaskDTO,
+        requester_id: &str,
+    ) -> Result<Subtask, TodoApiError> {
+        use self::subtasks::dsl;
+        let conn = &self.pool.get()?;
+
+        let todo = verify_todo_owner(conn, requester_id, todo_id)?;
+
+        conn.transaction(|| {
+            let last_position = dsl::subtasks
+                .filter(dsl::todo_id.eq(todo.id.to_string()))
+                .select(diesel::dsl::max(dsl::position))
+                .first::<Option<i32>>(conn)?;
+
+            let new_subtask = Subtask::from(
+                subtask.title.trim().to_string(),
+                todo.id,
+                last_position.map_or(0, |position| position + 1),
+            );
+
+            diesel::insert_into(dsl::subtasks)
+                .values(&SubtaskRow::from(&new_subtask))
+                .execute(conn)?;
+
+            Ok(new_subtask)
+        })
+    }
+
+    fn reorder_subtasks(
+        &self,
+        todo_id: &str,
+        order: ReorderSubtasksDTO,
+        requester_id: &str,
+    ) -> Result<Vec<Subtask>, TodoApiError> {
+        use self::subtasks::dsl;
+        let conn = &self.pool.get()?;
+
+        let todo = verify_todo_owner(conn, requester_id, todo_id)?;
+
+        conn.transaction(|| {
+            let mut current_ids: Vec<Uuid> = subtasks_of(conn, todo.id)?
+                .into_iter()
+                .map(|subtask| subtask.id)
+                .collect();
+
+            let mut requested_ids = order.ids.clone();
+
+            current_ids.sort();
+            requested_ids.sort();
+
+            if current_ids != requested_ids {
+                return Err(TodoApiError::BadRequest(
+                    "Every subtask of the todo has to be listed".into(),
+                ));
+            }
+
+            let now = chrono::Local::now().naive_local();
+
+            for (index, subtask_id) in order.ids.iter().enumerate() {
+                diesel::update(dsl::subtasks.filter(dsl::id.eq(subtask_id.to_string())))
+                    .set((dsl::position.eq(index as i32), dsl::updated_at.eq(now)))
+                    .execute(conn)?;
+            }
+
+            subtasks_of(conn, todo.id)
+        })
+    }
+
+    fn set_subtask_completeness(
+        &self,
+        todo_id: &str,
+        subtask_id: &str,
+        is_complete: bool,
+        requester_id: &str,
+    ) -> Result<(), TodoApiError> {
+        use self::subtasks::dsl;
+        let conn = &self.pool.get()?;
+
+        let todo = verify_todo_owner(conn, requester_id, todo_id)?;
+
+        let update_count = diesel::update(
+            dsl::subtasks
+                .filter(dsl::id.eq(id_param(subtask_id)?))
+                .filter(dsl::todo_id.eq(todo.id.to_string())),
+        )
+        .set((
+            dsl::completed.eq(is_complete),
+            dsl::updated_at.eq(chrono::Local::now().naive_local()),
+        ))
+        .execute(conn)?;
+
+        ensure_subtask_found(update_count)
+    }
+
+    fn remove_subtask(
+        &self,
+        todo_id: &str,
+        subtask_id: &str,
+        requester_id: &str,
+    ) -> Result<(), TodoApiError> {
+        use self::subtasks::dsl;
+        let conn = &self.pool.get()?;
+
+        let todo = verify_todo_owner(conn, requester_id, todo_id)?;
+
+        let delete_count = diesel::delete(
+            dsl::subtasks
+                .filter(dsl::id.eq(id_param(subtask_id)?))
+                .filter(dsl::todo_id.eq(todo.id.to_string())),
+        )
+        .execute(conn)?;
+
+        ensure_subtask_found(delete_count)
+    }
+}
+
+/// Id sent by a client as it is stored, a malformed id
+/// is a bad request as it is with Postgres
+fn id_param(id: &str) -> Result<String, TodoApiError> {
+    Ok(Uuid::parse_str(id)?.to_string())
+}
+
+/// Gets the todo with `todo_id` owned by `requester_id`, todos in the trash are not found
+fn verify_todo_owner(
+    conn: &SqliteConnection,
+    requester_id: &str,
+    todo_id: &str,
+) -> Result<Todo, TodoApiError> {
+    todos::table
+        .filter(todos::id.eq(id_param(todo_id)?))
+        .filter(todos::user_id.eq(id_param(requester_id)?))
+        .filter(todos::deleted_at.is_null())
+        .first::<TodoRow>(conn)
+        .optional()?
+        .ok_or_else(|| TodoApiError::NotFound("Todo".to_string()))?
+        .try_into()
+}
+
+/// Gets the project with `project_id` owned by `requester_id`
+fn verify_project_owner(
+    conn: &SqliteConnection,
+    requester_id: &str,
+    project_id: &str,
+) -> Result<Project, TodoApiError> {
+    projects::table
+        .filter(projects::id.eq(id_param(project_id)?))
+        .filter(projects::user_id.eq(id_param(requester_id)?))
+        .first::<ProjectRow>(conn)
+        .optional()?
+        .ok_or_else(|| TodoApiError::NotFound("Project".to_string()))?
+        .try_into()
+}
+
+/// Write every column of a loaded todo back, diesel can't
+/// return the updated row from SQLite so the todo is changed first
+fn save_todo(conn: &SqliteConnection, todo: &Todo) -> Result<(), TodoApiError> {
+    diesel::update(todos::table.filter(todos::id.eq(todo.id.to_string())))
+        .set(&TodoRow::from(todo))
+        .execute(conn)?;
+
+    Ok(())
+}
+
+/// Move a todo owned by the requester to the trash
+fn trash_todo(
+    conn: &SqliteConnection,
+    todo_id: &str,
+    requester_id: &str,
+) -> Result<(), TodoApiError> {
+    use self::todos::dsl::*;
+
+    let delete_count = diesel::update(
+        todos
+            .filter(id.eq(id_param(todo_id)?))
+            .filter(user_id.eq(id_param(requester_id)?))
+            .filter(deleted_at.is_null()),
+    )
+    .set(deleted_at.eq(chrono::Local::now().naive_local()))
+    .execute(conn)?;
+
+    ensure_todo_found(delete_count)
+}
+
+/// Complete or reopen a todo owned by the requester, completing
+/// a recurring todo creates its next occurrence
+fn set_todo_completeness(
+    conn: &SqliteConnection,
+    todo_id: &str,
+    is_complete: bool,
+    requester_id: &str,
+) -> Result<Todo, TodoApiError> {
+    conn.transaction(|| {
+        let mut updated = verify_todo_owner(conn, requester_id, todo_id)?;
+
+        let was_complete = updated.completed;
+
+        let now = chrono::Local::now().naive_local();
+
+        updated.set_completed(is_complete, now);
+        updated.updated_at = now;
+
+        // Reopened todos can't stay hidden in the archive
+        if !is_complete {
+            updated.archived_at = None;
+        }
+
+        save_todo(conn, &updated)?;
+
+        if updated.completed && !was_complete {
+            spawn_next_occurrence(conn, &updated)?;
+        }
+
+        Ok(updated)
+    })
+}
+
+/// Apply a single operation of a bulk request,
+/// returns the todo as it is afterwards unless it was deleted
+fn apply_bulk_operation(
+    conn: &SqliteConnection,
+    operation: &BulkOperation,
+    requester_id: &str,
+) -> Result<Option<TodoItem>, TodoApiError> {
+    let todo_id = operation.todo_id().to_string();
+
+    let updated = match operation {
+        BulkOperation::Complete { .. } => {
+            set_todo_completeness(conn, &todo_id, true, requester_id)?
+        }
+        BulkOperation::Incomplete { .. } => {
+            set_todo_completeness(conn, &todo_id, false, requester_id)?
+        }
+        BulkOperation::Delete { .. } => {
+            trash_todo(conn, &todo_id, requester_id)?;
+
+            return Ok(None);
+        }
+        BulkOperation::Retag { add, remove, .. } => {
+            let mut todo = verify_todo_owner(conn, requester_id, &todo_id)?;
+
+            let names = tags_for_todos(conn, &[todo.id])?
+                .remove(&todo.id)
+                .unwrap_or_default();
+
+            set_todo_tags(conn, todo.user_id, todo.id, &retagged(names, add, remove))?;
+
+            todo.updated_at = chrono::Local::now().naive_local();
+
+            save_todo(conn, &todo)?;
+
+            todo
+        }
+        BulkOperation::Move { project_id, .. } => {
+            if let Some(project) = project_id {
+                verify_project_owner(conn, requester_id, project.to_string().as_str())?;
+            }
+
+            let mut todo = verify_todo_owner(conn, requester_id, &todo_id)?;
+
+            todo.project_id = *project_id;
+            todo.updated_at = chrono::Local::now().naive_local();
+
+            save_todo(conn, &todo)?;
+
+            todo
+        }
+    };
+
+    Ok(Some(into_items(conn, vec![updated])?.remove(0)))
+}
+
+/// Create the todo that follows `completed` in its recurring series,
+/// a series only ever has one open todo
+fn spawn_next_occurrence(conn: &SqliteConnection, completed: &Todo) -> Result<(), TodoApiError> {
+    use self::todos::dsl;
+
+    let recurrence = match &completed.recurrence {
+        Some(recurrence) => recurrence,
+        None => return Ok(()),
+    };
+
+    // Todos that were made recurring after they were created start their series here
+    let series = match completed.series_id {
+        Some(series) => series,
+        None => {
+            diesel::update(dsl::todos.filter(dsl::id.eq(completed.id.to_string())))
+                .set(dsl::series_id.eq(completed.id.to_string()))
+                .execute(conn)?;
+
+            completed.id
+        }
+    };
+
+    let open_count: i64 = dsl::todos
+        .filter(dsl::series_id.eq(series.to_string()))
+        .filter(dsl::completed.eq(false))
+        .filter(dsl::deleted_at.is_null())
+        .count()
+        .get_result(conn)?;
+
+    if open_count > 0 {
+        return Ok(());
+    }
+
+    let next = next_occurrence(completed, recurrence, series);
+
+    diesel::insert_into(dsl::todos)
+        .values(&TodoRow::from(&next))
+        .execute(conn)?;
+
+    let tags = tags_for_todos(conn, &[completed.id])?
+        .remove(&completed.id)
+        .unwrap_or_default();
+
+    set_todo_tags(conn, next.user_id, next.id, &tags)?;
+
+    copy_subtasks(conn, completed.id, next.id)
+}
+
+/// Position between `anchor` and its neighbour on the side `todo` is moved to,
+/// `None` when there is no room left between them
+fn free_position_next_to(
+    conn: &SqliteConnection,
+    todo: &Todo,
+    anchor: &Todo,
+    before: bool,
+) -> Result<Option<i64>, TodoApiError> {
+    use self::todos::dsl::*;
+
+    let others = || {
+        todos
+            .filter(user_id.eq(anchor.user_id.to_string()))
+            .filter(id.ne(todo.id.to_string()))
+    };
+
+    // Todos sharing a position can't be told apart, so they need rebalancing
+    let shared_count: i64 = others()
+        .filter(id.ne(anchor.id.to_string()))
+        .filter(position.eq(anchor.position))
+        .count()
+        .get_result(conn)?;
+
+    if shared_count > 0 {
+        return Ok(None);
+    }
+
+    let neighbour = if before {
+        others()
+            .filter(position.lt(anchor.position))
+            .select(diesel::dsl::max(position))
+            .first::<Option<i64>>(conn)?
+    } else {
+        others()
+            .filter(position.gt(anchor.position))
+            .select(diesel::dsl::min(position))
+            .first::<Option<i64>>(conn)?
+    };
+
+    Ok(position_next_to(anchor.position, neighbour, before))
+}
+
+/// Space the positions of all todos of a user evenly, keeping their order
+fn rebalance_positions(conn: &SqliteConnection, owner_id: Uuid) -> Result<(), TodoApiError> {
+    use self::todos::dsl::*;
+
+    let ordered_ids = todos
+        .filter(user_id.eq(owner_id.to_string()))
+        .order((position.asc(), created_at.desc()))
+        .select(id)
+        .load::<String>(conn)?;
+
+    for (index, todo_id) in ordered_ids.iter().enumerate() {
+        diesel::update(todos.filter(id.eq(todo_id)))
+            .set(position.eq((index as i64 + 1) * POSITION_GAP))
+            .execute(conn)?;
+    }
+
+    Ok(())
+}
+
+type TodoPredicate = Box<dyn BoxableExpression<todos::table, Sqlite, SqlType = Bool>>;
+
+/// Matches the todos that come after the keys of `last_seen` in `sort` order,
+/// see the Postgres version for how the keys are combined
+fn after_cursor(sort: TodoSort, last_seen: &TodoCursor) -> TodoPredicate {
+    use self::todos::dsl::*;
+
+    // Each key as (after, equal), `id` is the last key and is left out
+    let completed_key: (TodoPredicate, TodoPredicate) = (
+        Box::new(completed.gt(last_seen.completed)),
+        Box::new(completed.eq(last_seen.completed)),
+    );
+    let created_key = || -> (TodoPredicate, TodoPredicate) {
+        (
+            Box::new(created_at.lt(last_seen.created_at)),
+            Box::new(created_at.eq(last_seen.created_at)),
+        )
+    };
+    // Todos without a due date come last
+    let due_key = || -> (TodoPredicate, TodoPredicate) {
+        match last_seen.due_at {
+            Some(due) => (
+                Box::new(due_at.gt(due).or(due_at.is_null())),
+                Box::new(due_at.eq(due)),
+            ),
+            None => (
+                Box::new(diesel::dsl::sql::<Bool>("FALSE")),
+                Box::new(due_at.is_null()),
+            ),
+        }
+    };
+
+    let keys: Vec<(TodoPredicate, TodoPredicate)> = match sort {
+        TodoSort::Manual => vec![
+            completed_key,
+            (
+                Box::new(position.gt(last_seen.position)),
+                Box::new(position.eq(last_seen.position)),
+            ),
+            created_key(),
+        ],
+        TodoSort::Priority => vec![
+            completed_key,
+            (
+                Box::new(priority.lt(last_seen.priority)),
+                Box::new(priority.eq(last_seen.priority)),
+            ),
+            due_key(),
+            created_key(),
+        ],
+        TodoSort::Created => vec![completed_key, created_key()],
+        TodoSort::Updated => vec![
+            completed_key,
+            (
+                Box::new(updated_at.lt(last_seen.updated_at)),
+                Box::new(updated_at.eq(last_seen.updated_at)),
+            ),
+        ],
+        TodoSort::Due => vec![completed_key, due_key()],
+        TodoSort::Title => vec![
+            completed_key,
+            (
+                Box::new(title.gt(last_seen.title.clone())),
+                Box::new(title.eq(last_seen.title.clone())),
+            ),
+        ],
+    };
+
+    keys.into_iter().rev().fold(
+        Box::new(id.gt(last_seen.id.to_string())),
+        |rest, (after, equal)| Box::new(after.or(equal.and(rest))),
+    )
+}
+
+/// Search match as returned by the search query
+#[derive(QueryableByName)]
+struct SearchRow {
+    #[sql_type = "diesel::sql_types::Text"]
+    id: String,
+    #[sql_type = "diesel::sql_types::Double"]
+    rank: f64,
+    #[sql_type = "diesel::sql_types::Text"]
+    highlighted_title: String,
+    #[sql_type = "diesel::sql_types::Nullable<diesel::sql_types::Text>"]
+    snippet: Option<String>,
+}
+
+/// Names of the tags of each of the given todos, sorted by name
+fn tags_for_todos(
+    conn: &SqliteConnection,
+    todo_ids: &[Uuid],
+) -> Result<HashMap<Uuid, Vec<String>>, TodoApiError> {
+    let ids: Vec<String> = todo_ids.iter().map(Uuid::to_string).collect();
+
+    let rows: Vec<(String, String)> = todo_tags::table
+        .inner_join(tags::table)
+        .filter(todo_tags::todo_id.eq_any(ids))
+        .select((todo_tags::todo_id, tags::name))
+        .order(tags::name.asc())
+        .load(conn)?;
+
+    let mut tags_by_todo: HashMap<Uuid, Vec<String>> = HashMap::new();
+
+    for (todo_id, name) in rows {
+        if let Ok(todo_id) = Uuid::parse_str(&todo_id) {
+            tags_by_todo.entry(todo_id).or_default().push(name);
+        }
+    }
+
+    Ok(tags_by_todo)
+}
+
+/// Replace the tags of a todo with `names`,
+/// tags that don't exist yet are created for the user
+fn set_todo_tags(
+    conn: &SqliteConnection,
+    owner_id: Uuid,
+    todo_id: Uuid,
+    names: &[String],
+) -> Result<(), TodoApiError> {
+    let owner_id = owner_id.to_string();
+    let todo_id = todo_id.to_string();
+
+    diesel::delete(todo_tags::table.filter(todo_tags::todo_id.eq(&todo_id))).execute(conn)?;
+
+    if names.is_empty() {
+        return Ok(());
+    }
+
+    let now = chrono::Local::now().naive_local();
+
+    let new_tags: Vec<TagRow> = names
+        .iter()
+        .map(|name| TagRow {
+            id: Uuid::new_v4().to_string(),
+            name: name.to_owned(),
+            user_id: owner_id.clone(),
+            created_at: now,
+        })
+        .collect();
+
+    // Tags the user already has are left as they are
+    for new_tag in &new_tags {
+        diesel::insert_or_ignore_into(tags::table)
+            .values(new_tag)
+            .execute(conn)?;
+    }
+
+    let links: Vec<TodoTagRow> = tags::table
+        .filter(tags::user_id.eq(&owner_id))
+        .filter(tags::name.eq_any(names))
+        .select(tags::id)
+        .load::<String>(conn)?
+        .into_iter()
+        .map(|tag_id| TodoTagRow {
+            todo_id: todo_id.clone(),
+            tag_id,
+        })
+        .collect();
+
+    for link in &links {
+        diesel::insert_into(todo_tags::table)
+            .values(link)
+            .execute(conn)?;
+    }
+
+    Ok(())
+}
+
+/// Subtasks of a todo in checklist order
+fn subtasks_of(conn: &SqliteConnection, todo_id: Uuid) -> Result<Vec<Subtask>, TodoApiError> {
+    let list = subtasks::table
+        .filter(subtasks::todo_id.eq(todo_id.to_string()))
+        .order(subtasks::position.asc())
+        .load::<SubtaskRow>(conn)?;
+
+    from_rows(list)
+}
+
+/// Subtask progress of each of the given todos,
+/// todos without subtasks are left out
+fn progress_for_todos(
+    conn: &SqliteConnection,
+    todo_ids: &[Uuid],
+) -> Result<HashMap<Uuid, Progress>, TodoApiError> {
+    let ids: Vec<String> = todo_ids.iter().map(Uuid::to_string).collect();
+
+    let rows: Vec<(String, bool)> = subtasks::table
+        .filter(subtasks::todo_id.eq_any(ids))
+        .select((subtasks::todo_id, subtasks::completed))
+        .load(conn)?;
+
+    let mut progress_by_todo: HashMap<Uuid, Progress> = HashMap::new();
+
+    for (todo_id, completed) in rows {
+        let todo_id = match Uuid::parse_str(&todo_id) {
+            Ok(todo_id) => todo_id,
+            Err(_) => continue,
+        };
+
+        let progress = progress_by_todo.entry(todo_id).or_default();
+
+        progress.total += 1;
+
+        if completed {
+            progress.done += 1;
+        }
+    }
+
+    Ok(progress_by_todo)
+}
+
+/// Copy the subtasks of one todo to another, unchecked
+fn copy_subtasks(
+    conn: &SqliteConnection,
+    from_todo_id: Uuid,
+    to_todo_id: Uuid,
+) -> Result<(), TodoApiError> {
+    for subtask in subtasks_of(conn, from_todo_id)? {
+        let copy = Subtask::from(subtask.title, to_todo_id, subtask.position);
+
+        diesel::insert_into(subtasks::table)
+            .values(&SubtaskRow::from(&copy))
+            .execute(conn)?;
+    }
+
+    Ok(())
+}
+
+/// Attach the data stored in other tables to `todos_list`
+fn into_items(
+    conn: &SqliteConnection,
+    todos_list: Vec<Todo>,
+) -> Result<Vec<TodoItem>, TodoApiError> {
+    let ids: Vec<Uuid> = todos_list.iter().map(|todo| todo.id).collect();
+
+    let mut tags_by_todo = tags_for_todos(conn, &ids)?;
+
+    let mut progress_by_todo = progress_for_todos(conn, &ids)?;
+
+    Ok(todos_list
+        .into_iter()
+        .map(|todo| TodoItem {
+            tags: tags_by_todo.remove(&todo.id).unwrap_or_default(),
+            progress: progress_by_todo.remove(&todo.id).unwrap_or_default(),
+            todo,
+        })
+        .collect())
+}
@@ -0,0 +1,353 @@
+//! Rows of the SQLite tables. Ids are stored as text, so the models
+//! are converted to and from these rows on their way in and out
+
+use std::convert::TryFrom;
+
+use chrono::NaiveDateTime;
+use uuid::Uuid;
+
+use super::schema::*;
+use crate::api::errors::TodoApiError;
+use crate::models::{
+    email_verification_model::EmailVerification,
+    password_reset_model::PasswordReset,
+    project_model::Project,
+    session_model::Session,
+    subtask_model::Subtask,
+    todo_model::{Priority, Recurrence, Todo},
+    user_model::User,
+};
+
+/// Id read back from the database, one that doesn't parse means broken data
+fn stored_id(id: &str) -> Result<Uuid, TodoApiError> {
+    Uuid::parse_str(id).map_err(|_| TodoApiError::InternalServerError)
+}
+
+/// Convert the rows loaded by a query to models
+pub fn from_rows<R, T>(rows: Vec<R>) -> Result<Vec<T>, TodoApiError>
+where
+    T: TryFrom<R, Error = TodoApiError>,
+{
+    rows.into_iter().map(T::try_from).collect()
+}
+
+#[derive(Queryable, Insertable)]
+#[table_name = "users"]
+pub struct UserRow {
+    pub id: String,
+    pub email: String,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+    pub password: String,
+    pub name: String,
+    pub email_verified_at: Option<NaiveDateTime>,
+}
+
+impl From<&User> for UserRow {
+    fn from(user: &User) -> Self {
+        Self {
+            id: user.id.to_string(),
+            email: user.email.clone(),
+            created_at: user.created_at,
+            updated_at: user.updated_at,
+            password: user.password.clone(),
+            name: user.name.clone(),
+            email_verified_at: user.email_verified_at,
+        }
+    }
+}
+
+impl TryFrom<UserRow> for User {
+    type Error = TodoApiError;
+
+    fn try_from(row: UserRow) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: stored_id(&row.id)?,
+            email: row.email,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+            password: row.password,
+            name: row.name,
+            email_verified_at: row.email_verified_at,
+        })
+    }
+}
+
+#[derive(Queryable, Insertable)]
+#[table_name = "sessions"]
+pub struct SessionRow {
+    pub id: String,
+    pub user_id: String,
+    pub refresh_token_hash: String,
+    pub created_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+    pub revoked_at: Option<NaiveDateTime>,
+}
+
+impl From<&Session> for SessionRow {
+    fn from(session: &Session) -> Self {
+        Self {
+            id: session.id.to_string(),
+            user_id: session.user_id.to_string(),
+            refresh_token_hash: session.refresh_token_hash.clone(),
+            created_at: session.created_at,
+            expires_at: session.expires_at,
+            revoked_at: session.revoked_at,
+        }
+    }
+}
+
+impl TryFrom<SessionRow> for Session {
+    type Error = TodoApiError;
+
+    fn try_from(row: SessionRow) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: stored_id(&row.id)?,
+            user_id: stored_id(&row.user_id)?,
+            refresh_token_hash: row.refresh_token_hash,
+            created_at: row.created_at,
+            expires_at: row.expires_at,
+            revoked_at: row.revoked_at,
+        })
+    }
+}
+
+#[derive(Queryable, Insertable)]
+#[table_name = "email_verifications"]
+pub struct EmailVerificationRow {
+    pub id: String,
+    pub user_id: String,
+    pub token_hash: String,
+    pub created_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+}
+
+impl From<&EmailVerification> for EmailVerificationRow {
+    fn from(verification: &EmailVerification) -> Self {
+        Self {
+            id: verification.id.to_string(),
+            user_id: verification.user_id.to_string(),
+            token_hash: verification.token_hash.clone(),
+            created_at: verification.created_at,
+            expires_at: verification.expires_at,
+        }
+    }
+}
+
+impl TryFrom<EmailVerificationRow> for EmailVerification {
+    type Error = TodoApiError;
+
+    fn try_from(row: EmailVerificationRow) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: stored_id(&row.id)?,
+            user_id: stored_id(&row.user_id)?,
+            token_hash: row.token_hash,
+            created_at: row.created_at,
+            expires_at: row.expires_at,
+        })
+    }
+}
+
+#[derive(Queryable, Insertable)]
+#[table_name = "password_resets"]
+pub struct PasswordResetRow {
+    pub id: String,
+    pub user_id: String,
+    pub token_hash: String,
+    pub created_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+    pub used_at: Option<NaiveDateTime>,
+}
+
+impl From<&PasswordReset> for PasswordResetRow {
+    fn from(reset: &PasswordReset) -> Self {
+        Self {
+            id: reset.id.to_string(),
+            user_id: reset.user_id.to_string(),
+            token_hash: reset.token_hash.clone(),
+            created_at: reset.created_at,
+            expires_at: reset.expires_at,
+            used_at: reset.used_at,
+        }
+    }
+}
+
+impl TryFrom<PasswordResetRow> for PasswordReset {
+    type Error = TodoApiError;
+
+    fn try_from(row: PasswordResetRow) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: stored_id(&row.id)?,
+            user_id: stored_id(&row.user_id)?,
+            token_hash: row.token_hash,
+            created_at: row.created_at,
+            expires_at: row.expires_at,
+            used_at: row.used_at,
+        })
+    }
+}
+
+#[derive(Queryable, Insertable)]
+#[table_name = "projects"]
+pub struct ProjectRow {
+    pub id: String,
+    pub name: String,
+    pub user_id: String,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+impl From<&Project> for ProjectRow {
+    fn from(project: &Project) -> Self {
+        Self {
+            id: project.id.to_string(),
+            name: project.name.clone(),
+            user_id: project.user_id.to_string(),
+            created_at: project.created_at,
+            updated_at: project.updated_at,
+        }
+    }
+}
+
+impl TryFrom<ProjectRow> for Project {
+    type Error = TodoApiError;
+
+    fn try_from(row: ProjectRow) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: stored_id(&row.id)?,
+            name: row.name,
+            user_id: stored_id(&row.user_id)?,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+        })
+    }
+}
+
+/// A todo row, also used to write back every column of a loaded todo
+#[derive(Queryable, Insertable, AsChangeset)]
+#[table_name = "todos"]
+#[changeset_options(treat_none_as_null = "true")]
+pub struct TodoRow {
+    pub id: String,
+    pub title: String,
+    pub completed: bool,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+    pub user_id: String,
+    pub due_at: Option<NaiveDateTime>,
+    pub priority: Priority,
+    pub project_id: Option<String>,
+    pub recurrence: Option<Recurrence>,
+    pub series_id: Option<String>,
+    pub notes: Option<String>,
+    pub position: i64,
+    pub deleted_at: Option<NaiveDateTime>,
+    pub archived_at: Option<NaiveDateTime>,
+    pub completed_at: Option<NaiveDateTime>,
+}
+
+impl From<&Todo> for TodoRow {
+    fn from(todo: &Todo) -> Self {
+        Self {
+            id: todo.id.to_string(),
+            title: todo.title.clone(),
+            completed: todo.completed,
+            created_at: todo.created_at,
+            updated_at: todo.updated_at,
+            user_id: todo.user_id.to_string(),
+            due_at: todo.due_at,
+            priority: todo.priority,
+            project_id: todo.project_id.map(|project_id| project_id.to_string()),
+            recurrence: todo.recurrence.clone(),
+            series_id: todo.series_id.map(|series_id| series_id.to_string()),
+            notes: todo.notes.clone(),
+            position: todo.position,
+            deleted_at: todo.deleted_at,
+            archived_at: todo.archived_at,
+            completed_at: todo.completed_at,
+        }
+    }
+}
+
+impl TryFrom<TodoRow> for Todo {
+    type Error = TodoApiError;
+
+    fn try_from(row: TodoRow) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: stored_id(&row.id)?,
+            title: row.title,
+            completed: row.completed,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+            user_id: stored_id(&row.user_id)?,
+            due_at: row.due_at,
+            priority: row.priority,
+            project_id: row.project_id.as_deref().map(stored_id).transpose()?,
+            recurrence: row.recurrence,
+            series_id: row.series_id.as_deref().map(stored_id).transpose()?,
+            notes: row.notes,
+            position: row.position,
+            deleted_at: row.deleted_at,
+            archived_at: row.archived_at,
+            completed_at: row.completed_at,
+        })
+    }
+}
+
+#[derive(Queryable, Insertable)]
+#[table_name = "subtasks"]
+pub struct SubtaskRow {
+    pub id: String,
+    pub todo_id: String,
+    pub title: String,
+    pub completed: bool,
+    pub position: i32,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+impl From<&Subtask> for SubtaskRow {
+    fn from(subtask: &Subtask) -> Self {
+        Self {
+            id: subtask.id.to_string(),
+            todo_id: subtask.todo_id.to_string(),
+            title: subtask.title.clone(),
+            completed: subtask.completed,
+            position: subtask.position,
+            created_at: subtask.created_at,
+            updated_at: subtask.updated_at,
+        }
+    }
+}
+
+impl TryFrom<SubtaskRow> for Subtask {
+    type Error = TodoApiError;
+
+    fn try_from(row: SubtaskRow) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: stored_id(&row.id)?,
+            todo_id: stored_id(&row.todo_id)?,
+            title: row.title,
+            completed: row.completed,
+            position: row.position,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+        })
+    }
+}
+
+#[derive(Insertable)]
+#[table_name = "tags"]
+pub struct TagRow {
+    pub id: String,
+    pub name: String,
+    pub user_id: String,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[table_name = "todo_tags"]
+pub struct TodoTagRow {
+    pub todo_id: String,
+    pub tag_id: String,
+}
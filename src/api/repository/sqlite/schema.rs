@@ -0,0 +1,144 @@
+//! Tables of `setup.sql`, the Postgres schema with ids stored as text
+
+diesel::table! {
+    email_verifications (id) {
+        id -> Text,
+        user_id -> Text,
+        token_hash -> Text,
+        created_at -> Timestamp,
+        expires_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    password_resets (id) {
+        id -> Text,
+        user_id -> Text,
+        token_hash -> Text,
+        created_at -> Timestamp,
+        expires_at -> Timestamp,
+        used_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    projects (id) {
+        id -> Text,
+        name -> Text,
+        user_id -> Text,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    sessions (id) {
+        id -> Text,
+        user_id -> Text,
+        refresh_token_hash -> Text,
+        created_at -> Timestamp,
+        expires_at -> Timestamp,
+        revoked_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    subtasks (id) {
+        id -> Text,
+        todo_id -> Text,
+        title -> Text,
+        completed -> Bool,
+        position -> Integer,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    tags (id) {
+        id -> Text,
+        name -> Text,
+        user_id -> Text,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    todo_tags (todo_id, tag_id) {
+        todo_id -> Text,
+        tag_id -> Text,
+    }
+}
+
+diesel::table! {
+    todo_tombstones (todo_id) {
+        todo_id -> Text,
+        user_id -> Text,
+        deleted_at -> Timestamp,
+        sync_seq -> BigInt,
+    }
+}
+
+diesel::table! {
+    todos (id) {
+        id -> Text,
+        title -> Text,
+        completed -> Bool,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+        user_id -> Text,
+        due_at -> Nullable<Timestamp>,
+        priority -> SmallInt,
+        project_id -> Nullable<Text>,
+        recurrence -> Nullable<Text>,
+        series_id -> Nullable<Text>,
+        notes -> Nullable<Text>,
+        position -> BigInt,
+        deleted_at -> Nullable<Timestamp>,
+        archived_at -> Nullable<Timestamp>,
+        completed_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    users (id) {
+        id -> Text,
+        email -> Text,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+        password -> Text,
+        name -> Text,
+        email_verified_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    sync_sequence (id) {
+        id -> Integer,
+        value -> BigInt,
+    }
+}
+
+diesel::joinable!(email_verifications -> users (user_id));
+diesel::joinable!(password_resets -> users (user_id));
+diesel::joinable!(projects -> users (user_id));
+diesel::joinable!(sessions -> users (user_id));
+diesel::joinable!(subtasks -> todos (todo_id));
+diesel::joinable!(tags -> users (user_id));
+diesel::joinable!(todo_tags -> tags (tag_id));
+diesel::joinable!(todo_tags -> todos (todo_id));
+diesel::joinable!(todos -> projects (project_id));
+diesel::joinable!(todos -> users (user_id));
+
+diesel::allow_tables_to_appear_in_same_query!(
+    email_verifications,
+    password_resets,
+    projects,
+    sessions,
+    subtasks,
+    tags,
+    todo_tags,
+    todo_tombstones,
+    todos,
+    users,
+);
@@ -0,0 +1,203 @@
+-- Schema of the SQLite database, the same tables as the Postgres
+-- migrations with ids stored as text. Run every time the server
+-- starts, so every statement has to be safe to run again
+
+CREATE TABLE IF NOT EXISTS users (
+    id TEXT NOT NULL PRIMARY KEY,
+    email TEXT NOT NULL UNIQUE,
+    created_at TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP NOT NULL,
+    password TEXT NOT NULL,
+    name TEXT NOT NULL,
+    email_verified_at TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS sessions (
+    id TEXT NOT NULL PRIMARY KEY,
+    user_id TEXT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    refresh_token_hash TEXT NOT NULL UNIQUE,
+    created_at TIMESTAMP NOT NULL,
+    expires_at TIMESTAMP NOT NULL,
+    revoked_at TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS sessions_user_id_idx ON sessions (user_id);
+
+CREATE TABLE IF NOT EXISTS email_verifications (
+    id TEXT NOT NULL PRIMARY KEY,
+    user_id TEXT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    token_hash TEXT NOT NULL UNIQUE,
+    created_at TIMESTAMP NOT NULL,
+    expires_at TIMESTAMP NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS email_verifications_user_id_idx ON email_verifications (user_id);
+
+CREATE TABLE IF NOT EXISTS password_resets (
+    id TEXT NOT NULL PRIMARY KEY,
+    user_id TEXT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    token_hash TEXT NOT NULL UNIQUE,
+    created_at TIMESTAMP NOT NULL,
+    expires_at TIMESTAMP NOT NULL,
+    used_at TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS password_resets_user_id_idx ON password_resets (user_id);
+
+CREATE TABLE IF NOT EXISTS projects (
+    id TEXT NOT NULL PRIMARY KEY,
+    name TEXT NOT NULL,
+    user_id TEXT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    created_at TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP NOT NULL,
+    UNIQUE (user_id, name)
+);
+
+CREATE TABLE IF NOT EXISTS todos (
+    id TEXT NOT NULL PRIMARY KEY,
+    title TEXT NOT NULL,
+    completed BOOLEAN NOT NULL,
+    created_at TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP NOT NULL,
+    user_id TEXT NOT NULL REFERENCES users (id),
+    due_at TIMESTAMP,
+    priority SMALLINT NOT NULL DEFAULT 0,
+    project_id TEXT REFERENCES projects (id) ON DELETE SET NULL,
+    recurrence TEXT,
+    series_id TEXT,
+    notes TEXT,
+    position BIGINT NOT NULL DEFAULT 0,
+    deleted_at TIMESTAMP,
+    archived_at TIMESTAMP,
+    completed_at TIMESTAMP,
+    -- Value of `sync_sequence` when the todo was last written,
+    -- set by the triggers below and left out of the schema
+    sync_seq BIGINT NOT NULL DEFAULT 0
+);
+
+CREATE INDEX IF NOT EXISTS todos_user_id_position_idx ON todos (user_id, position);
+CREATE INDEX IF NOT EXISTS todos_user_id_due_at_idx ON todos (user_id, due_at);
+CREATE INDEX IF NOT EXISTS todos_user_id_sync_seq_idx ON todos (user_id, sync_seq);
+CREATE INDEX IF NOT EXISTS todos_project_id_idx ON todos (project_id);
+CREATE INDEX IF NOT EXISTS todos_series_id_idx ON todos (series_id);
+
+CREATE TABLE IF NOT EXISTS tags (
+    id TEXT NOT NULL PRIMARY KEY,
+    name TEXT NOT NULL,
+    user_id TEXT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    created_at TIMESTAMP NOT NULL,
+    UNIQUE (user_id, name)
+);
+
+CREATE TABLE IF NOT EXISTS todo_tags (
+    todo_id TEXT NOT NULL REFERENCES todos (id) ON DELETE CASCADE,
+    tag_id TEXT NOT NULL REFERENCES tags (id) ON DELETE CASCADE,
+    PRIMARY KEY (todo_id, tag_id)
+);
+
+CREATE INDEX IF NOT EXISTS todo_tags_tag_id_idx ON todo_tags (tag_id);
+
+CREATE TABLE IF NOT EXISTS subtasks (
+    id TEXT NOT NULL PRIMARY KEY,
+    todo_id TEXT NOT NULL REFERENCES todos (id) ON DELETE CASCADE,
+    title TEXT NOT NULL,
+    completed BOOLEAN NOT NULL DEFAULT 0,
+    position INTEGER NOT NULL,
+    created_at TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS subtasks_todo_id_position_idx ON subtasks (todo_id, position);
+
+-- Full-text index of the titles and notes, kept in sync by the triggers below
+CREATE VIRTUAL TABLE IF NOT EXISTS todos_search USING fts5 (
+    title,
+    notes,
+    content = 'todos',
+    content_rowid = 'rowid',
+    tokenize = 'porter unicode61'
+);
+
+CREATE TRIGGER IF NOT EXISTS todos_search_insert AFTER INSERT ON todos BEGIN
+    INSERT INTO todos_search (rowid, title, notes) VALUES (NEW.rowid, NEW.title, NEW.notes);
+END;
+
+CREATE TRIGGER IF NOT EXISTS todos_search_delete AFTER DELETE ON todos BEGIN
+    INSERT INTO todos_search (todos_search, rowid, title, notes)
+        VALUES ('delete', OLD.rowid, OLD.title, OLD.notes);
+END;
+
+CREATE TRIGGER IF NOT EXISTS todos_search_update AFTER UPDATE OF title, notes ON todos BEGIN
+    INSERT INTO todos_search (todos_search, rowid, title, notes)
+        VALUES ('delete', OLD.rowid, OLD.title, OLD.notes);
+    INSERT INTO todos_search (rowid, title, notes) VALUES (NEW.rowid, NEW.title, NEW.notes);
+END;
+
+-- Counter standing in for the transaction ids Postgres hands out as sync
+-- tokens, every write to a todo takes the next value
+CREATE TABLE IF NOT EXISTS sync_sequence (
+    id INTEGER NOT NULL PRIMARY KEY CHECK (id = 1),
+    value BIGINT NOT NULL
+);
+
+INSERT OR IGNORE INTO sync_sequence (id, value) VALUES (1, 0);
+
+CREATE TRIGGER IF NOT EXISTS todos_set_sync_seq_insert AFTER INSERT ON todos BEGIN
+    UPDATE sync_sequence SET value = value + 1;
+    UPDATE todos SET sync_seq = (SELECT value FROM sync_sequence) WHERE rowid = NEW.rowid;
+END;
+
+-- The update made by the trigger itself changes `sync_seq` and is left alone
+CREATE TRIGGER IF NOT EXISTS todos_set_sync_seq_update AFTER UPDATE ON todos
+    WHEN NEW.sync_seq = OLD.sync_seq
+BEGIN
+    UPDATE sync_sequence SET value = value + 1;
+    UPDATE todos SET sync_seq = (SELECT value FROM sync_sequence) WHERE rowid = NEW.rowid;
+END;
+
+-- Tags and subtasks are part of a todo for clients, changing them touches the todo
+CREATE TRIGGER IF NOT EXISTS todo_tags_touch_todo_insert AFTER INSERT ON todo_tags BEGIN
+    UPDATE sync_sequence SET value = value + 1;
+    UPDATE todos SET sync_seq = (SELECT value FROM sync_sequence) WHERE id = NEW.todo_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS todo_tags_touch_todo_delete AFTER DELETE ON todo_tags BEGIN
+    UPDATE sync_sequence SET value = value + 1;
+    UPDATE todos SET sync_seq = (SELECT value FROM sync_sequence) WHERE id = OLD.todo_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS subtasks_touch_todo_insert AFTER INSERT ON subtasks BEGIN
+    UPDATE sync_sequence SET value = value + 1;
+    UPDATE todos SET sync_seq = (SELECT value FROM sync_sequence) WHERE id = NEW.todo_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS subtasks_touch_todo_update AFTER UPDATE ON subtasks BEGIN
+    UPDATE sync_sequence SET value = value + 1;
+    UPDATE todos SET sync_seq = (SELECT value FROM sync_sequence) WHERE id = NEW.todo_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS subtasks_touch_todo_delete AFTER DELETE ON subtasks BEGIN
+    UPDATE sync_sequence SET value = value + 1;
+    UPDATE todos SET sync_seq = (SELECT value FROM sync_sequence) WHERE id = OLD.todo_id;
+END;
+
+-- Todos deleted for good, so that clients can drop them too
+CREATE TABLE IF NOT EXISTS todo_tombstones (
+    todo_id TEXT NOT NULL PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    deleted_at TIMESTAMP NOT NULL,
+    sync_seq BIGINT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS todo_tombstones_user_id_sync_seq_idx ON todo_tombstones (user_id, sync_seq);
+
+CREATE TRIGGER IF NOT EXISTS todos_add_tombstone AFTER DELETE ON todos BEGIN
+    UPDATE sync_sequence SET value = value + 1;
+    INSERT OR REPLACE INTO todo_tombstones (todo_id, user_id, deleted_at, sync_seq)
+        VALUES (
+            OLD.id,
+            OLD.user_id,
+            strftime('%Y-%m-%d %H:%M:%f', 'now', 'localtime'),
+            (SELECT value FROM sync_sequence)
+        );
+END;
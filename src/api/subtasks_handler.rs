@@ -0,0 +1,289 @@
+use actix_web::{web, HttpResponse};
+use serde_json::json;
+
+use super::middlewares::auth::Authenticated;
+use super::repository::Repository;
+use crate::api::dtos::subtask::{ReorderSubtasksDTO, SubtaskDTO};
+
+/// Api handler for getting the subtasks of a todo
+pub async fn get_subtasks(
+    auth: Authenticated,
+    todo_id: web::Path<String>,
+    repository: web::Data<dyn Repository>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let list = web::block(move || repository.subtasks(todo_id.as_str(), &auth.id)).await??;
+
+    Ok(HttpResponse::Ok().json(json!({ "subtasks": list })))
+}
+
+/// Add a subtask at the end of the checklist of a todo
+pub async fn create_subtask(
+    auth: Authenticated,
+    todo_id: web::Path<String>,
+    request_data: web::Json<SubtaskDTO>,
+    repository: web::Data<dyn Repository>,
+) -> Result<HttpResponse, actix_web::Error> {
+    request_data.validate()?;
+
+    let inserted = web::block(move || {
+        repository.insert_subtask(todo_id.as_str(), request_data.into_inner(), &auth.id)
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().json(&inserted))
+}
+
+/// Change the order of the subtasks of a todo
+pub async fn reorder_subtasks(
+    auth: Authenticated,
+    todo_id: web::Path<String>,
+    request_data: web::Json<ReorderSubtasksDTO>,
+    repository: web::Data<dyn Repository>,
+) -> Result<HttpResponse, actix_web::Error> {
+    request_data.validate()?;
+
+    let list = web::block(move || {
+        repository.reorder_subtasks(todo_id.as_str(), request_data.into_inner(), &auth.id)
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().json(json!({ "subtasks": list })))
+}
+
+/// Mark a subtask as done
+pub async fn mark_subtask_as_complete(
+    auth: Authenticated,
+    path: web::Path<(String, String)>,
+    repository: web::Data<dyn Repository>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let (todo_id, subtask_id) = path.into_inner();
+
+    web::block(move || {
+        repository.set_subtask_completeness(todo_id.as_str(), subtask_id.as_str(), true, &auth.id)
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Mark a subtask as not done
+pub async fn mark_subtask_as_incomplete(
+    auth: Authenticated,
+    path: web::Path<(String, String)>,
+    repository: web::Data<dyn Repository>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let (todo_id, subtask_id) = path.into_inner();
+
+    web::block(move || {
+        repository.set_subtask_completeness(todo_id.as_str(), subtask_id.as_str(), false, &auth.id)
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Delete a subtask
+pub async fn delete_subtask(
+    auth: Authenticated,
+    path: web::Path<(String, String)>,
+    repository: web::Data<dyn Repository>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let (todo_id, subtask_id) = path.into_inner();
+
+    web::block(move || repository.remove_subtask(todo_id.as_str(), subtask_id.as_str(), &auth.id))
+        .await??;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[cfg(test)]
+mod test {
+    use actix_web::{http::StatusCode, test};
+    use serde_json::{json, Value};
+
+    use crate::api::test_utils::{
+        authorized, signup_request, test_app, test_mailer, test_repositories,
+    };
+
+    #[actix_web::test]
+    async fn test_subtasks() {
+        for repository in test_repositories() {
+            let app = test::init_service(test_app(repository, test_mailer(None))).await;
+
+            let user: Value =
+                test::call_and_read_body_json(&app, signup_request().to_request()).await;
+            let token = user["token"].as_str().unwrap();
+
+            let todo: Value = test::call_and_read_body_json(
+                &app,
+                authorized(test::TestRequest::post().uri("/api/todo"), token)
+                    .set_json(json!({ "title": "Pack for the trip" }))
+                    .to_request(),
+            )
+            .await;
+            let todo_url = format!("/api/todo/{}", todo["id"].as_str().unwrap());
+            let subtasks_url = format!("{}/subtasks", todo_url);
+
+            let mut subtasks = vec![];
+
+            for title in ["Passport", "Tickets", "Charger"] {
+                let subtask: Value = test::call_and_read_body_json(
+                    &app,
+                    authorized(test::TestRequest::post().uri(&subtasks_url), token)
+                        .set_json(json!({ "title": title }))
+                        .to_request(),
+                )
+                .await;
+
+                subtasks.push(subtask);
+            }
+
+            let response = test::call_service(
+                &app,
+                authorized(
+                    test::TestRequest::put().uri(&format!(
+                        "{}/{}/complete",
+                        subtasks_url,
+                        subtasks[1]["id"].as_str().unwrap()
+                    )),
+                    token,
+                )
+                .to_request(),
+            )
+            .await;
+
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let response = test::call_service(
+                &app,
+                authorized(
+                    test::TestRequest::delete().uri(&format!(
+                        "{}/{}",
+                        subtasks_url,
+                        subtasks[2]["id"].as_str().unwrap()
+                    )),
+                    token,
+                )
+                .to_request(),
+            )
+            .await;
+
+            assert_eq!(response.status(), StatusCode::OK);
+
+            // The progress of the todo rolls up its subtasks
+            let todo: Value = test::call_and_read_body_json(
+                &app,
+                authorized(test::TestRequest::get().uri(&todo_url), token).to_request(),
+            )
+            .await;
+
+            assert_eq!(todo["progress"], json!({ "done": 1, "total": 2 }));
+
+            let reordered: Value = test::call_and_read_body_json(
+                &app,
+                authorized(
+                    test::TestRequest::put().uri(&format!("{}/order", subtasks_url)),
+                    token,
+                )
+                .set_json(json!({ "ids": [subtasks[1]["id"], subtasks[0]["id"]] }))
+                .to_request(),
+            )
+            .await;
+
+            let titles: Vec<&Value> = reordered["subtasks"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|subtask| &subtask["title"])
+                .collect();
+
+            assert_eq!(titles, vec!["Tickets", "Passport"]);
+            assert_eq!(reordered["subtasks"][0]["completed"], true);
+
+            // An order has to list every subtask of the todo
+            let response = test::call_service(
+                &app,
+                authorized(
+                    test::TestRequest::put().uri(&format!("{}/order", subtasks_url)),
+                    token,
+                )
+                .set_json(json!({ "ids": [subtasks[0]["id"]] }))
+                .to_request(),
+            )
+            .await;
+
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+            let list: Value = test::call_and_read_body_json(
+                &app,
+                authorized(test::TestRequest::get().uri(&subtasks_url), token).to_request(),
+            )
+            .await;
+
+            assert_eq!(list["subtasks"], reordered["subtasks"]);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_subtasks_of_other_users_are_not_found() {
+        for repository in test_repositories() {
+            let app = test::init_service(test_app(repository, test_mailer(None))).await;
+
+            let owner: Value =
+                test::call_and_read_body_json(&app, signup_request().to_request()).await;
+            let owner_token = owner["token"].as_str().unwrap();
+
+            let intruder: Value =
+                test::call_and_read_body_json(&app, signup_request().to_request()).await;
+            let intruder_token = intruder["token"].as_str().unwrap();
+
+            let todo: Value = test::call_and_read_body_json(
+                &app,
+                authorized(test::TestRequest::post().uri("/api/todo"), owner_token)
+                    .set_json(json!({ "title": "Owned" }))
+                    .to_request(),
+            )
+            .await;
+            let subtasks_url = format!("/api/todo/{}/subtasks", todo["id"].as_str().unwrap());
+
+            let subtask: Value = test::call_and_read_body_json(
+                &app,
+                authorized(test::TestRequest::post().uri(&subtasks_url), owner_token)
+                    .set_json(json!({ "title": "Owned step" }))
+                    .to_request(),
+            )
+            .await;
+            let subtask_url = format!("{}/{}", subtasks_url, subtask["id"].as_str().unwrap());
+
+            let foreign_requests = vec![
+                test::TestRequest::get().uri(&subtasks_url),
+                test::TestRequest::post()
+                    .uri(&subtasks_url)
+                    .set_json(json!({ "title": "Sneaked in" })),
+                test::TestRequest::put()
+                    .uri(&format!("{}/order", subtasks_url))
+                    .set_json(json!({ "ids": [subtask["id"]] })),
+                test::TestRequest::put().uri(&format!("{}/complete", subtask_url)),
+                test::TestRequest::put().uri(&format!("{}/incomplete", subtask_url)),
+                test::TestRequest::delete().uri(&subtask_url),
+            ];
+
+            for request in foreign_requests {
+                let response =
+                    test::call_service(&app, authorized(request, intruder_token).to_request())
+                        .await;
+
+                assert_eq!(response.status(), StatusCode::NOT_FOUND);
+            }
+
+            // Nothing was changed by the other user
+            let list: Value = test::call_and_read_body_json(
+                &app,
+                authorized(test::TestRequest::get().uri(&subtasks_url), owner_token).to_request(),
+            )
+            .await;
+
+            assert_eq!(list["subtasks"], json!([subtask]));
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use actix_web::{web, HttpResponse};
+
+use super::middlewares::auth::Authenticated;
+use super::repository::Repository;
+use crate::api::dtos::todo::ChangesQueryDTO;
+
+/// Api handler for the changes to the todos of a user since a sync token,
+/// without a token every todo is sent along with the first token
+pub async fn get_changes(
+    auth: Authenticated,
+    query: web::Query<ChangesQueryDTO>,
+    repository: web::Data<dyn Repository>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let since = query.since_txid()?;
+
+    let delta = web::block(move || repository.todo_changes(since, &auth.id)).await??;
+
+    Ok(HttpResponse::Ok().json(&delta))
+}
+
+#[cfg(test)]
+mod test {
+    use actix_web::{http::StatusCode, test};
+    use serde_json::{json, Value};
+
+    use crate::api::test_utils::{
+        authorized, signup_request, test_app, test_mailer, test_repositories,
+    };
+
+    #[actix_web::test]
+    async fn test_changes_since_a_token() {
+        for repository in test_repositories() {
+            let app = test::init_service(test_app(repository, test_mailer(None))).await;
+
+            let user: Value =
+                test::call_and_read_body_json(&app, signup_request().to_request()).await;
+            let token = user["token"].as_str().unwrap();
+
+            let other: Value =
+                test::call_and_read_body_json(&app, signup_request().to_request()).await;
+
+            let mut todos = vec![];
+
+            for title in ["Buy milk", "Call mom"] {
+                let todo: Value = test::call_and_read_body_json(
+                    &app,
+                    authorized(test::TestRequest::post().uri("/api/todo"), token)
+                        .set_json(json!({ "title": title }))
+                        .to_request(),
+                )
+                .await;
+
+                todos.push(todo);
+            }
+
+            let changes_request = |since: Option<&Value>| {
+                let uri = match since {
+                    Some(since) => format!("/api/todo/changes?since={}", since.as_str().unwrap()),
+                    None => "/api/todo/changes".to_string(),
+                };
+
+                authorized(test::TestRequest::get().uri(&uri), token).to_request()
+            };
+
+            // The first sync gets every todo
+            let first: Value = test::call_and_read_body_json(&app, changes_request(None)).await;
+
+            assert_eq!(first["changed"].as_array().unwrap().len(), 2);
+            assert_eq!(first["deleted"], json!([]));
+
+            let unchanged: Value =
+                test::call_and_read_body_json(&app, changes_request(Some(&first["next_token"])))
+                    .await;
+
+            assert_eq!(unchanged["changed"], json!([]));
+            assert_eq!(unchanged["deleted"], json!([]));
+
+            let todo_url = |todo: &Value| format!("/api/todo/{}", todo["id"].as_str().unwrap());
+
+            test::call_service(
+                &app,
+                authorized(test::TestRequest::patch().uri(&todo_url(&todos[0])), token)
+                    .set_json(json!({ "title": "Buy oat milk" }))
+                    .to_request(),
+            )
+            .await;
+
+            test::call_service(
+                &app,
+                authorized(test::TestRequest::delete().uri(&todo_url(&todos[1])), token)
+                    .to_request(),
+            )
+            .await;
+
+            // Changes of other users are never sent
+            test::call_service(
+                &app,
+                authorized(
+                    test::TestRequest::post().uri("/api/todo"),
+                    other["token"].as_str().unwrap(),
+                )
+                .set_json(json!({ "title": "Foreign" }))
+                .to_request(),
+            )
+            .await;
+
+            // Deleted todos come back as tombstones
+            let delta: Value =
+                test::call_and_read_body_json(&app, changes_request(Some(&first["next_token"])))
+                    .await;
+
+            assert_eq!(delta["changed"].as_array().unwrap().len(), 1);
+            assert_eq!(delta["changed"][0]["id"], todos[0]["id"]);
+            assert_eq!(delta["changed"][0]["title"], "Buy oat milk");
+            assert_eq!(delta["deleted"].as_array().unwrap().len(), 1);
+            assert_eq!(delta["deleted"][0]["id"], todos[1]["id"]);
+
+            let unchanged: Value =
+                test::call_and_read_body_json(&app, changes_request(Some(&delta["next_token"])))
+                    .await;
+
+            assert_eq!(unchanged["changed"], json!([]));
+            assert_eq!(unchanged["deleted"], json!([]));
+
+            let response =
+                test::call_service(&app, changes_request(Some(&json!("not-a-token")))).await;
+
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        }
+    }
+}
@@ -0,0 +1,69 @@
+//! Helpers shared by the tests of the api handlers
+
+use std::{path::PathBuf, sync::Arc};
+
+use actix_web::{
+    body::BoxBody,
+    dev::{ServiceFactory, ServiceRequest, ServiceResponse},
+    test, web, App, Error,
+};
+use serde_json::json;
+
+use super::{
+    api::configure,
+    mailer::{LogMailer, Mailer},
+    repository::{MemoryRepository, Repository, SqliteRepository},
+};
+
+/// The api with `repository` and `mailer`, ready for `test::init_service`
+pub fn test_app(
+    repository: web::Data<dyn Repository>,
+    mailer: web::Data<dyn Mailer>,
+) -> App<
+    impl ServiceFactory<
+        ServiceRequest,
+        Config = (),
+        Response = ServiceResponse<BoxBody>,
+        Error = Error,
+        InitError = (),
+    >,
+> {
+    App::new()
+        .app_data(repository)
+        .app_data(mailer)
+        .configure(configure)
+}
+
+pub fn test_repository() -> web::Data<dyn Repository> {
+    web::Data::from(Arc::new(MemoryRepository::new()) as Arc<dyn Repository>)
+}
+
+/// Every repository the tests run against, none of them needs a database server
+/// or leaves a file behind
+pub fn test_repositories() -> Vec<web::Data<dyn Repository>> {
+    // The pool has a single connection, the database lives as long as the repository
+    let sqlite = SqliteRepository::open(":memory:").expect("Failed to open the SQLite database");
+
+    vec![
+        test_repository(),
+        web::Data::from(Arc::new(sqlite) as Arc<dyn Repository>),
+    ]
+}
+
+/// Mailer writing to `path`, or printing without one
+pub fn test_mailer(path: Option<PathBuf>) -> web::Data<dyn Mailer> {
+    web::Data::from(Arc::new(LogMailer::new(path)) as Arc<dyn Mailer>)
+}
+
+/// Signup request for a user with a unique email
+pub fn signup_request() -> test::TestRequest {
+    let email = format!("{}@todo.test", uuid::Uuid::new_v4());
+
+    test::TestRequest::post()
+        .uri("/api/auth/signup")
+        .set_json(json!({ "email": email, "password": "password", "name": "Test" }))
+}
+
+pub fn authorized(request: test::TestRequest, token: &str) -> test::TestRequest {
+    request.insert_header(("Authorization", format!("Bearer {}", token)))
+}
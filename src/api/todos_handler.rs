@@ -1,190 +1,1105 @@
-use std::cmp::Ordering;
-
 use actix_web::{web, HttpResponse};
 use serde_json::json;
 
 use super::errors::TodoApiError;
 use super::middlewares::auth::Authenticated;
-use crate::api::auth_utils::verify_todo_owner;
-use crate::api::dtos::todo::CreateTodoDTO;
-use crate::models::todo_model::Todo;
-use crate::models::Pool;
+use super::repository::Repository;
+use super::trash::TRASH_RETENTION_DAYS;
+use crate::api::dtos::todo::{
+    ArchiveTodosDTO, BulkDTO, CreateTodoDTO, MoveTodoDTO, SearchQueryDTO, SetPriorityDTO,
+    TodoListQuery, UpdateTodoDTO,
+};
 
-use diesel::prelude::*;
 /// Create a new todo
-
 pub async fn create_todo(
     request_data: web::Json<CreateTodoDTO>,
-    pool: web::Data<Pool>,
+    repository: web::Data<dyn Repository>,
     auth: Authenticated,
 ) -> Result<HttpResponse, actix_web::Error> {
-    let inserted =
-        web::block(move || insert_new_todo(pool, request_data.into_inner(), &auth.id)).await??;
+    request_data.validate()?;
+
+    let inserted = web::block(move || {
+        let todo = request_data.into_inner();
+
+        // A create sent again returns the todo it created the first time
+        if let Some(todo_id) = todo.id {
+            match repository.get_todo(todo_id.to_string().as_str(), &auth.id) {
+                Err(TodoApiError::NotFound(_)) => {}
+                found => return found,
+            }
+        }
+
+        repository.insert_todo(todo, &auth.id)
+    })
+    .await??;
 
     Ok(HttpResponse::Ok().json(&inserted))
 }
 
+/// Api handler for getting a single todo
+pub async fn get_todo(
+    auth: Authenticated,
+    todo_id: web::Path<String>,
+    repository: web::Data<dyn Repository>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let todo = web::block(move || repository.get_todo(todo_id.as_str(), &auth.id)).await??;
+
+    Ok(HttpResponse::Ok().json(&todo))
+}
+
+/// Partially update a todo, only the fields sent are changed
+pub async fn update_todo(
+    auth: Authenticated,
+    todo_id: web::Path<String>,
+    request_data: web::Json<UpdateTodoDTO>,
+    repository: web::Data<dyn Repository>,
+) -> Result<HttpResponse, actix_web::Error> {
+    request_data.validate()?;
+
+    let updated = web::block(move || {
+        repository.update_todo(todo_id.as_str(), request_data.into_inner(), &auth.id)
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().json(&updated))
+}
+
 /// Api handler for getting all todos for a user
 pub async fn get_todos(
     auth: Authenticated,
-    pool: web::Data<Pool>,
+    query: web::Query<Vec<(String, String)>>,
+    repository: web::Data<dyn Repository>,
 ) -> Result<HttpResponse, actix_web::Error> {
-    let mut list = web::block(move || get_all_todos_for_user(pool, &auth.id)).await??;
+    let list_query = TodoListQuery::try_from(query.into_inner())?;
 
-    list.sort_by(|a, b| {
-        if a.completed {
-            return Ordering::Less;
-        }
+    let (list, next_cursor) =
+        web::block(move || repository.list_todos(list_query, &auth.id)).await??;
 
-        if a.completed == b.completed {
-            return Ordering::Equal;
-        }
+    let next_cursor = next_cursor.map(|cursor| cursor.encode());
 
-        if b.completed {
-            return Ordering::Greater;
-        }
+    Ok(HttpResponse::Ok().json(json!({ "todos": list, "next_cursor": next_cursor })))
+}
 
-        Ordering::Equal
-    });
+/// Api handler for applying many operations at once,
+/// responds with the outcome of each operation in request order
+pub async fn bulk_update_todos(
+    auth: Authenticated,
+    request_data: web::Json<BulkDTO>,
+    repository: web::Data<dyn Repository>,
+) -> Result<HttpResponse, actix_web::Error> {
+    request_data.validate()?;
+
+    let results =
+        web::block(move || repository.apply_bulk_operations(request_data.into_inner(), &auth.id))
+            .await??;
 
-    Ok(HttpResponse::Ok().json(json!({ "todos": list })))
+    Ok(HttpResponse::Ok().json(json!({ "results": results })))
 }
 
-/// Api to Delete a TODO
+/// Api handler for a full-text search over the titles and notes of the todos of a user
+pub async fn search_todos(
+    auth: Authenticated,
+    query: web::Query<SearchQueryDTO>,
+    repository: web::Data<dyn Repository>,
+) -> Result<HttpResponse, actix_web::Error> {
+    query.validate()?;
+
+    let hits = web::block(move || repository.search_todos(query.into_inner(), &auth.id)).await??;
+
+    Ok(HttpResponse::Ok().json(json!({ "results": hits })))
+}
+
+/// Api handler for getting the todos in the trash of a user
+pub async fn get_trash(
+    auth: Authenticated,
+    repository: web::Data<dyn Repository>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let list = web::block(move || repository.trashed_todos(&auth.id)).await??;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "todos": list,
+        "retention_days": *TRASH_RETENTION_DAYS,
+    })))
+}
+
+/// Archive the completed todos of a user that were completed
+/// more than the requested number of days ago
+pub async fn archive_todos(
+    auth: Authenticated,
+    request_data: web::Json<ArchiveTodosDTO>,
+    repository: web::Data<dyn Repository>,
+) -> Result<HttpResponse, actix_web::Error> {
+    request_data.validate()?;
+
+    let archived = web::block(move || {
+        repository.archive_completed_todos(request_data.older_than_days, &auth.id)
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().json(json!({ "archived": archived })))
+}
+
+/// Take a todo back out of the trash
+pub async fn restore_todo(
+    auth: Authenticated,
+    todo_id: web::Path<String>,
+    repository: web::Data<dyn Repository>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let restored =
+        web::block(move || repository.restore_todo(todo_id.as_str(), &auth.id)).await??;
+
+    Ok(HttpResponse::Ok().json(&restored))
+}
+
+/// Api to move a TODO to the trash
 pub async fn delete_todo(
     auth: Authenticated,
     params: web::Path<String>,
-    pool: web::Data<Pool>,
+    repository: web::Data<dyn Repository>,
 ) -> Result<HttpResponse, actix_web::Error> {
-    //TODO: Implement check for user before deleting
-    let _ = web::block(move || remove_todo(pool, params.into_inner().as_str(), &auth.id)).await??;
+    web::block(move || repository.trash_todo(params.into_inner().as_str(), &auth.id)).await??;
 
     Ok(HttpResponse::Ok().finish())
 }
 
 /// Update a Todo's completeness
 pub async fn mark_todo_as_complete(
-    _: Authenticated, // Not checking for valid user, (YET)
+    auth: Authenticated,
     todo_id: web::Path<String>,
-    pool: web::Data<Pool>,
+    repository: web::Data<dyn Repository>,
 ) -> Result<HttpResponse, actix_web::Error> {
-    let _ = web::block(move || update_todo_completeness(pool, todo_id.into_inner().as_str(), true))
-        .await??;
+    web::block(move || {
+        repository.set_todo_completeness(todo_id.into_inner().as_str(), true, &auth.id)
+    })
+    .await??;
 
     Ok(HttpResponse::Ok().finish())
 }
 
 /// Mark a todo as incomplete
 pub async fn mark_todo_as_incomplete(
-    _: Authenticated, // Not checking for valid user (YET)
+    auth: Authenticated,
     todo_id: web::Path<String>,
-    pool: web::Data<Pool>,
+    repository: web::Data<dyn Repository>,
 ) -> Result<HttpResponse, actix_web::Error> {
-    let _ =
-        web::block(move || update_todo_completeness(pool, todo_id.into_inner().as_str(), false))
-            .await??;
+    web::block(move || {
+        repository.set_todo_completeness(todo_id.into_inner().as_str(), false, &auth.id)
+    })
+    .await??;
 
     Ok(HttpResponse::Ok().finish())
 }
 
-/// Remove a todo
-fn remove_todo(
-    pool: web::Data<Pool>,
-    todo_id: &str,
-    requester_id: &str,
-) -> Result<(), TodoApiError> {
-    use crate::schema::todos::dsl::*;
+/// Change the priority of a todo
+pub async fn set_todo_priority(
+    auth: Authenticated,
+    todo_id: web::Path<String>,
+    request_data: web::Json<SetPriorityDTO>,
+    repository: web::Data<dyn Repository>,
+) -> Result<HttpResponse, actix_web::Error> {
+    web::block(move || {
+        repository.set_todo_priority(
+            todo_id.into_inner().as_str(),
+            request_data.priority,
+            &auth.id,
+        )
+    })
+    .await??;
 
-    let conn = &pool.get()?;
+    Ok(HttpResponse::Ok().finish())
+}
 
-    match uuid::Uuid::parse_str(todo_id) {
-        Ok(_uid) => {
-            // check if requester is the owner of the todo
-            verify_todo_owner(conn, requester_id, todo_id)?;
+/// Move a todo right before or after another todo
+pub async fn move_todo(
+    auth: Authenticated,
+    todo_id: web::Path<String>,
+    request_data: web::Json<MoveTodoDTO>,
+    repository: web::Data<dyn Repository>,
+) -> Result<HttpResponse, actix_web::Error> {
+    request_data.validate()?;
+
+    let moved = web::block(move || {
+        repository.move_todo(todo_id.as_str(), request_data.into_inner(), &auth.id)
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().json(&moved))
+}
+
+#[cfg(test)]
+mod test {
+    use actix_web::{http::StatusCode, test};
+    use serde_json::{json, Value};
+
+    use crate::api::test_utils::{
+        authorized, signup_request, test_app, test_mailer, test_repositories,
+    };
+
+    #[actix_web::test]
+    async fn test_todo_crud() {
+        for repository in test_repositories() {
+            let app = test::init_service(test_app(repository, test_mailer(None))).await;
+
+            let user: Value =
+                test::call_and_read_body_json(&app, signup_request().to_request()).await;
+            let token = user["token"].as_str().unwrap();
+
+            let todo: Value = test::call_and_read_body_json(
+                &app,
+                authorized(test::TestRequest::post().uri("/api/todo"), token)
+                    .set_json(json!({ "title": " Buy milk ", "tags": ["Errands"] }))
+                    .to_request(),
+            )
+            .await;
+            let todo_url = format!("/api/todo/{}", todo["id"].as_str().unwrap());
+
+            assert_eq!(todo["title"], "Buy milk");
+            assert_eq!(todo["tags"], json!(["errands"]));
+            assert_eq!(todo["completed"], false);
+
+            let updated: Value = test::call_and_read_body_json(
+                &app,
+                authorized(test::TestRequest::patch().uri(&todo_url), token)
+                    .set_json(json!({ "title": "Buy oat milk", "priority": "high" }))
+                    .to_request(),
+            )
+            .await;
+
+            assert_eq!(updated["title"], "Buy oat milk");
+            assert_eq!(updated["priority"], "high");
+            assert_eq!(updated["tags"], json!(["errands"]));
+
+            let response = test::call_service(
+                &app,
+                authorized(
+                    test::TestRequest::put().uri(&format!("{}/complete", todo_url)),
+                    token,
+                )
+                .to_request(),
+            )
+            .await;
 
-            let todo_id = uuid::Uuid::parse_str(todo_id).unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
 
-            let update_count = diesel::delete(todos.filter(id.eq(todo_id))).execute(conn)?;
+            let list: Value = test::call_and_read_body_json(
+                &app,
+                authorized(test::TestRequest::get().uri("/api/todo"), token).to_request(),
+            )
+            .await;
 
-            if update_count > 0 {
-                return Ok(());
-            } else {
-                return Err(TodoApiError::NotFound(String::from("Todo")));
+            assert_eq!(list["todos"].as_array().unwrap().len(), 1);
+            assert_eq!(list["todos"][0]["id"], todo["id"]);
+            assert_eq!(list["todos"][0]["completed"], true);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_sending_a_create_again_creates_one_todo() {
+        for repository in test_repositories() {
+            let app = test::init_service(test_app(repository, test_mailer(None))).await;
+
+            let user: Value =
+                test::call_and_read_body_json(&app, signup_request().to_request()).await;
+            let token = user["token"].as_str().unwrap();
+
+            let todo_id = uuid::Uuid::new_v4();
+
+            for _ in 0..2 {
+                let todo: Value = test::call_and_read_body_json(
+                    &app,
+                    authorized(test::TestRequest::post().uri("/api/todo"), token)
+                        .set_json(json!({ "id": todo_id, "title": "Buy milk" }))
+                        .to_request(),
+                )
+                .await;
+
+                assert_eq!(todo["id"], todo_id.to_string());
             }
+
+            let list: Value = test::call_and_read_body_json(
+                &app,
+                authorized(test::TestRequest::get().uri("/api/todo"), token).to_request(),
+            )
+            .await;
+
+            assert_eq!(list["todos"].as_array().unwrap().len(), 1);
+
+            // The id of someone else's todo can't be taken
+            let other: Value =
+                test::call_and_read_body_json(&app, signup_request().to_request()).await;
+
+            let response = test::call_service(
+                &app,
+                authorized(
+                    test::TestRequest::post().uri("/api/todo"),
+                    other["token"].as_str().unwrap(),
+                )
+                .set_json(json!({ "id": todo_id, "title": "Buy milk" }))
+                .to_request(),
+            )
+            .await;
+
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
         }
-        Err(e) => {
-            eprintln!("Error while deleting Todo {}", e);
-            Err(TodoApiError::BadRequest(String::from("Invalid Todo Id")))
+    }
+
+    #[actix_web::test]
+    async fn test_remove_todo() {
+        for repository in test_repositories() {
+            let app = test::init_service(test_app(repository, test_mailer(None))).await;
+
+            let user: Value =
+                test::call_and_read_body_json(&app, signup_request().to_request()).await;
+            let token = user["token"].as_str().unwrap();
+
+            let todo: Value = test::call_and_read_body_json(
+                &app,
+                authorized(test::TestRequest::post().uri("/api/todo"), token)
+                    .set_json(json!({ "title": "Removed" }))
+                    .to_request(),
+            )
+            .await;
+            let todo_url = format!("/api/todo/{}", todo["id"].as_str().unwrap());
+
+            let response = test::call_service(
+                &app,
+                authorized(test::TestRequest::delete().uri(&todo_url), token).to_request(),
+            )
+            .await;
+
+            assert_eq!(response.status(), StatusCode::OK);
+
+            // Removed todos are only found in the trash
+            for request in [
+                test::TestRequest::get().uri(&todo_url),
+                test::TestRequest::delete().uri(&todo_url),
+            ] {
+                let response =
+                    test::call_service(&app, authorized(request, token).to_request()).await;
+
+                assert_eq!(response.status(), StatusCode::NOT_FOUND);
+            }
+
+            let list: Value = test::call_and_read_body_json(
+                &app,
+                authorized(test::TestRequest::get().uri("/api/todo"), token).to_request(),
+            )
+            .await;
+
+            assert_eq!(list["todos"], json!([]));
+
+            let trash: Value = test::call_and_read_body_json(
+                &app,
+                authorized(test::TestRequest::get().uri("/api/todo/trash"), token).to_request(),
+            )
+            .await;
+
+            assert_eq!(trash["todos"][0]["id"], todo["id"]);
         }
     }
-}
 
-fn insert_new_todo(
-    pool: web::Data<Pool>,
-    todo: CreateTodoDTO,
-    requester_id: &str,
-) -> Result<Todo, TodoApiError> {
-    use crate::schema::todos::dsl::*;
+    #[actix_web::test]
+    async fn test_todos_of_other_users_are_not_found() {
+        for repository in test_repositories() {
+            let app = test::init_service(test_app(repository, test_mailer(None))).await;
+
+            let owner: Value =
+                test::call_and_read_body_json(&app, signup_request().to_request()).await;
+            let owner_token = owner["token"].as_str().unwrap();
+
+            let intruder: Value =
+                test::call_and_read_body_json(&app, signup_request().to_request()).await;
+            let intruder_token = intruder["token"].as_str().unwrap();
+
+            let todo: Value = test::call_and_read_body_json(
+                &app,
+                authorized(test::TestRequest::post().uri("/api/todo"), owner_token)
+                    .set_json(json!({ "title": "Owned" }))
+                    .to_request(),
+            )
+            .await;
+            let todo_url = format!("/api/todo/{}", todo["id"].as_str().unwrap());
+
+            let foreign_requests = vec![
+                test::TestRequest::get().uri(&todo_url),
+                test::TestRequest::patch()
+                    .uri(&todo_url)
+                    .set_json(json!({ "title": "Taken over" })),
+                test::TestRequest::put().uri(&format!("{}/complete", todo_url)),
+                test::TestRequest::put().uri(&format!("{}/incomplete", todo_url)),
+                test::TestRequest::put()
+                    .uri(&format!("{}/priority", todo_url))
+                    .set_json(json!({ "priority": "urgent" })),
+                test::TestRequest::delete().uri(&todo_url),
+            ];
 
-    let conn = &pool.get()?;
+            for request in foreign_requests {
+                let response =
+                    test::call_service(&app, authorized(request, intruder_token).to_request())
+                        .await;
 
-    let new_todo = Todo::from(todo.title, uuid::Uuid::parse_str(requester_id).unwrap());
+                assert_eq!(response.status(), StatusCode::NOT_FOUND);
+            }
+
+            // The todo isn't listed for the other user either
+            let list: Value = test::call_and_read_body_json(
+                &app,
+                authorized(test::TestRequest::get().uri("/api/todo"), intruder_token).to_request(),
+            )
+            .await;
+
+            assert_eq!(list["todos"], json!([]));
+
+            // Nothing was changed by the other user
+            let todo: Value = test::call_and_read_body_json(
+                &app,
+                authorized(test::TestRequest::get().uri(&todo_url), owner_token).to_request(),
+            )
+            .await;
 
-    let inserted = diesel::insert_into(todos)
-        .values(&new_todo)
-        .get_result(conn);
+            assert_eq!(todo["title"], "Owned");
+            assert_eq!(todo["completed"], false);
+            assert_eq!(todo["priority"], "none");
 
-    match inserted {
-        Ok(todo) => Ok(todo),
-        Err(e) => {
-            eprintln!("Error {}", e);
-            Err(TodoApiError::InternalServerError)
+            let response = test::call_service(
+                &app,
+                authorized(
+                    test::TestRequest::put().uri(&format!("{}/complete", todo_url)),
+                    owner_token,
+                )
+                .to_request(),
+            )
+            .await;
+
+            assert_eq!(response.status(), StatusCode::OK);
         }
     }
-}
 
-/// Complete or Uncomplete a todo
-fn update_todo_completeness(
-    pool: web::Data<Pool>,
-    todo_id: &str,
-    is_complete: bool,
-) -> Result<(), TodoApiError> {
-    use crate::schema::todos::dsl::*;
-    let conn = &pool.get()?;
-
-    match uuid::Uuid::parse_str(todo_id) {
-        Ok(uid) => {
-            let _: usize = diesel::update(todos)
-                .filter(id.eq(uid))
-                .set(completed.eq(is_complete))
-                .execute(conn)?;
-
-            Ok(())
+    #[actix_web::test]
+    async fn test_invalid_requests_are_bad_requests() {
+        for repository in test_repositories() {
+            let app = test::init_service(test_app(repository, test_mailer(None))).await;
+
+            let user: Value =
+                test::call_and_read_body_json(&app, signup_request().to_request()).await;
+            let token = user["token"].as_str().unwrap();
+
+            let invalid_requests = vec![
+                test::TestRequest::put().uri("/api/todo/not-a-uuid/complete"),
+                test::TestRequest::get().uri("/api/todo/not-a-uuid"),
+                test::TestRequest::post()
+                    .uri("/api/todo")
+                    .set_json(json!({ "title": "   " })),
+                test::TestRequest::post()
+                    .uri("/api/todo")
+                    .set_json(json!({ "title": "Buy \u{2}milk\u{3}" })),
+                test::TestRequest::get().uri("/api/todo?sort=sideways"),
+                test::TestRequest::get().uri("/api/todo?cursor=not-a-cursor"),
+            ];
+
+            for request in invalid_requests {
+                let response =
+                    test::call_service(&app, authorized(request, token).to_request()).await;
+
+                assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+                let body: Value = test::read_body_json(response).await;
+
+                assert!(body["error"].as_str().unwrap().starts_with("BadRequest"));
+            }
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_pages_start_where_the_previous_page_ended() {
+        for repository in test_repositories() {
+            let app = test::init_service(test_app(repository, test_mailer(None))).await;
+
+            let user: Value =
+                test::call_and_read_body_json(&app, signup_request().to_request()).await;
+            let token = user["token"].as_str().unwrap();
+
+            for title in ["Buy milk", "Call mom", "Fix the bike"] {
+                test::call_service(
+                    &app,
+                    authorized(test::TestRequest::post().uri("/api/todo"), token)
+                        .set_json(json!({ "title": title }))
+                        .to_request(),
+                )
+                .await;
+            }
+
+            let first_page: Value = test::call_and_read_body_json(
+                &app,
+                authorized(test::TestRequest::get().uri("/api/todo?limit=2"), token).to_request(),
+            )
+            .await;
+            let first_todos = first_page["todos"].as_array().unwrap();
+
+            assert_eq!(first_todos.len(), 2);
+
+            // Completing the last todo of the page moves it to the end of the list,
+            // the next page still starts where the first one ended
+            let last_id = first_todos[1]["id"].as_str().unwrap();
+
+            test::call_service(
+                &app,
+                authorized(
+                    test::TestRequest::put().uri(&format!("/api/todo/{}/complete", last_id)),
+                    token,
+                )
+                .to_request(),
+            )
+            .await;
+
+            let next_page: Value = test::call_and_read_body_json(
+                &app,
+                authorized(
+                    test::TestRequest::get().uri(&format!(
+                        "/api/todo?limit=2&cursor={}",
+                        first_page["next_cursor"].as_str().unwrap()
+                    )),
+                    token,
+                )
+                .to_request(),
+            )
+            .await;
+            let next_todos = next_page["todos"].as_array().unwrap();
+
+            assert_eq!(next_todos.len(), 2);
+            assert_eq!(next_todos[0]["completed"], false);
+            assert!(first_todos
+                .iter()
+                .all(|todo| todo["id"] != next_todos[0]["id"]));
+            assert_eq!(next_todos[1]["id"], last_id);
+            assert_eq!(next_page["next_cursor"], Value::Null);
         }
-        Err(e) => {
-            eprintln!("Error while updating");
-            eprintln!("{}", e);
-            return Err(TodoApiError::BadRequest(String::from("Invalid Todo Id")));
+    }
+
+    #[actix_web::test]
+    async fn test_move_todo() {
+        for repository in test_repositories() {
+            let app = test::init_service(test_app(repository, test_mailer(None))).await;
+
+            let user: Value =
+                test::call_and_read_body_json(&app, signup_request().to_request()).await;
+            let token = user["token"].as_str().unwrap();
+
+            for title in ["Buy milk", "Call mom", "Fix the bike"] {
+                test::call_service(
+                    &app,
+                    authorized(test::TestRequest::post().uri("/api/todo"), token)
+                        .set_json(json!({ "title": title }))
+                        .to_request(),
+                )
+                .await;
+            }
+
+            let list: Value = test::call_and_read_body_json(
+                &app,
+                authorized(test::TestRequest::get().uri("/api/todo"), token).to_request(),
+            )
+            .await;
+            let ids: Vec<Value> = list["todos"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|todo| todo["id"].clone())
+                .collect();
+
+            let move_request = |id: &Value, target: Value| {
+                authorized(
+                    test::TestRequest::post()
+                        .uri(&format!("/api/todo/{}/move", id.as_str().unwrap())),
+                    token,
+                )
+                .set_json(target)
+            };
+
+            let moved: Value = test::call_and_read_body_json(
+                &app,
+                move_request(&ids[2], json!({ "before": ids[0] })).to_request(),
+            )
+            .await;
+
+            assert_eq!(moved["id"], ids[2]);
+
+            test::call_service(
+                &app,
+                move_request(&ids[1], json!({ "after": ids[0] })).to_request(),
+            )
+            .await;
+
+            let list: Value = test::call_and_read_body_json(
+                &app,
+                authorized(test::TestRequest::get().uri("/api/todo"), token).to_request(),
+            )
+            .await;
+            let order: Vec<&Value> = list["todos"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|todo| &todo["id"])
+                .collect();
+
+            assert_eq!(order, vec![&ids[2], &ids[0], &ids[1]]);
+
+            // Exactly one of before and after is given
+            for target in [json!({}), json!({ "before": ids[0], "after": ids[1] })] {
+                let response =
+                    test::call_service(&app, move_request(&ids[2], target).to_request()).await;
+
+                assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+            }
+
+            // Neither the todo nor the one it is moved next to can be someone else's
+            let other: Value =
+                test::call_and_read_body_json(&app, signup_request().to_request()).await;
+            let other_token = other["token"].as_str().unwrap();
+
+            let foreign: Value = test::call_and_read_body_json(
+                &app,
+                authorized(test::TestRequest::post().uri("/api/todo"), other_token)
+                    .set_json(json!({ "title": "Foreign" }))
+                    .to_request(),
+            )
+            .await;
+
+            for request in [
+                move_request(&ids[0], json!({ "before": foreign["id"] })),
+                move_request(&foreign["id"], json!({ "before": ids[0] })),
+            ] {
+                let response = test::call_service(&app, request.to_request()).await;
+
+                assert_eq!(response.status(), StatusCode::NOT_FOUND);
+            }
         }
     }
-}
 
-/// Get all todos for a user
-fn get_all_todos_for_user(
-    pool: web::Data<Pool>,
-    requester_id: &str,
-) -> Result<Vec<Todo>, TodoApiError> {
-    use crate::schema::todos::dsl::*;
-    let conn = &pool.get()?;
+    #[actix_web::test]
+    async fn test_restore_and_purge_the_trash() {
+        for repository in test_repositories() {
+            let app = test::init_service(test_app(repository.clone(), test_mailer(None))).await;
 
-    let todos_list = todos
-        .filter(user_id.eq(uuid::Uuid::parse_str(requester_id).unwrap()))
-        .load::<Todo>(conn)?;
+            let user: Value =
+                test::call_and_read_body_json(&app, signup_request().to_request()).await;
+            let token = user["token"].as_str().unwrap();
 
-    Ok(todos_list)
-}
+            let mut trashed = vec![];
 
-#[cfg(test)]
-mod test {
-    fn test_remove_todo() {}
+            for title in ["Restored", "Purged"] {
+                let todo: Value = test::call_and_read_body_json(
+                    &app,
+                    authorized(test::TestRequest::post().uri("/api/todo"), token)
+                        .set_json(json!({ "title": title }))
+                        .to_request(),
+                )
+                .await;
+
+                test::call_service(
+                    &app,
+                    authorized(
+                        test::TestRequest::delete()
+                            .uri(&format!("/api/todo/{}", todo["id"].as_str().unwrap())),
+                        token,
+                    )
+                    .to_request(),
+                )
+                .await;
+
+                trashed.push(todo);
+            }
+
+            let restore_request = |todo: &Value| {
+                test::TestRequest::post().uri(&format!(
+                    "/api/todo/{}/restore",
+                    todo["id"].as_str().unwrap()
+                ))
+            };
+
+            // Only the owner can restore a todo
+            let other: Value =
+                test::call_and_read_body_json(&app, signup_request().to_request()).await;
+
+            let response = test::call_service(
+                &app,
+                authorized(
+                    restore_request(&trashed[0]),
+                    other["token"].as_str().unwrap(),
+                )
+                .to_request(),
+            )
+            .await;
+
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+            let restored: Value = test::call_and_read_body_json(
+                &app,
+                authorized(restore_request(&trashed[0]), token).to_request(),
+            )
+            .await;
+
+            assert_eq!(restored["id"], trashed[0]["id"]);
+
+            // A todo that isn't in the trash can't be restored
+            let response = test::call_service(
+                &app,
+                authorized(restore_request(&trashed[0]), token).to_request(),
+            )
+            .await;
+
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+            let list: Value = test::call_and_read_body_json(
+                &app,
+                authorized(test::TestRequest::get().uri("/api/todo"), token).to_request(),
+            )
+            .await;
+
+            assert_eq!(list["todos"].as_array().unwrap().len(), 1);
+            assert_eq!(list["todos"][0]["id"], trashed[0]["id"]);
+
+            // Todos trashed before the retention period ended are deleted for good
+            let purged = repository
+                .purge_trash(chrono::Local::now().naive_local() - chrono::Duration::days(1))
+                .unwrap();
+
+            assert_eq!(purged, 0);
+
+            let purged = repository
+                .purge_trash(chrono::Local::now().naive_local() + chrono::Duration::days(1))
+                .unwrap();
+
+            assert_eq!(purged, 1);
+
+            let trash: Value = test::call_and_read_body_json(
+                &app,
+                authorized(test::TestRequest::get().uri("/api/todo/trash"), token).to_request(),
+            )
+            .await;
+
+            assert_eq!(trash["todos"], json!([]));
+
+            let response = test::call_service(
+                &app,
+                authorized(restore_request(&trashed[1]), token).to_request(),
+            )
+            .await;
+
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_archive_completed_todos() {
+        for repository in test_repositories() {
+            let app = test::init_service(test_app(repository, test_mailer(None))).await;
+
+            let mut tokens = vec![];
+
+            // Every user has a completed and an open todo
+            for _ in 0..2 {
+                let user: Value =
+                    test::call_and_read_body_json(&app, signup_request().to_request()).await;
+                let token = user["token"].as_str().unwrap().to_string();
+
+                for title in ["Done", "Open"] {
+                    let todo: Value = test::call_and_read_body_json(
+                        &app,
+                        authorized(test::TestRequest::post().uri("/api/todo"), &token)
+                            .set_json(json!({ "title": title }))
+                            .to_request(),
+                    )
+                    .await;
+
+                    if title == "Done" {
+                        test::call_service(
+                            &app,
+                            authorized(
+                                test::TestRequest::put().uri(&format!(
+                                    "/api/todo/{}/complete",
+                                    todo["id"].as_str().unwrap()
+                                )),
+                                &token,
+                            )
+                            .to_request(),
+                        )
+                        .await;
+                    }
+                }
+
+                tokens.push(token);
+            }
+
+            let archive_request = |older_than_days: Value| {
+                authorized(
+                    test::TestRequest::post().uri("/api/todo/archive"),
+                    &tokens[0],
+                )
+                .set_json(json!({ "older_than_days": older_than_days }))
+            };
+
+            // The todo was completed today
+            let archived: Value =
+                test::call_and_read_body_json(&app, archive_request(json!(1)).to_request()).await;
+
+            assert_eq!(archived["archived"], 0);
+
+            let archived: Value =
+                test::call_and_read_body_json(&app, archive_request(json!(0)).to_request()).await;
+
+            assert_eq!(archived["archived"], 1);
+
+            let list: Value = test::call_and_read_body_json(
+                &app,
+                authorized(test::TestRequest::get().uri("/api/todo"), &tokens[0]).to_request(),
+            )
+            .await;
+
+            assert_eq!(list["todos"].as_array().unwrap().len(), 1);
+            assert_eq!(list["todos"][0]["title"], "Open");
+
+            let list: Value = test::call_and_read_body_json(
+                &app,
+                authorized(
+                    test::TestRequest::get().uri("/api/todo?include=archived"),
+                    &tokens[0],
+                )
+                .to_request(),
+            )
+            .await;
+
+            assert_eq!(list["todos"].as_array().unwrap().len(), 2);
+
+            // The todos of other users are left alone
+            let list: Value = test::call_and_read_body_json(
+                &app,
+                authorized(test::TestRequest::get().uri("/api/todo"), &tokens[1]).to_request(),
+            )
+            .await;
+
+            assert_eq!(list["todos"].as_array().unwrap().len(), 2);
+
+            // Looking back further than dates go is a bad request, not a crash
+            for older_than_days in [json!(36_501), json!(u32::MAX)] {
+                let response =
+                    test::call_service(&app, archive_request(older_than_days).to_request()).await;
+
+                assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+            }
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_search_todos() {
+        for repository in test_repositories() {
+            let app = test::init_service(test_app(repository, test_mailer(None))).await;
+
+            let user: Value =
+                test::call_and_read_body_json(&app, signup_request().to_request()).await;
+            let token = user["token"].as_str().unwrap();
+
+            let other: Value =
+                test::call_and_read_body_json(&app, signup_request().to_request()).await;
+
+            let mut todos = vec![];
+
+            for (title, notes) in [
+                ("Buy milk", Some("Oat milk from the corner shop")),
+                ("Call mom", Some("Ask her about the milk bread")),
+                ("Fix the bike", None),
+            ] {
+                let todo: Value = test::call_and_read_body_json(
+                    &app,
+                    authorized(test::TestRequest::post().uri("/api/todo"), token)
+                        .set_json(json!({ "title": title, "notes": notes }))
+                        .to_request(),
+                )
+                .await;
+
+                todos.push(todo);
+            }
+
+            test::call_service(
+                &app,
+                authorized(
+                    test::TestRequest::post().uri("/api/todo"),
+                    other["token"].as_str().unwrap(),
+                )
+                .set_json(json!({ "title": "Buy milk" }))
+                .to_request(),
+            )
+            .await;
+
+            let search = |query: &str| {
+                authorized(
+                    test::TestRequest::get().uri(&format!("/api/todo/search?q={}", query)),
+                    token,
+                )
+                .to_request()
+            };
+
+            // Matches in the title rank above matches in the notes,
+            // the todos of other users are never found
+            let found: Value = test::call_and_read_body_json(&app, search("milk")).await;
+            let results = found["results"].as_array().unwrap();
+
+            assert_eq!(results.len(), 2);
+            assert_eq!(results[0]["id"], todos[0]["id"]);
+            assert_eq!(results[0]["highlighted_title"], "Buy \u{2}milk\u{3}");
+            assert_eq!(results[1]["id"], todos[1]["id"]);
+            assert_eq!(results[1]["highlighted_title"], "Call mom");
+            assert!(results[1]["snippet"]
+                .as_str()
+                .unwrap()
+                .contains("\u{2}milk\u{3}"));
+
+            // Every word has to be found
+            let found: Value = test::call_and_read_body_json(&app, search("milk+corner")).await;
+
+            assert_eq!(found["results"].as_array().unwrap().len(), 1);
+            assert_eq!(found["results"][0]["id"], todos[0]["id"]);
+
+            // Todos in the trash aren't searched
+            test::call_service(
+                &app,
+                authorized(
+                    test::TestRequest::delete()
+                        .uri(&format!("/api/todo/{}", todos[0]["id"].as_str().unwrap())),
+                    token,
+                )
+                .to_request(),
+            )
+            .await;
+
+            let found: Value = test::call_and_read_body_json(&app, search("milk")).await;
+
+            assert_eq!(found["results"].as_array().unwrap().len(), 1);
+            assert_eq!(found["results"][0]["id"], todos[1]["id"]);
+
+            let response = test::call_service(&app, search("+")).await;
+
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_bulk_operations() {
+        for repository in test_repositories() {
+            let app = test::init_service(test_app(repository, test_mailer(None))).await;
+
+            let user: Value =
+                test::call_and_read_body_json(&app, signup_request().to_request()).await;
+            let token = user["token"].as_str().unwrap();
+
+            let other: Value =
+                test::call_and_read_body_json(&app, signup_request().to_request()).await;
+            let other_token = other["token"].as_str().unwrap();
+
+            let mut todos = vec![];
+
+            for title in ["Buy milk", "Call mom", "Fix the bike"] {
+                let todo: Value = test::call_and_read_body_json(
+                    &app,
+                    authorized(test::TestRequest::post().uri("/api/todo"), token)
+                        .set_json(json!({ "title": title }))
+                        .to_request(),
+                )
+                .await;
+
+                todos.push(todo["id"].clone());
+            }
+
+            let project: Value = test::call_and_read_body_json(
+                &app,
+                authorized(test::TestRequest::post().uri("/api/projects"), token)
+                    .set_json(json!({ "name": "Home" }))
+                    .to_request(),
+            )
+            .await;
+
+            let foreign_project: Value = test::call_and_read_body_json(
+                &app,
+                authorized(test::TestRequest::post().uri("/api/projects"), other_token)
+                    .set_json(json!({ "name": "Foreign" }))
+                    .to_request(),
+            )
+            .await;
+
+            let foreign_todo: Value = test::call_and_read_body_json(
+                &app,
+                authorized(test::TestRequest::post().uri("/api/todo"), other_token)
+                    .set_json(json!({ "title": "Foreign" }))
+                    .to_request(),
+            )
+            .await;
+
+            let bulk: Value = test::call_and_read_body_json(
+                &app,
+                authorized(test::TestRequest::post().uri("/api/todo/bulk"), token)
+                    .set_json(json!({ "operations": [
+                        { "op": "complete", "id": todos[0] },
+                        { "op": "retag", "id": todos[1], "add": ["Urgent"] },
+                        { "op": "move", "id": todos[1], "project_id": foreign_project["id"] },
+                        { "op": "delete", "id": foreign_todo["id"] },
+                        { "op": "move", "id": todos[2], "project_id": project["id"] },
+                        { "op": "delete", "id": todos[2] },
+                    ] }))
+                    .to_request(),
+            )
+            .await;
+            let results = bulk["results"].as_array().unwrap();
+
+            // Every operation has a result of its own, failed ones don't stop the others
+            let ok: Vec<&Value> = results.iter().map(|result| &result["ok"]).collect();
+
+            assert_eq!(ok, vec![true, true, false, false, true, true]);
+            assert_eq!(results[0]["todo"]["completed"], true);
+            assert_eq!(results[1]["todo"]["tags"], json!(["urgent"]));
+            assert!(results[2]["error"].is_string());
+            assert_eq!(results[3]["id"], foreign_todo["id"]);
+            assert_eq!(results[4]["todo"]["project_id"], project["id"]);
+            assert_eq!(results[5]["todo"], Value::Null);
+
+            // The failed operations changed nothing
+            let todo: Value = test::call_and_read_body_json(
+                &app,
+                authorized(
+                    test::TestRequest::get()
+                        .uri(&format!("/api/todo/{}", todos[1].as_str().unwrap())),
+                    token,
+                )
+                .to_request(),
+            )
+            .await;
+
+            assert_eq!(todo["project_id"], Value::Null);
+            assert_eq!(todo["tags"], json!(["urgent"]));
+
+            let response = test::call_service(
+                &app,
+                authorized(
+                    test::TestRequest::get().uri(&format!(
+                        "/api/todo/{}",
+                        foreign_todo["id"].as_str().unwrap()
+                    )),
+                    other_token,
+                )
+                .to_request(),
+            )
+            .await;
+
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let response = test::call_service(
+                &app,
+                authorized(test::TestRequest::post().uri("/api/todo/bulk"), token)
+                    .set_json(json!({ "operations": [] }))
+                    .to_request(),
+            )
+            .await;
+
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        }
+    }
 }
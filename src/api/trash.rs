@@ -0,0 +1,39 @@
+use std::time::Duration;
+
+use actix_web::{rt, web};
+
+use super::repository::Repository;
+
+lazy_static::lazy_static! {
+    /// Days a todo stays in the trash before it is deleted for good
+    pub static ref TRASH_RETENTION_DAYS: i64 = std::env::var("TRASH_RETENTION_DAYS")
+        .ok()
+        .and_then(|days| days.parse().ok())
+        .unwrap_or(30);
+}
+
+/// How often the trash is checked for todos past the retention period
+const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Periodically delete the todos that have been in the trash
+/// for longer than [`TRASH_RETENTION_DAYS`], runs for as long as the server
+pub fn spawn_trash_purge(repository: web::Data<dyn Repository>) {
+    rt::spawn(async move {
+        let mut interval = rt::time::interval(PURGE_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            let repository = repository.clone();
+
+            let expired_before =
+                chrono::Local::now().naive_local() - chrono::Duration::days(*TRASH_RETENTION_DAYS);
+
+            match web::block(move || repository.purge_trash(expired_before)).await {
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) => eprintln!("Failed to purge the trash: {}", e),
+                Err(e) => eprintln!("Failed to purge the trash: {}", e),
+            }
+        }
+    });
+}
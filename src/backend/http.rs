@@ -0,0 +1,299 @@
+use std::cell::Cell;
+
+use reqwest::{
+    blocking::{Client, RequestBuilder},
+    header::{AUTHORIZATION, CONTENT_TYPE},
+    Method, StatusCode,
+};
+use serde::de::DeserializeOwned;
+use serde_json::json;
+
+use super::TodoBackend;
+use crate::{
+    errors::BaseError,
+    models::{
+        project_model::Project,
+        subtask_model::Subtask,
+        todo_model::{BulkResult, Priority, SearchHit, Todo, TodoItem},
+    },
+    offline::{is_unreachable, new_todo_body, queue_when_unreachable, LocalStore},
+    utils::{get_saved_token, make_api_url},
+};
+
+/// Backend going through the api server, the changes made while the server
+/// is unreachable are kept in the [`LocalStore`] to be sent later
+#[derive(Default)]
+pub struct HttpBackend {
+    client: Client,
+    offline: Cell<bool>,
+    synced_at: Cell<Option<chrono::NaiveDateTime>>,
+}
+
+impl HttpBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Request to an api resource on behalf of the logged in user
+    fn request(&self, method: Method, resource: &str) -> Result<RequestBuilder, BaseError> {
+        let token = get_saved_token()?;
+
+        Ok(self
+            .client
+            .request(method, make_api_url(resource))
+            .header(AUTHORIZATION, format!("Bearer {}", token))
+            .header(CONTENT_TYPE, "application/json"))
+    }
+
+    /// Send a request, returns the body of a successful response
+    fn send(&self, request: RequestBuilder) -> Result<String, BaseError> {
+        let response = match request.send() {
+            Ok(response) => response,
+            Err(e) => {
+                self.offline.set(is_unreachable(&e));
+                return Err(e.into());
+            }
+        };
+
+        self.offline.set(false);
+
+        match response.status() {
+            StatusCode::OK => Ok(response.text()?),
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Err("Login First".into()),
+            _ => {
+                // The api tells what went wrong in `error`
+                let body: serde_json::Value = response.json().unwrap_or_default();
+
+                match body.get("error").and_then(|error| error.as_str()) {
+                    Some(error) => Err(error.into()),
+                    None => Err("Main line error".into()),
+                }
+            }
+        }
+    }
+
+    /// Send a request and read `key` of the json response,
+    /// or the whole response without a key
+    fn send_json<T: DeserializeOwned>(
+        &self,
+        request: RequestBuilder,
+        key: Option<&str>,
+    ) -> Result<T, BaseError> {
+        let mut value: serde_json::Value = serde_json::from_str(self.send(request)?.as_str())?;
+
+        if let Some(key) = key {
+            value = value.get(key).ok_or("Invalid response")?.to_owned();
+        }
+
+        Ok(serde_json::from_value(value)?)
+    }
+}
+
+impl TodoBackend for HttpBackend {
+    fn list_todos(&self, query: &[(String, String)]) -> Result<Vec<TodoItem>, BaseError> {
+        let mut todos: Vec<TodoItem> = vec![];
+        let mut page_query = query.to_vec();
+
+        // The list comes in pages, each one pointing to the next
+        loop {
+            let request = self.request(Method::GET, "todo")?.query(&page_query);
+
+            let page: serde_json::Value = match self.send_json(request, None) {
+                Ok(page) => page,
+                Err(e) if is_unreachable(e.as_ref()) => {
+                    let store = LocalStore::load()?;
+
+                    if store.synced_at.is_none() && store.todos.is_empty() {
+                        return Err(
+                            "Server unreachable and no todos were saved for offline use".into()
+                        );
+                    }
+
+                    self.synced_at.set(store.synced_at);
+
+                    return store.cached_todos(query);
+                }
+                Err(e) => return Err(e),
+            };
+
+            let items = page.get("todos").ok_or("Invalid response")?.to_owned();
+
+            todos.extend(serde_json::from_value::<Vec<TodoItem>>(items)?);
+
+            match page.get("next_cursor").and_then(|cursor| cursor.as_str()) {
+                Some(cursor) => {
+                    page_query.retain(|(key, _)| key != "cursor");
+                    page_query.push(("cursor".into(), cursor.into()));
+                }
+                None => break,
+            }
+        }
+
+        // The saved copy is only a fallback, the list is fine without it
+        let _ = LocalStore::load().and_then(|mut store| {
+            store.refresh(&self.client, get_saved_token()?.as_str())?;
+            store.save()
+        });
+
+        Ok(todos)
+    }
+
+    fn get_todo(&self, todo_id: &str) -> Result<TodoItem, BaseError> {
+        let request = self.request(Method::GET, format!("todo/{}", todo_id).as_str())?;
+
+        self.send_json(request, None)
+    }
+
+    fn create_todo(&self, todo: &TodoItem) -> Result<TodoItem, BaseError> {
+        let request = self
+            .request(Method::POST, "todo")?
+            .json(&new_todo_body(todo));
+
+        match self.send_json(request, None) {
+            Ok(created) => Ok(created),
+            Err(e) => {
+                queue_when_unreachable(e, |store| store.queue_create(todo.clone()))?;
+
+                Ok(todo.clone())
+            }
+        }
+    }
+
+    fn update_todo(
+        &self,
+        todo_id: &str,
+        changes: serde_json::Value,
+    ) -> Result<TodoItem, BaseError> {
+        let request = self
+            .request(Method::PATCH, format!("todo/{}", todo_id).as_str())?
+            .json(&changes);
+
+        self.send_json(request, None)
+    }
+
+    fn complete_todo(&self, todo: &Todo) -> Result<(), BaseError> {
+        let request = self.request(Method::PUT, format!("todo/{}/complete", todo.id).as_str())?;
+
+        match self.send(request) {
+            Ok(_) => Ok(()),
+            Err(e) => queue_when_unreachable(e, |store| store.queue_complete(todo)),
+        }
+    }
+
+    fn set_priority(&self, todo_id: &str, priority: Priority) -> Result<(), BaseError> {
+        let request = self
+            .request(Method::PUT, format!("todo/{}/priority", todo_id).as_str())?
+            .json(&json!({ "priority": priority }));
+
+        self.send(request).map(|_| ())
+    }
+
+    fn move_todo(&self, todo_id: &str, target: serde_json::Value) -> Result<TodoItem, BaseError> {
+        let request = self
+            .request(Method::POST, format!("todo/{}/move", todo_id).as_str())?
+            .json(&target);
+
+        self.send_json(request, None)
+    }
+
+    fn delete_todo(&self, todo: &Todo) -> Result<(), BaseError> {
+        let request = self.request(Method::DELETE, format!("todo/{}", todo.id).as_str())?;
+
+        match self.send(request) {
+            Ok(_) => Ok(()),
+            Err(e) => queue_when_unreachable(e, |store| store.queue_delete(todo)),
+        }
+    }
+
+    fn trash(&self) -> Result<(Vec<TodoItem>, i64), BaseError> {
+        let request = self.request(Method::GET, "todo/trash")?;
+
+        let json: serde_json::Value = self.send_json(request, None)?;
+
+        let todos = json.get("todos").ok_or("Invalid response")?.to_owned();
+
+        let retention_days = json
+            .get("retention_days")
+            .and_then(|days| days.as_i64())
+            .unwrap_or_default();
+
+        Ok((serde_json::from_value(todos)?, retention_days))
+    }
+
+    fn restore_todo(&self, todo_id: &str) -> Result<TodoItem, BaseError> {
+        let request = self.request(Method::POST, format!("todo/{}/restore", todo_id).as_str())?;
+
+        self.send_json(request, None)
+    }
+
+    fn archive_done(&self) -> Result<u64, BaseError> {
+        let request = self
+            .request(Method::POST, "todo/archive")?
+            .json(&json!({ "older_than_days": 0 }));
+
+        self.send_json(request, Some("archived"))
+    }
+
+    fn search(&self, query: &str) -> Result<Vec<SearchHit>, BaseError> {
+        let request = self
+            .request(Method::GET, "todo/search")?
+            .query(&[("q", query)]);
+
+        self.send_json(request, Some("results"))
+    }
+
+    fn bulk(&self, operations: Vec<serde_json::Value>) -> Result<Vec<BulkResult>, BaseError> {
+        let request = self
+            .request(Method::POST, "todo/bulk")?
+            .json(&json!({ "operations": operations }));
+
+        self.send_json(request, Some("results"))
+    }
+
+    fn subtasks(&self, todo_id: &str) -> Result<Vec<Subtask>, BaseError> {
+        let request = self.request(Method::GET, format!("todo/{}/subtasks", todo_id).as_str())?;
+
+        self.send_json(request, Some("subtasks"))
+    }
+
+    fn add_subtask(&self, todo_id: &str, title: String) -> Result<Subtask, BaseError> {
+        let request = self
+            .request(Method::POST, format!("todo/{}/subtasks", todo_id).as_str())?
+            .json(&json!({ "title": title }));
+
+        self.send_json(request, None)
+    }
+
+    fn projects(&self) -> Result<Vec<Project>, BaseError> {
+        let request = self.request(Method::GET, "projects")?;
+
+        match self.send_json::<Vec<Project>>(request, Some("projects")) {
+            Ok(projects) => {
+                let _ = LocalStore::load().and_then(|mut store| {
+                    store.projects = projects.clone();
+                    store.save()
+                });
+
+                Ok(projects)
+            }
+            Err(e) if is_unreachable(e.as_ref()) => Ok(LocalStore::load()?.projects),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn create_project(&self, name: &str) -> Result<Project, BaseError> {
+        let request = self
+            .request(Method::POST, "projects")?
+            .json(&json!({ "name": name }));
+
+        self.send_json(request, None)
+    }
+
+    fn is_offline(&self) -> bool {
+        self.offline.get()
+    }
+
+    fn synced_at(&self) -> Option<chrono::NaiveDateTime> {
+        self.synced_at.get()
+    }
+}
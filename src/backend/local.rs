@@ -0,0 +1,833 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use super::{filter_todos, TodoBackend};
+use crate::{
+    api::{
+        dtos::{
+            project::ProjectDTO,
+            subtask::SubtaskDTO,
+            todo::{
+                normalize_notes, BulkDTO, BulkOperation, CreateTodoDTO, MoveTodoDTO,
+                SearchQueryDTO, UpdateTodoDTO,
+            },
+        },
+        errors::TodoApiError,
+        trash::TRASH_RETENTION_DAYS,
+    },
+    errors::{BaseError, TodoError},
+    models::{
+        project_model::Project,
+        subtask_model::{Progress, Subtask},
+        todo_model::{
+            BulkResult, Priority, SearchHit, Todo, TodoChanges, TodoItem, HIGHLIGHT_END,
+            HIGHLIGHT_START, POSITION_GAP,
+        },
+    },
+    offline::new_todo_body,
+};
+
+/// Everything the local backend keeps, saved as json
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct LocalData {
+    /// Todos in the trash included
+    todos: Vec<TodoItem>,
+    projects: Vec<Project>,
+    subtasks: Vec<Subtask>,
+}
+
+/// Backend keeping the todos in `~/todo/local.json`,
+/// to use the cli without the server and Postgres
+///
+/// It follows the rules of the api, down to its validation and error messages
+pub struct LocalBackend {
+    path: PathBuf,
+}
+
+impl LocalBackend {
+    pub fn new() -> Self {
+        let mut path = dirs::home_dir().unwrap();
+        path.push("todo/local.json");
+
+        Self { path }
+    }
+
+    /// Read the saved data, todos past their time in the trash are left out
+    fn load(&self) -> Result<LocalData, BaseError> {
+        if !self.path.exists() {
+            return Ok(LocalData::default());
+        }
+
+        let contents = std::fs::read_to_string(&self.path)?;
+
+        let mut data: LocalData = serde_json::from_str(contents.as_str())?;
+
+        let expired_before =
+            chrono::Local::now().naive_local() - chrono::Duration::days(*TRASH_RETENTION_DAYS);
+
+        data.todos.retain(|todo| {
+            todo.deleted_at
+                .is_none_or(|deleted| deleted >= expired_before)
+        });
+
+        let todo_ids: Vec<uuid::Uuid> = data.todos.iter().map(|todo| todo.id).collect();
+
+        data.subtasks
+            .retain(|subtask| todo_ids.contains(&subtask.todo_id));
+
+        Ok(data)
+    }
+
+    /// Read the data, apply `change` to it and save it when that went well
+    fn change<T>(
+        &self,
+        change: impl FnOnce(&mut LocalData) -> Result<T, BaseError>,
+    ) -> Result<T, BaseError> {
+        let mut data = self.load()?;
+
+        let result = change(&mut data)?;
+
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        std::fs::write(&self.path, serde_json::to_string(&data)?)?;
+
+        Ok(result)
+    }
+}
+
+impl Default for LocalBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn api_error(e: TodoApiError) -> BaseError {
+    TodoError::ApiError(e).into()
+}
+
+fn not_found(resource: &str) -> BaseError {
+    api_error(TodoApiError::NotFound(resource.to_string()))
+}
+
+impl LocalData {
+    /// A todo that isn't in the trash
+    fn todo_mut(&mut self, todo_id: &str) -> Result<&mut TodoItem, BaseError> {
+        let todo_id = uuid::Uuid::parse_str(todo_id).map_err(|_| not_found("Todo"))?;
+
+        self.todos
+            .iter_mut()
+            .find(|todo| todo.id == todo_id && todo.deleted_at.is_none())
+            .ok_or_else(|| not_found("Todo"))
+    }
+
+    /// The todo as the api returns it, with the progress of its subtasks
+    fn item(&self, todo: &TodoItem) -> TodoItem {
+        let mut progress = Progress::default();
+
+        for subtask in self.subtasks.iter().filter(|s| s.todo_id == todo.id) {
+            progress.total += 1;
+
+            if subtask.completed {
+                progress.done += 1;
+            }
+        }
+
+        TodoItem {
+            progress,
+            ..todo.clone()
+        }
+    }
+
+    fn verify_project(&self, project_id: uuid::Uuid) -> Result<(), BaseError> {
+        match self.projects.iter().any(|project| project.id == project_id) {
+            true => Ok(()),
+            false => Err(not_found("Project")),
+        }
+    }
+
+    fn trash_todo(&mut self, todo_id: &str) -> Result<(), BaseError> {
+        self.todo_mut(todo_id)?.deleted_at = Some(chrono::Local::now().naive_local());
+
+        Ok(())
+    }
+
+    /// Complete or reopen a todo, completing a recurring
+    /// todo creates its next occurrence
+    fn set_completeness(
+        &mut self,
+        todo_id: &str,
+        is_complete: bool,
+    ) -> Result<TodoItem, BaseError> {
+        let todo = self.todo_mut(todo_id)?;
+
+        let was_complete = todo.completed;
+
+        let now = chrono::Local::now().naive_local();
+
+        todo.set_completed(is_complete, now);
+        todo.updated_at = now;
+
+        // Reopened todos can't stay hidden in the archive
+        if !is_complete {
+            todo.archived_at = None;
+        }
+
+        let updated = todo.clone();
+
+        if is_complete && !was_complete {
+            self.spawn_next_occurrence(&updated);
+        }
+
+        Ok(self.item(&updated))
+    }
+
+    /// Create the todo that follows `completed` in its recurring series,
+    /// a series only ever has one open todo
+    fn spawn_next_occurrence(&mut self, completed: &TodoItem) {
+        let recurrence = match &completed.recurrence {
+            Some(recurrence) => recurrence.clone(),
+            None => return,
+        };
+
+        // Todos that were made recurring after they were created start their series here
+        let series = completed.series_id.unwrap_or(completed.id);
+
+        for todo in self.todos.iter_mut().filter(|todo| todo.id == completed.id) {
+            todo.series_id = Some(series);
+        }
+
+        let has_open = self.todos.iter().any(|todo| {
+            todo.series_id == Some(series) && !todo.completed && todo.deleted_at.is_none()
+        });
+
+        if has_open {
+            return;
+        }
+
+        let mut next = Todo::from(completed.title.clone(), completed.user_id);
+        next.due_at = Some(recurrence.next_due(completed.due_at, completed.updated_at));
+        next.priority = completed.priority;
+        next.project_id = completed.project_id;
+        next.recurrence = Some(recurrence);
+        next.series_id = Some(series);
+        next.notes = completed.notes.clone();
+        // The next occurrence takes the place of the completed one
+        next.position = completed.position;
+
+        let copies: Vec<Subtask> = self
+            .subtasks
+            .iter()
+            .filter(|subtask| subtask.todo_id == completed.id)
+            .map(|subtask| Subtask::from(subtask.title.clone(), next.id, subtask.position))
+            .collect();
+
+        self.subtasks.extend(copies);
+
+        self.todos.push(TodoItem {
+            todo: next,
+            tags: completed.tags.clone(),
+            progress: Progress::default(),
+        });
+    }
+
+    /// Apply a single operation of a bulk request,
+    /// returns the todo as it is afterwards unless it was deleted
+    fn apply_bulk_operation(
+        &mut self,
+        operation: &BulkOperation,
+    ) -> Result<Option<TodoItem>, BaseError> {
+        let todo_id = operation.todo_id().to_string();
+
+        let updated = match operation {
+            BulkOperation::Complete { .. } => {
+                return self.set_completeness(&todo_id, true).map(Some)
+            }
+            BulkOperation::Incomplete { .. } => {
+                return self.set_completeness(&todo_id, false).map(Some)
+            }
+            BulkOperation::Delete { .. } => {
+                self.trash_todo(&todo_id)?;
+
+                return Ok(None);
+            }
+            BulkOperation::Retag { add, remove, .. } => {
+                let todo = self.todo_mut(&todo_id)?;
+
+                todo.tags.retain(|name| !remove.contains(name));
+
+                for name in add {
+                    if !todo.tags.contains(name) {
+                        todo.tags.push(name.clone());
+                    }
+                }
+
+                todo.updated_at = chrono::Local::now().naive_local();
+                todo.clone()
+            }
+            BulkOperation::Move { project_id, .. } => {
+                if let Some(project) = project_id {
+                    self.verify_project(*project)?;
+                }
+
+                let todo = self.todo_mut(&todo_id)?;
+
+                todo.project_id = *project_id;
+                todo.updated_at = chrono::Local::now().naive_local();
+                todo.clone()
+            }
+        };
+
+        Ok(Some(self.item(&updated)))
+    }
+}
+
+/// Position between `anchor` and its neighbour on the side the todo with
+/// `todo_id` is moved to, `None` when there is no room left between them
+fn free_position_next_to(
+    todos: &[TodoItem],
+    todo_id: uuid::Uuid,
+    anchor: &Todo,
+    before: bool,
+) -> Option<i64> {
+    let others = || todos.iter().filter(|todo| todo.id != todo_id);
+
+    // Todos sharing a position can't be told apart, so they need rebalancing
+    if others().any(|todo| todo.id != anchor.id && todo.position == anchor.position) {
+        return None;
+    }
+
+    let (neighbour, fallback) = if before {
+        let neighbour = others()
+            .map(|todo| todo.position)
+            .filter(|position| *position < anchor.position)
+            .max();
+
+        (neighbour, anchor.position - POSITION_GAP)
+    } else {
+        let neighbour = others()
+            .map(|todo| todo.position)
+            .filter(|position| *position > anchor.position)
+            .min();
+
+        (neighbour, anchor.position + POSITION_GAP)
+    };
+
+    match neighbour {
+        None => Some(fallback),
+        Some(neighbour) if (neighbour - anchor.position).abs() > 1 => {
+            Some(anchor.position + (neighbour - anchor.position) / 2)
+        }
+        Some(_) => None,
+    }
+}
+
+/// Wrap the words of `text` starting with one of `words` in the highlight markers,
+/// like the api highlights search matches. `words` have to be lowercase,
+/// returns the highlighted text and the words that were found
+fn highlight<'a>(text: &str, words: &'a [String]) -> (String, Vec<&'a String>) {
+    let mut highlighted = String::new();
+    let mut found: Vec<&String> = vec![];
+
+    let mut rest = text;
+
+    while !rest.is_empty() {
+        let word_len = rest
+            .char_indices()
+            .find(|(_, c)| !c.is_alphanumeric())
+            .map_or(rest.len(), |(i, _)| i);
+
+        if word_len == 0 {
+            let c = rest.chars().next().unwrap();
+
+            highlighted.push(c);
+            rest = &rest[c.len_utf8()..];
+            continue;
+        }
+
+        let word = &rest[..word_len];
+        let lowercase = word.to_lowercase();
+
+        let matches: Vec<&String> = words
+            .iter()
+            .filter(|search| lowercase.starts_with(search.as_str()))
+            .collect();
+
+        if matches.is_empty() {
+            highlighted.push_str(word);
+        } else {
+            highlighted.push_str(format!("{}{}{}", HIGHLIGHT_START, word, HIGHLIGHT_END).as_str());
+            found.extend(matches);
+        }
+
+        rest = &rest[word_len..];
+    }
+
+    (highlighted, found)
+}
+
+impl TodoBackend for LocalBackend {
+    fn list_todos(&self, query: &[(String, String)]) -> Result<Vec<TodoItem>, BaseError> {
+        let data = self.load()?;
+
+        let items: Vec<TodoItem> = data.todos.iter().map(|todo| data.item(todo)).collect();
+
+        filter_todos(&items, &data.projects, query)
+    }
+
+    fn get_todo(&self, todo_id: &str) -> Result<TodoItem, BaseError> {
+        let mut data = self.load()?;
+
+        let todo = data.todo_mut(todo_id)?.clone();
+
+        Ok(data.item(&todo))
+    }
+
+    fn create_todo(&self, todo: &TodoItem) -> Result<TodoItem, BaseError> {
+        let dto: CreateTodoDTO = serde_json::from_value(new_todo_body(todo))?;
+
+        dto.validate().map_err(api_error)?;
+
+        self.change(|data| {
+            if let Some(project) = dto.project_id {
+                data.verify_project(project)?;
+            }
+
+            let mut new_todo = Todo::from(dto.title.trim().to_string(), uuid::Uuid::nil());
+            new_todo.due_at = dto.due_at;
+            new_todo.priority = dto.priority;
+            new_todo.project_id = dto.project_id;
+            new_todo.notes = dto.notes.as_deref().and_then(normalize_notes);
+
+            if dto.recurrence.is_some() {
+                new_todo.series_id = Some(new_todo.id);
+                new_todo.recurrence = dto.recurrence;
+            }
+
+            // New todos go to the top of the list
+            new_todo.position = data
+                .todos
+                .iter()
+                .map(|todo| todo.position)
+                .min()
+                .map_or(0, |top| top - POSITION_GAP);
+
+            let inserted = TodoItem {
+                todo: new_todo,
+                tags: dto.tags,
+                progress: Progress::default(),
+            };
+
+            data.todos.push(inserted.clone());
+
+            Ok(inserted)
+        })
+    }
+
+    fn update_todo(
+        &self,
+        todo_id: &str,
+        changes: serde_json::Value,
+    ) -> Result<TodoItem, BaseError> {
+        let update: UpdateTodoDTO = serde_json::from_value(changes)?;
+
+        update.validate().map_err(api_error)?;
+
+        let changes = TodoChanges::from(&update);
+
+        self.change(|data| {
+            if let Some(Some(project)) = changes.project_id {
+                data.verify_project(project)?;
+            }
+
+            let todo = data.todo_mut(todo_id)?;
+
+            let was_complete = todo.completed;
+
+            changes.apply_to(todo);
+
+            if let Some(tags) = update.tags {
+                todo.tags = tags;
+            }
+
+            let updated = todo.clone();
+
+            if updated.completed && !was_complete {
+                data.spawn_next_occurrence(&updated);
+            }
+
+            Ok(data.item(&updated))
+        })
+    }
+
+    fn complete_todo(&self, todo: &Todo) -> Result<(), BaseError> {
+        self.change(|data| data.set_completeness(todo.id.to_string().as_str(), true))
+            .map(|_| ())
+    }
+
+    fn set_priority(&self, todo_id: &str, priority: Priority) -> Result<(), BaseError> {
+        self.change(|data| {
+            let todo = data.todo_mut(todo_id)?;
+
+            todo.priority = priority;
+            todo.updated_at = chrono::Local::now().naive_local();
+
+            Ok(())
+        })
+    }
+
+    fn move_todo(&self, todo_id: &str, target: serde_json::Value) -> Result<TodoItem, BaseError> {
+        let target: MoveTodoDTO = serde_json::from_value(target)?;
+
+        target.validate().map_err(api_error)?;
+
+        let (anchor_id, before) = match (target.before, target.after) {
+            (Some(anchor_id), None) => (anchor_id, true),
+            (_, after) => (after.unwrap_or_default(), false),
+        };
+
+        self.change(|data| {
+            let todo = data.todo_mut(todo_id)?.todo.clone();
+
+            if todo.id == anchor_id {
+                return Err(api_error(TodoApiError::BadRequest(
+                    "A todo can not be moved next to itself".into(),
+                )));
+            }
+
+            let anchor = |data: &mut LocalData| -> Result<Todo, BaseError> {
+                Ok(data.todo_mut(anchor_id.to_string().as_str())?.todo.clone())
+            };
+
+            let first_anchor = anchor(data)?;
+
+            let new_position =
+                match free_position_next_to(&data.todos, todo.id, &first_anchor, before) {
+                    Some(new_position) => new_position,
+                    None => {
+                        // Only when the gap is used up are all positions rewritten
+                        data.todos.sort_by(|a, b| {
+                            (a.position, b.created_at).cmp(&(b.position, a.created_at))
+                        });
+
+                        for (index, todo) in data.todos.iter_mut().enumerate() {
+                            todo.position = (index as i64 + 1) * POSITION_GAP;
+                        }
+
+                        let anchor = anchor(data)?;
+
+                        free_position_next_to(&data.todos, todo.id, &anchor, before).ok_or_else(
+                            || {
+                                api_error(TodoApiError::BadRequest(
+                                    "Todo could not be moved".into(),
+                                ))
+                            },
+                        )?
+                    }
+                };
+
+            let moved = data.todo_mut(todo_id)?;
+
+            moved.position = new_position;
+            moved.updated_at = chrono::Local::now().naive_local();
+
+            let moved = moved.clone();
+
+            Ok(data.item(&moved))
+        })
+    }
+
+    fn delete_todo(&self, todo: &Todo) -> Result<(), BaseError> {
+        self.change(|data| data.trash_todo(todo.id.to_string().as_str()))
+    }
+
+    fn trash(&self) -> Result<(Vec<TodoItem>, i64), BaseError> {
+        let data = self.load()?;
+
+        let mut trash: Vec<TodoItem> = data
+            .todos
+            .iter()
+            .filter(|todo| todo.deleted_at.is_some())
+            .map(|todo| data.item(todo))
+            .collect();
+
+        trash.sort_by_key(|todo| std::cmp::Reverse(todo.deleted_at));
+
+        Ok((trash, *TRASH_RETENTION_DAYS))
+    }
+
+    fn restore_todo(&self, todo_id: &str) -> Result<TodoItem, BaseError> {
+        let todo_id = uuid::Uuid::parse_str(todo_id).map_err(|_| not_found("Todo"))?;
+
+        self.change(|data| {
+            let todo = data
+                .todos
+                .iter_mut()
+                .find(|todo| todo.id == todo_id && todo.deleted_at.is_some())
+                .ok_or_else(|| not_found("Todo"))?;
+
+            todo.deleted_at = None;
+            todo.updated_at = chrono::Local::now().naive_local();
+
+            let restored = todo.clone();
+
+            Ok(data.item(&restored))
+        })
+    }
+
+    fn archive_done(&self) -> Result<u64, BaseError> {
+        self.change(|data| {
+            let now = chrono::Local::now().naive_local();
+
+            let mut archived = 0;
+
+            for todo in data.todos.iter_mut().filter(|todo| {
+                todo.completed && todo.archived_at.is_none() && todo.deleted_at.is_none()
+            }) {
+                todo.archived_at = Some(now);
+                archived += 1;
+            }
+
+            Ok(archived)
+        })
+    }
+
+    fn search(&self, query: &str) -> Result<Vec<SearchHit>, BaseError> {
+        let search: SearchQueryDTO = serde_json::from_value(json!({ "q": query }))?;
+
+        search.validate().map_err(api_error)?;
+
+        let words: Vec<String> = search.words().map(|word| word.to_lowercase()).collect();
+
+        let data = self.load()?;
+
+        let mut hits: Vec<SearchHit> = vec![];
+
+        for todo in data.todos.iter().filter(|todo| todo.deleted_at.is_none()) {
+            let (highlighted_title, in_title) = highlight(&todo.title, &words);
+
+            // Only the lines of the notes with a match make it to the snippet
+            let mut snippet_lines: Vec<String> = vec![];
+            let mut in_notes: Vec<&String> = vec![];
+
+            for line in todo.notes.as_deref().unwrap_or_default().lines() {
+                let (highlighted, found) = highlight(line, &words);
+
+                if !found.is_empty() {
+                    snippet_lines.push(highlighted.trim().to_string());
+                    in_notes.extend(found);
+                }
+            }
+
+            // Todos need every word, in the title or in the notes
+            if !words
+                .iter()
+                .all(|word| in_title.contains(&word) || in_notes.contains(&word))
+            {
+                continue;
+            }
+
+            hits.push(SearchHit {
+                todo: data.item(todo),
+                // Matches in the title count more, as they do for the api
+                rank: in_title.len() as f32 + 0.4 * in_notes.len() as f32,
+                highlighted_title,
+                snippet: match snippet_lines.is_empty() {
+                    true => None,
+                    false => Some(snippet_lines.join(" ... ")),
+                },
+            });
+        }
+
+        hits.sort_by(|a, b| {
+            b.rank
+                .total_cmp(&a.rank)
+                .then(b.todo.updated_at.cmp(&a.todo.updated_at))
+        });
+
+        hits.truncate(search.limit as usize);
+
+        Ok(hits)
+    }
+
+    fn bulk(&self, operations: Vec<serde_json::Value>) -> Result<Vec<BulkResult>, BaseError> {
+        let bulk: BulkDTO = serde_json::from_value(json!({ "operations": operations }))?;
+
+        bulk.validate().map_err(api_error)?;
+
+        self.change(|data| {
+            let results = bulk
+                .operations
+                .iter()
+                .map(|operation| {
+                    // An operation that fails is undone without undoing the others
+                    let before = data.clone();
+
+                    match data.apply_bulk_operation(operation) {
+                        Ok(todo) => BulkResult {
+                            id: operation.todo_id(),
+                            ok: true,
+                            error: None,
+                            todo,
+                        },
+                        Err(e) => {
+                            *data = before;
+
+                            BulkResult {
+                                id: operation.todo_id(),
+                                ok: false,
+                                error: Some(e.to_string()),
+                                todo: None,
+                            }
+                        }
+                    }
+                })
+                .collect();
+
+            Ok(results)
+        })
+    }
+
+    fn subtasks(&self, todo_id: &str) -> Result<Vec<Subtask>, BaseError> {
+        let mut data = self.load()?;
+
+        let todo_id = data.todo_mut(todo_id)?.id;
+
+        let mut list: Vec<Subtask> = data
+            .subtasks
+            .into_iter()
+            .filter(|subtask| subtask.todo_id == todo_id)
+            .collect();
+
+        list.sort_by_key(|subtask| subtask.position);
+
+        Ok(list)
+    }
+
+    fn add_subtask(&self, todo_id: &str, title: String) -> Result<Subtask, BaseError> {
+        let subtask = SubtaskDTO { title };
+
+        subtask.validate().map_err(api_error)?;
+
+        self.change(|data| {
+            let todo_id = data.todo_mut(todo_id)?.id;
+
+            let last_position = data
+                .subtasks
+                .iter()
+                .filter(|subtask| subtask.todo_id == todo_id)
+                .map(|subtask| subtask.position)
+                .max();
+
+            let new_subtask = Subtask::from(
+                subtask.title.trim().to_string(),
+                todo_id,
+                last_position.map_or(0, |position| position + 1),
+            );
+
+            data.subtasks.push(new_subtask.clone());
+
+            Ok(new_subtask)
+        })
+    }
+
+    fn projects(&self) -> Result<Vec<Project>, BaseError> {
+        let mut projects = self.load()?.projects;
+
+        projects.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(projects)
+    }
+
+    fn create_project(&self, name: &str) -> Result<Project, BaseError> {
+        let project = ProjectDTO {
+            name: name.to_string(),
+        };
+
+        project.validate().map_err(api_error)?;
+
+        self.change(|data| {
+            let name = project.name.trim().to_string();
+
+            // Names are unique, as they are for the api
+            if data.projects.iter().any(|project| project.name == name) {
+                return Err(api_error(TodoApiError::BadRequest(format!(
+                    "Project {} already exists",
+                    name
+                ))));
+            }
+
+            let new_project = Project::from(name, uuid::Uuid::nil());
+
+            data.projects.push(new_project.clone());
+
+            Ok(new_project)
+        })
+    }
+}
+
+#[cfg(test)]
+mod local_test {
+    use super::{highlight, LocalBackend};
+    use crate::{
+        backend::TodoBackend,
+        models::{
+            subtask_model::Progress,
+            todo_model::{Recurrence, Todo, TodoItem},
+        },
+    };
+
+    #[test]
+    fn test_highlight() {
+        let words = vec!["rep".to_string(), "milk".to_string()];
+
+        let (highlighted, found) = highlight("Write the Report, buy milk", &words);
+
+        assert_eq!(
+            highlighted,
+            "Write the \u{2}Report\u{3}, buy \u{2}milk\u{3}"
+        );
+        assert_eq!(found, vec!["rep", "milk"]);
+    }
+
+    #[test]
+    fn test_local_backend() {
+        let mut path = std::env::temp_dir();
+        path.push(format!("todo-{}.json", uuid::Uuid::new_v4()));
+
+        let backend = LocalBackend { path: path.clone() };
+
+        let mut todo = Todo::from("Water the plants".into(), uuid::Uuid::nil());
+        todo.recurrence = Some("daily".parse::<Recurrence>().unwrap());
+
+        let created = backend
+            .create_todo(&TodoItem {
+                todo,
+                tags: vec!["home".into()],
+                progress: Progress::default(),
+            })
+            .unwrap();
+
+        backend.complete_todo(&created).unwrap();
+
+        // The next occurrence is open, in the same series
+        let open = backend
+            .list_todos(&[("completed".into(), "false".into())])
+            .unwrap();
+
+        assert_eq!(open.len(), 1);
+        assert_ne!(open[0].id, created.id);
+        assert_eq!(open[0].series_id, Some(created.id));
+        assert_eq!(open[0].tags, vec!["home"]);
+
+        backend.delete_todo(&open[0]).unwrap();
+
+        assert_eq!(backend.search("plants").unwrap().len(), 1);
+        assert_eq!(backend.trash().unwrap().0.len(), 1);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,186 @@
+use std::cmp::Ordering;
+
+use crate::{
+    api::dtos::todo::{DueFilter, TagMatch, TodoListQuery, TodoSort},
+    errors::{BaseError, TodoError},
+    models::{
+        project_model::Project,
+        subtask_model::Subtask,
+        todo_model::{BulkResult, Priority, SearchHit, Todo, TodoItem},
+    },
+};
+
+pub mod http;
+pub mod local;
+
+pub use http::HttpBackend;
+pub use local::LocalBackend;
+
+/// Where the cli and the todo list keep the todos,
+/// the api server or a file on this machine
+///
+/// Changes that follow the api take the same json bodies as its endpoints
+pub trait TodoBackend {
+    /// Todos matching the query pairs of `GET /api/todo`, every page of them
+    fn list_todos(&self, query: &[(String, String)]) -> Result<Vec<TodoItem>, BaseError>;
+
+    fn get_todo(&self, todo_id: &str) -> Result<TodoItem, BaseError>;
+
+    /// Create a todo from what the user filled in of `todo`
+    fn create_todo(&self, todo: &TodoItem) -> Result<TodoItem, BaseError>;
+
+    /// Apply `changes`, the body of `PATCH /api/todo/{id}`
+    fn update_todo(&self, todo_id: &str, changes: serde_json::Value)
+        -> Result<TodoItem, BaseError>;
+
+    /// Complete a todo, recurring todos come back as their next occurrence
+    fn complete_todo(&self, todo: &Todo) -> Result<(), BaseError>;
+
+    fn set_priority(&self, todo_id: &str, priority: Priority) -> Result<(), BaseError>;
+
+    /// Move a todo next to another one, `target` is the body of `POST /api/todo/{id}/move`
+    fn move_todo(&self, todo_id: &str, target: serde_json::Value) -> Result<TodoItem, BaseError>;
+
+    /// Move a todo to the trash
+    fn delete_todo(&self, todo: &Todo) -> Result<(), BaseError>;
+
+    /// Todos in the trash, last deleted first, along with
+    /// the days they stay there before being deleted for good
+    fn trash(&self) -> Result<(Vec<TodoItem>, i64), BaseError>;
+
+    fn restore_todo(&self, todo_id: &str) -> Result<TodoItem, BaseError>;
+
+    /// Archive every completed todo, returns how many were archived
+    fn archive_done(&self) -> Result<u64, BaseError>;
+
+    /// Todos whose title or notes have all the words of `query`, best matches first
+    fn search(&self, query: &str) -> Result<Vec<SearchHit>, BaseError>;
+
+    /// Apply `operations`, as sent to `POST /api/todo/bulk`
+    fn bulk(&self, operations: Vec<serde_json::Value>) -> Result<Vec<BulkResult>, BaseError>;
+
+    /// Subtasks of a todo, in checklist order
+    fn subtasks(&self, todo_id: &str) -> Result<Vec<Subtask>, BaseError>;
+
+    fn add_subtask(&self, todo_id: &str, title: String) -> Result<Subtask, BaseError>;
+
+    fn projects(&self) -> Result<Vec<Project>, BaseError>;
+
+    fn create_project(&self, name: &str) -> Result<Project, BaseError>;
+
+    /// Whether the last request found the server unreachable,
+    /// changes are then kept to be sent later
+    fn is_offline(&self) -> bool {
+        false
+    }
+
+    /// When the todos listed while offline were last synced with the server
+    fn synced_at(&self) -> Option<chrono::NaiveDateTime> {
+        None
+    }
+}
+
+/// Narrow `todos` down to the ones a list query returns, in the order it asks
+/// for. Deleted todos are never listed and pages are left to the caller
+pub fn filter_todos(
+    todos: &[TodoItem],
+    projects: &[Project],
+    query: &[(String, String)],
+) -> Result<Vec<TodoItem>, BaseError> {
+    let query = TodoListQuery::try_from(query.to_vec()).map_err(TodoError::ApiError)?;
+
+    let now = chrono::Local::now().naive_local();
+
+    // Projects can be referred to by id or by name
+    let project_id = match &query.project {
+        Some(project) => Some(
+            projects
+                .iter()
+                .find(|p| p.name == *project || p.id.to_string() == *project)
+                .map(|p| p.id)
+                .ok_or(format!("Project {} not found", project))?,
+        ),
+        None => None,
+    };
+
+    let text = query.text.as_ref().map(|text| text.to_lowercase());
+
+    let mut list: Vec<TodoItem> = todos
+        .iter()
+        .filter(|todo| todo.deleted_at.is_none())
+        .filter(|todo| project_id.is_none() || todo.project_id == project_id)
+        .filter(|todo| query.include_archived || todo.archived_at.is_none())
+        .filter(|todo| query.series.is_none() || todo.series_id == query.series)
+        .filter(|todo| query.completed.is_none_or(|done| todo.completed == done))
+        .filter(|todo| query.created_before.is_none_or(|t| todo.created_at < t))
+        .filter(|todo| query.created_after.is_none_or(|t| todo.created_at > t))
+        .filter(|todo| query.updated_since.is_none_or(|t| todo.updated_at >= t))
+        .filter(|todo| {
+            let mut tags = query.tags.iter();
+
+            match query.tag_match {
+                _ if query.tags.is_empty() => true,
+                TagMatch::Any => tags.any(|tag| todo.tags.contains(tag)),
+                TagMatch::All => tags.all(|tag| todo.tags.contains(tag)),
+            }
+        })
+        .filter(|todo| match (query.due, todo.due_at) {
+            (None, _) => true,
+            (Some(_), None) => false,
+            (Some(due), Some(due_at)) => {
+                let (from, until) = due.window(now);
+
+                due_at < until
+                    && from.is_none_or(|from| due_at >= from)
+                    && !(matches!(due, DueFilter::Overdue) && todo.completed)
+            }
+        })
+        .filter(|todo| match &text {
+            Some(text) => {
+                todo.title.to_lowercase().contains(text)
+                    || todo
+                        .notes
+                        .as_ref()
+                        .is_some_and(|notes| notes.to_lowercase().contains(text))
+            }
+            None => true,
+        })
+        .cloned()
+        .collect();
+
+    // Same keys as the api, open todos first and ties broken by id
+    list.sort_by(|a, b| {
+        let order = match query.sort {
+            TodoSort::Manual => a
+                .position
+                .cmp(&b.position)
+                .then(b.created_at.cmp(&a.created_at)),
+            TodoSort::Priority => b
+                .priority
+                .cmp(&a.priority)
+                .then(compare_due(a, b))
+                .then(b.created_at.cmp(&a.created_at)),
+            TodoSort::Created => b.created_at.cmp(&a.created_at),
+            TodoSort::Updated => b.updated_at.cmp(&a.updated_at),
+            TodoSort::Due => compare_due(a, b),
+            TodoSort::Title => a.title.cmp(&b.title),
+        };
+
+        a.completed
+            .cmp(&b.completed)
+            .then(order)
+            .then(a.id.cmp(&b.id))
+    });
+
+    Ok(list)
+}
+
+/// Soonest due first, todos without a due date last
+fn compare_due(a: &Todo, b: &Todo) -> Ordering {
+    match (a.due_at, b.due_at) {
+        (Some(a), Some(b)) => a.cmp(&b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
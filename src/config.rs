@@ -1,3 +1,5 @@
 lazy_static::lazy_static! {
     pub static ref API_URL: String = std::env::var("API_URL").unwrap_or_else(|_| String::from("localhost:5900"));
+    /// `local` keeps the todos in a file instead of going through the api server
+    pub static ref BACKEND: String = std::env::var("TODO_BACKEND").unwrap_or_else(|_| String::from("http"));
 }
@@ -4,7 +4,6 @@ use crate::api::errors::TodoApiError;
 
 #[derive(Debug)]
 pub enum TodoError {
-    #[allow(dead_code)]
     ApiError(TodoApiError),
     HttpErrror(String),
     OperationError,
@@ -5,15 +5,18 @@ use api::api::start_server;
 #[macro_use]
 extern crate diesel;
 
+use backend::{HttpBackend, LocalBackend, TodoBackend};
 use clap::{Parser, Subcommand};
 use serde::{Deserialize, Serialize};
 use utils::is_server_running;
 
-use crate::utils::{make_api_url, save_token};
+use crate::utils::{get_saved_token, make_api_url, remove_credentials, save_credentials};
 mod api;
+mod backend;
 mod config;
 mod errors;
 mod models;
+mod offline;
 mod schema;
 mod todo_commands;
 mod ui;
@@ -28,10 +31,55 @@ struct TodoConfig {
 enum Commands {
     Login,
     Signup,
+    /// Logout, the saved tokens stop working
+    Logout,
+    /// Choose a new password with a reset code sent by mail
+    ResetPassword,
     #[clap(alias = "ls")]
-    List,
+    List {
+        /// Only show todos that are overdue, due today or due this week
+        #[clap(long, value_parser = ["overdue", "today", "week"])]
+        due: Option<String>,
+        /// Only show todos of this project
+        #[clap(short, long)]
+        project: Option<String>,
+        /// Only show todos with this tag, can be repeated
+        #[clap(short, long = "tag")]
+        tags: Vec<String>,
+        /// Todos need all the tags instead of any of them
+        #[clap(long)]
+        all_tags: bool,
+        /// Also show the archived todos
+        #[clap(long)]
+        archived: bool,
+        /// Order of the todos, the order you arranged them in by default
+        #[clap(long, value_parser = ["manual", "priority", "created", "updated", "due", "title"])]
+        sort: Option<String>,
+    },
     #[clap(alias = "c")]
-    Create,
+    Create {
+        /// Add the todo to this project
+        #[clap(short, long)]
+        project: Option<String>,
+    },
+    /// Edit the title, tags, due date, priority, recurrence or notes of a todo
+    #[clap(alias = "e")]
+    Edit {
+        /// Id of the todo to edit
+        id: String,
+    },
+    /// Search the titles and notes of the todos
+    Search {
+        /// Words to look for, todos need all of them
+        #[clap(required = true)]
+        query: Vec<String>,
+    },
+    /// Show the deleted todos and pick the ones to restore
+    Trash {
+        /// Restore the todo with this id right away
+        #[clap(long)]
+        restore: Option<String>,
+    },
 }
 
 #[derive(Debug, Parser)]
@@ -40,6 +88,10 @@ struct TodoArgs {
     #[clap(short = 's', long = "start-server")]
     start_server: bool,
 
+    /// Keep the todos in a file on this machine, no server needed
+    #[clap(short = 'l', long = "local")]
+    local: bool,
+
     #[clap(subcommand)]
     command: Option<Commands>,
 }
@@ -70,19 +122,31 @@ fn prompt_signup() -> Result<(), Box<dyn std::error::Error>> {
     let resp_json: serde_json::Value = resp.json()?;
 
     if resp_json.is_object() && res_status == 200 {
-        let data = resp_json.as_object().ok_or("Invalid response")?;
+        println!("Signup Successful");
 
-        let token = data.get("token").ok_or("Token Not Found, Signup Failed")?;
+        println!("A verification link was mailed to {}", email);
 
-        save_token(token.as_str().unwrap())?;
+        // No tokens means the email has to be verified before logging in
+        if resp_json.get("token").is_none_or(|token| token.is_null()) {
+            println!("Open it, then login");
 
-        println!("Signup Successful");
+            return Ok(());
+        }
+
+        let credentials =
+            serde_json::from_value(resp_json).map_err(|_| "Token Not Found, Signup Failed")?;
+
+        save_credentials(&credentials)?;
 
         println!("You are now logged in");
 
         return Ok(());
     }
 
+    if let Some(error) = resp_json.get("error").and_then(|error| error.as_str()) {
+        return Err(error.into());
+    }
+
     Err(Box::new(Error::new(
         std::io::ErrorKind::Other,
         "Invalid Response for Signup",
@@ -112,14 +176,15 @@ fn prompt_login() -> Result<(), Box<dyn std::error::Error>> {
 
     let resp_json: serde_json::Value = resp.json()?;
 
-    if resp_json.is_object() {
-        let data = resp_json.as_object().ok_or("Invalid response")?;
+    if let Some(error) = resp_json.get("error").and_then(|error| error.as_str()) {
+        return Err(error.into());
+    }
 
-        let token = data
-            .get("token")
-            .ok_or("Token Not Found in response, Login Failed")?;
+    if resp_json.is_object() {
+        let credentials = serde_json::from_value(resp_json)
+            .map_err(|_| "Token Not Found in response, Login Failed")?;
 
-        save_token(token.as_str().unwrap())?;
+        save_credentials(&credentials)?;
 
         println!("You are not logged in");
 
@@ -132,6 +197,77 @@ fn prompt_login() -> Result<(), Box<dyn std::error::Error>> {
     )))
 }
 
+// Reset a forgotten password through a code sent by mail
+fn prompt_reset_password() -> Result<(), Box<dyn std::error::Error>> {
+    use inquire::{Password, Text};
+
+    println!("Reset Todo password");
+
+    let client = reqwest::blocking::Client::new();
+
+    let email = Text::new("Email").prompt()?;
+
+    let resp = client
+        .post(make_api_url("auth/forgot-password"))
+        .json(&serde_json::json!({ "email": email }))
+        .send()?;
+
+    if !resp.status().is_success() {
+        return Err("Couldn't send the reset code".into());
+    }
+
+    println!("If {} has an account, a reset code was mailed to it", email);
+
+    let token = Text::new("Reset code").prompt()?;
+
+    let pass = Password::new("New Password").prompt()?;
+
+    let resp = client
+        .post(make_api_url("auth/reset-password"))
+        .json(&serde_json::json!({ "token": token.trim(), "password": pass }))
+        .send()?;
+
+    if !resp.status().is_success() {
+        let body: serde_json::Value = resp.json().unwrap_or_default();
+
+        return Err(body
+            .get("error")
+            .and_then(|error| error.as_str())
+            .unwrap_or("Password Reset Failed")
+            .into());
+    }
+
+    // The reset ended every session, including the one saved here
+    remove_credentials()?;
+
+    println!("Password changed, login with the new password");
+
+    Ok(())
+}
+
+/// Logout, the server ends the session before the credentials are removed
+fn logout() -> Result<(), Box<dyn std::error::Error>> {
+    use reqwest::header::AUTHORIZATION;
+
+    let token = get_saved_token().map_err(|_| "Not logged in")?;
+
+    let resp = reqwest::blocking::Client::new()
+        .post(make_api_url("auth/logout"))
+        .header(AUTHORIZATION, format!("Bearer {}", token))
+        .send()?;
+
+    // A token that is already rejected has nothing left to revoke
+    if !resp.status().is_success() && resp.status() != reqwest::StatusCode::UNAUTHORIZED {
+        return Err("Logout Failed".into());
+    }
+
+    remove_credentials()?;
+
+    println!("You are now logged out");
+
+    Ok(())
+}
+
 /// Wrapper function for looping a prompt function
 /// if error occurs
 fn super_prompt(title: &str, function: Box<dyn Fn() -> Result<(), Box<dyn std::error::Error>>>) {
@@ -173,15 +309,47 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     }
 
+    let local = args.local || config::BACKEND.as_str() == "local";
+
+    let backend: Box<dyn TodoBackend> = match local {
+        true => Box::new(LocalBackend::new()),
+        false => Box::new(HttpBackend::new()),
+    };
+
+    // Changes made offline go before anything else reaches the server
+    if !local
+        && !matches!(
+            args.command,
+            None | Some(Commands::Login) | Some(Commands::Signup) | Some(Commands::ResetPassword)
+        )
+    {
+        if let Err(e) = todo_commands::replay_offline_changes() {
+            eprintln!("Couldn't send the changes made offline: {}", e);
+        }
+    }
+
     match &args.command {
+        Some(Commands::Login | Commands::Signup | Commands::Logout | Commands::ResetPassword)
+            if local =>
+        {
+            println!("No account is needed for local todos");
+        }
         Some(Commands::Login) => {
             super_prompt("Login", Box::new(prompt_login));
         }
         Some(Commands::Signup) => {
             super_prompt("Signup", Box::new(prompt_signup));
         }
-        Some(Commands::Create) => {
-            let x = todo_commands::create_new_todo();
+        Some(Commands::ResetPassword) => {
+            super_prompt("Reset Password", Box::new(prompt_reset_password));
+        }
+        Some(Commands::Logout) => {
+            if let Err(e) = logout() {
+                eprintln!("{}", e);
+            }
+        }
+        Some(Commands::Create { project }) => {
+            let x = todo_commands::create_new_todo(backend.as_ref(), project.as_deref());
 
             match x {
                 Ok(_) => {}
@@ -190,8 +358,39 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 }
             }
         }
-        Some(Commands::List) => {
-            let res = todo_commands::list_todos();
+        Some(Commands::Edit { id }) => {
+            if let Err(e) = todo_commands::edit_todo(backend.as_ref(), id) {
+                eprintln!("{}", e);
+            }
+        }
+        Some(Commands::Search { query }) => {
+            if let Err(e) = todo_commands::search_todos(backend.as_ref(), query.join(" ").as_str())
+            {
+                eprintln!("{}", e);
+            }
+        }
+        Some(Commands::Trash { restore }) => {
+            if let Err(e) = todo_commands::show_trash(backend.as_ref(), restore.as_deref()) {
+                eprintln!("{}", e);
+            }
+        }
+        Some(Commands::List {
+            due,
+            project,
+            tags,
+            all_tags,
+            archived,
+            sort,
+        }) => {
+            let res = todo_commands::list_todos(
+                backend.as_ref(),
+                due.as_deref(),
+                project.as_deref(),
+                tags,
+                *all_tags,
+                *archived,
+                sort.as_deref(),
+            );
 
             match res {
                 Err(e) => {
@@ -0,0 +1,30 @@
+use crate::schema::*;
+use diesel::{Insertable, Queryable};
+
+/// A verification mail sent to a user, following its link verifies the email
+#[derive(Debug, Clone, Insertable, Queryable)]
+#[table_name = "email_verifications"]
+pub struct EmailVerification {
+    pub id: uuid::Uuid,
+    pub user_id: uuid::Uuid,
+    /// SHA-256 of the token, the token itself is only in the mail
+    pub token_hash: String,
+    pub created_at: chrono::NaiveDateTime,
+    pub expires_at: chrono::NaiveDateTime,
+}
+
+impl EmailVerification {
+    pub fn from(
+        user_id: uuid::Uuid,
+        token_hash: String,
+        expires_at: chrono::NaiveDateTime,
+    ) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4(),
+            user_id,
+            token_hash,
+            created_at: chrono::Local::now().naive_local(),
+            expires_at,
+        }
+    }
+}
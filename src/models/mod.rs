@@ -1,3 +1,9 @@
+pub(crate) mod email_verification_model;
+pub(crate) mod password_reset_model;
+pub(crate) mod project_model;
+pub(crate) mod session_model;
+pub(crate) mod subtask_model;
+pub(crate) mod tag_model;
 pub(crate) mod todo_model;
 pub(crate) mod user_model;
 
@@ -0,0 +1,38 @@
+use crate::schema::*;
+use diesel::{Insertable, Queryable};
+
+/// A password reset requested by a user, the token is sent to their email
+#[derive(Debug, Clone, Insertable, Queryable)]
+#[table_name = "password_resets"]
+pub struct PasswordReset {
+    pub id: uuid::Uuid,
+    pub user_id: uuid::Uuid,
+    /// SHA-256 of the token, the token itself is only in the mail
+    pub token_hash: String,
+    pub created_at: chrono::NaiveDateTime,
+    pub expires_at: chrono::NaiveDateTime,
+    /// When the token was used, or made useless by another reset
+    pub used_at: Option<chrono::NaiveDateTime>,
+}
+
+impl PasswordReset {
+    pub fn from(
+        user_id: uuid::Uuid,
+        token_hash: String,
+        expires_at: chrono::NaiveDateTime,
+    ) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4(),
+            user_id,
+            token_hash,
+            created_at: chrono::Local::now().naive_local(),
+            expires_at,
+            used_at: None,
+        }
+    }
+
+    /// Whether the token can still reset the password at `now`
+    pub fn is_usable(&self, now: chrono::NaiveDateTime) -> bool {
+        self.used_at.is_none() && self.expires_at > now
+    }
+}
@@ -0,0 +1,26 @@
+use crate::schema::*;
+use diesel::{Insertable, Queryable};
+use serde::{Deserialize, Serialize};
+
+/// A named list todos can be grouped into
+#[derive(Debug, Clone, Serialize, Deserialize, Insertable, Queryable)]
+#[table_name = "projects"]
+pub struct Project {
+    pub id: uuid::Uuid,
+    pub name: String,
+    pub user_id: uuid::Uuid,
+    pub created_at: chrono::NaiveDateTime,
+    pub updated_at: chrono::NaiveDateTime,
+}
+
+impl Project {
+    pub fn from(name: String, user_id: uuid::Uuid) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4(),
+            name,
+            user_id,
+            created_at: chrono::Local::now().naive_local(),
+            updated_at: chrono::Local::now().naive_local(),
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use crate::schema::*;
+use diesel::{Insertable, Queryable};
+
+/// A login of a user, access tokens carry its id as `jti`. It ends when
+/// the user logs out or when its refresh token isn't used in time
+#[derive(Debug, Clone, Insertable, Queryable)]
+#[table_name = "sessions"]
+pub struct Session {
+    pub id: uuid::Uuid,
+    pub user_id: uuid::Uuid,
+    /// SHA-256 of the refresh token, the token itself is only known to the client
+    pub refresh_token_hash: String,
+    pub created_at: chrono::NaiveDateTime,
+    /// When the refresh token stops working, pushed back on every refresh
+    pub expires_at: chrono::NaiveDateTime,
+    /// When the user logged out
+    pub revoked_at: Option<chrono::NaiveDateTime>,
+}
+
+impl Session {
+    pub fn from(
+        user_id: uuid::Uuid,
+        refresh_token_hash: String,
+        expires_at: chrono::NaiveDateTime,
+    ) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4(),
+            user_id,
+            refresh_token_hash,
+            created_at: chrono::Local::now().naive_local(),
+            expires_at,
+            revoked_at: None,
+        }
+    }
+
+    /// Whether tokens of the session are still accepted at `now`
+    pub fn is_active(&self, now: chrono::NaiveDateTime) -> bool {
+        self.revoked_at.is_none() && self.expires_at > now
+    }
+}
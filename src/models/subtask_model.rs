@@ -0,0 +1,98 @@
+use std::collections::HashMap;
+
+use crate::api::errors::TodoApiError;
+use crate::schema::*;
+use diesel::prelude::*;
+use diesel::{Insertable, PgConnection, Queryable};
+use serde::{Deserialize, Serialize};
+
+/// A checklist item of a todo, ordered by `position`
+#[derive(Debug, Clone, Serialize, Deserialize, Insertable, Queryable)]
+#[table_name = "subtasks"]
+pub struct Subtask {
+    pub id: uuid::Uuid,
+    pub todo_id: uuid::Uuid,
+    pub title: String,
+    pub completed: bool,
+    pub position: i32,
+    pub created_at: chrono::NaiveDateTime,
+    pub updated_at: chrono::NaiveDateTime,
+}
+
+impl Subtask {
+    pub fn from(title: String, todo_id: uuid::Uuid, position: i32) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4(),
+            todo_id,
+            title,
+            completed: false,
+            position,
+            created_at: chrono::Local::now().naive_local(),
+            updated_at: chrono::Local::now().naive_local(),
+        }
+    }
+}
+
+/// How many of the subtasks of a todo are done
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Progress {
+    pub done: usize,
+    pub total: usize,
+}
+
+impl std::fmt::Display for Progress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{} done", self.done, self.total)
+    }
+}
+
+/// Subtask progress of each of the given todos,
+/// todos without subtasks are left out
+pub fn progress_for_todos(
+    conn: &PgConnection,
+    todo_ids: &[uuid::Uuid],
+) -> Result<HashMap<uuid::Uuid, Progress>, TodoApiError> {
+    let rows: Vec<(uuid::Uuid, bool)> = subtasks::table
+        .filter(subtasks::todo_id.eq_any(todo_ids))
+        .select((subtasks::todo_id, subtasks::completed))
+        .load(conn)?;
+
+    let mut progress_by_todo: HashMap<uuid::Uuid, Progress> = HashMap::new();
+
+    for (todo_id, completed) in rows {
+        let progress = progress_by_todo.entry(todo_id).or_default();
+
+        progress.total += 1;
+
+        if completed {
+            progress.done += 1;
+        }
+    }
+
+    Ok(progress_by_todo)
+}
+
+/// Copy the subtasks of one todo to another, unchecked
+pub fn copy_subtasks(
+    conn: &PgConnection,
+    from_todo_id: uuid::Uuid,
+    to_todo_id: uuid::Uuid,
+) -> Result<(), TodoApiError> {
+    let copies: Vec<Subtask> = subtasks::table
+        .filter(subtasks::todo_id.eq(from_todo_id))
+        .order(subtasks::position.asc())
+        .load::<Subtask>(conn)?
+        .into_iter()
+        .map(|subtask| Subtask::from(subtask.title, to_todo_id, subtask.position))
+        .collect();
+
+    if copies.is_empty() {
+        return Ok(());
+    }
+
+    diesel::insert_into(subtasks::table)
+        .values(&copies)
+        .execute(conn)?;
+
+    Ok(())
+}
@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+
+use crate::api::errors::TodoApiError;
+use crate::schema::*;
+use diesel::prelude::*;
+use diesel::{Insertable, PgConnection, Queryable};
+use serde::{Deserialize, Serialize};
+
+/// A free-form label, unique by name per user
+#[derive(Debug, Clone, Serialize, Deserialize, Insertable, Queryable)]
+#[table_name = "tags"]
+pub struct Tag {
+    pub id: uuid::Uuid,
+    pub name: String,
+    pub user_id: uuid::Uuid,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+impl Tag {
+    pub fn from(name: String, user_id: uuid::Uuid) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4(),
+            name,
+            user_id,
+            created_at: chrono::Local::now().naive_local(),
+        }
+    }
+}
+
+/// Row of the `todo_tags` join table
+#[derive(Debug, Clone, Insertable, Queryable)]
+#[table_name = "todo_tags"]
+pub struct TodoTag {
+    pub todo_id: uuid::Uuid,
+    pub tag_id: uuid::Uuid,
+}
+
+/// Names of the tags of each of the given todos, sorted by name
+pub fn tags_for_todos(
+    conn: &PgConnection,
+    todo_ids: &[uuid::Uuid],
+) -> Result<HashMap<uuid::Uuid, Vec<String>>, TodoApiError> {
+    let rows: Vec<(uuid::Uuid, String)> = todo_tags::table
+        .inner_join(tags::table)
+        .filter(todo_tags::todo_id.eq_any(todo_ids))
+        .select((todo_tags::todo_id, tags::name))
+        .order(tags::name.asc())
+        .load(conn)?;
+
+    let mut tags_by_todo: HashMap<uuid::Uuid, Vec<String>> = HashMap::new();
+
+    for (todo_id, name) in rows {
+        tags_by_todo.entry(todo_id).or_default().push(name);
+    }
+
+    Ok(tags_by_todo)
+}
+
+/// Replace the tags of a todo with `names`,
+/// tags that don't exist yet are created for the user
+pub fn set_todo_tags(
+    conn: &PgConnection,
+    user_id: uuid::Uuid,
+    todo_id: uuid::Uuid,
+    names: &[String],
+) -> Result<(), TodoApiError> {
+    diesel::delete(todo_tags::table.filter(todo_tags::todo_id.eq(todo_id))).execute(conn)?;
+
+    if names.is_empty() {
+        return Ok(());
+    }
+
+    let new_tags: Vec<Tag> = names
+        .iter()
+        .map(|name| Tag::from(name.to_owned(), user_id))
+        .collect();
+
+    // Tags the user already has are left as they are
+    diesel::insert_into(tags::table)
+        .values(&new_tags)
+        .on_conflict_do_nothing()
+        .execute(conn)?;
+
+    let tag_ids: Vec<uuid::Uuid> = tags::table
+        .filter(tags::user_id.eq(user_id))
+        .filter(tags::name.eq_any(names))
+        .select(tags::id)
+        .load(conn)?;
+
+    let links: Vec<TodoTag> = tag_ids
+        .into_iter()
+        .map(|tag_id| TodoTag { todo_id, tag_id })
+        .collect();
+
+    diesel::insert_into(todo_tags::table)
+        .values(&links)
+        .execute(conn)?;
+
+    Ok(())
+}
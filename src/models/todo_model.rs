@@ -1,7 +1,268 @@
+use std::{io::Write, str::FromStr};
+
+use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime};
+
+use crate::models::subtask_model::Progress;
 use crate::schema::*;
-use diesel::{Insertable, Queryable};
+use diesel::{
+    backend::Backend,
+    deserialize::{self, FromSql},
+    serialize::{self, Output, ToSql},
+    sql_types::{SmallInt, Text},
+    AsChangeset, Insertable, Queryable,
+};
 use serde::{Deserialize, Serialize};
 
+/// How important a todo is, stored as a `SMALLINT` so
+/// that todos can be ordered by it in the database
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Default,
+    Serialize,
+    Deserialize,
+    AsExpression,
+    FromSqlRow,
+)]
+#[serde(rename_all = "lowercase")]
+#[sql_type = "SmallInt"]
+pub enum Priority {
+    #[default]
+    None = 0,
+    Low = 1,
+    Medium = 2,
+    High = 3,
+    Urgent = 4,
+}
+
+impl Priority {
+    pub const ALL: [Priority; 5] = [
+        Priority::None,
+        Priority::Low,
+        Priority::Medium,
+        Priority::High,
+        Priority::Urgent,
+    ];
+
+    /// Next priority level, wraps around to `None` after `Urgent`
+    pub fn next(self) -> Priority {
+        Priority::ALL[(self as usize + 1) % Priority::ALL.len()]
+    }
+}
+
+impl std::fmt::Display for Priority {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Priority::None => write!(f, "none"),
+            Priority::Low => write!(f, "low"),
+            Priority::Medium => write!(f, "medium"),
+            Priority::High => write!(f, "high"),
+            Priority::Urgent => write!(f, "urgent"),
+        }
+    }
+}
+
+impl<DB: Backend> ToSql<SmallInt, DB> for Priority
+where
+    i16: ToSql<SmallInt, DB>,
+{
+    fn to_sql<W: Write>(&self, out: &mut Output<W, DB>) -> serialize::Result {
+        ToSql::<SmallInt, DB>::to_sql(&(*self as i16), out)
+    }
+}
+
+impl<DB: Backend> FromSql<SmallInt, DB> for Priority
+where
+    i16: FromSql<SmallInt, DB>,
+{
+    fn from_sql(bytes: Option<&DB::RawValue>) -> deserialize::Result<Self> {
+        let value = <i16 as FromSql<SmallInt, DB>>::from_sql(bytes)?;
+
+        Priority::ALL
+            .get(value as usize)
+            .copied()
+            .ok_or_else(|| format!("Unrecognized priority {}", value).into())
+    }
+}
+
+/// When a recurring todo comes back after it is completed,
+/// stored as text such as `weekly:mon,fri`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, AsExpression, FromSqlRow)]
+#[serde(try_from = "String", into = "String")]
+#[sql_type = "Text"]
+pub enum Recurrence {
+    /// `daily`
+    Daily,
+    /// `weekly:mon,wed`, on each of the weekdays
+    Weekly(Vec<chrono::Weekday>),
+    /// `monthly:15`, on that day of the month or
+    /// on the last day of shorter months
+    Monthly(u32),
+    /// `every:3`, that many days after the todo was completed
+    EveryDays(u32),
+}
+
+impl Recurrence {
+    /// Due date of the occurrence that follows a todo
+    /// due at `due_at` and completed at `completed_at`
+    ///
+    /// Occurrences keep the time of day of the series, without a due date
+    /// they are due at the end of the day. Occurrences of calendar rules
+    /// that were missed before the todo was completed are skipped
+    pub fn next_due(
+        &self,
+        due_at: Option<NaiveDateTime>,
+        completed_at: NaiveDateTime,
+    ) -> NaiveDateTime {
+        let time = due_at
+            .map(|due_at| due_at.time())
+            .unwrap_or_else(|| NaiveTime::from_hms_opt(23, 59, 59).unwrap());
+
+        let after = match (self, due_at) {
+            (Recurrence::EveryDays(days), _) => {
+                return (completed_at.date() + chrono::Duration::days(i64::from(*days)))
+                    .and_time(time)
+            }
+            (_, Some(due_at)) => due_at.max(completed_at),
+            (_, None) => completed_at.date().and_time(time),
+        };
+
+        let mut date = after.date();
+
+        while date.and_time(time) <= after || !self.falls_on(date) {
+            date = date.succ_opt().unwrap();
+        }
+
+        date.and_time(time)
+    }
+
+    /// Whether a calendar rule has an occurrence on `date`
+    fn falls_on(&self, date: NaiveDate) -> bool {
+        match self {
+            Recurrence::Daily | Recurrence::EveryDays(_) => true,
+            Recurrence::Weekly(weekdays) => weekdays.contains(&date.weekday()),
+            Recurrence::Monthly(day) => date.day() == (*day).min(last_day_of_month(date)),
+        }
+    }
+}
+
+fn last_day_of_month(date: NaiveDate) -> u32 {
+    let (year, month) = match date.month() {
+        12 => (date.year() + 1, 1),
+        month => (date.year(), month + 1),
+    };
+
+    NaiveDate::from_ymd_opt(year, month, 1)
+        .and_then(|first_of_next_month| first_of_next_month.pred_opt())
+        .map_or(31, |last_day| last_day.day())
+}
+
+impl std::fmt::Display for Recurrence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Recurrence::Daily => write!(f, "daily"),
+            Recurrence::Weekly(weekdays) => {
+                let weekdays: Vec<String> = weekdays
+                    .iter()
+                    .map(|weekday| weekday.to_string().to_lowercase())
+                    .collect();
+
+                write!(f, "weekly:{}", weekdays.join(","))
+            }
+            Recurrence::Monthly(day) => write!(f, "monthly:{}", day),
+            Recurrence::EveryDays(days) => write!(f, "every:{}", days),
+        }
+    }
+}
+
+impl FromStr for Recurrence {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_lowercase();
+
+        let (rule, value) = match s.split_once(':') {
+            Some((rule, value)) => (rule, Some(value.trim())),
+            None => (s.as_str(), None),
+        };
+
+        match (rule.trim(), value) {
+            ("daily", None) => Ok(Recurrence::Daily),
+            ("weekly", Some(weekdays)) => {
+                let mut weekdays = weekdays
+                    .split(',')
+                    .map(|weekday| {
+                        weekday
+                            .trim()
+                            .parse::<chrono::Weekday>()
+                            .map_err(|_| format!("Unknown weekday {}", weekday))
+                    })
+                    .collect::<Result<Vec<chrono::Weekday>, String>>()?;
+
+                weekdays.sort_by_key(|weekday| weekday.num_days_from_monday());
+                weekdays.dedup();
+
+                Ok(Recurrence::Weekly(weekdays))
+            }
+            ("monthly", Some(day)) => match day.parse::<u32>() {
+                Ok(day) if (1..=31).contains(&day) => Ok(Recurrence::Monthly(day)),
+                _ => Err(format!("Day of the month {} is not between 1 and 31", day)),
+            },
+            ("every", Some(days)) => match days.parse::<u32>() {
+                Ok(days) if (1..=365).contains(&days) => Ok(Recurrence::EveryDays(days)),
+                _ => Err(format!("Interval {} is not between 1 and 365 days", days)),
+            },
+            _ => Err(format!(
+                "Unknown recurrence {}, expected daily, weekly:mon,fri, monthly:15 or every:3",
+                s
+            )),
+        }
+    }
+}
+
+impl TryFrom<String> for Recurrence {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Recurrence> for String {
+    fn from(recurrence: Recurrence) -> Self {
+        recurrence.to_string()
+    }
+}
+
+impl<DB: Backend> ToSql<Text, DB> for Recurrence
+where
+    String: ToSql<Text, DB>,
+{
+    fn to_sql<W: Write>(&self, out: &mut Output<W, DB>) -> serialize::Result {
+        ToSql::<Text, DB>::to_sql(&self.to_string(), out)
+    }
+}
+
+impl<DB: Backend> FromSql<Text, DB> for Recurrence
+where
+    String: FromSql<Text, DB>,
+{
+    fn from_sql(bytes: Option<&DB::RawValue>) -> deserialize::Result<Self> {
+        let value = <String as FromSql<Text, DB>>::from_sql(bytes)?;
+
+        Ok(value.parse()?)
+    }
+}
+
+/// Space left between the positions of neighbouring todos,
+/// a todo moved between two others takes the middle of the gap
+pub const POSITION_GAP: i64 = 1024;
+
 #[derive(Debug, Clone, Serialize, Deserialize, Insertable, Queryable)]
 #[table_name = "todos"]
 pub struct Todo {
@@ -11,6 +272,169 @@ pub struct Todo {
     pub created_at: chrono::NaiveDateTime,
     pub updated_at: chrono::NaiveDateTime,
     pub user_id: uuid::Uuid,
+    pub due_at: Option<chrono::NaiveDateTime>,
+    pub priority: Priority,
+    pub project_id: Option<uuid::Uuid>,
+    pub recurrence: Option<Recurrence>,
+    /// Id of the first todo of a recurring series
+    pub series_id: Option<uuid::Uuid>,
+    /// Markdown description of the todo
+    pub notes: Option<String>,
+    /// Place of the todo in the manually ordered list, see [`POSITION_GAP`]
+    pub position: i64,
+    /// When the todo was moved to the trash
+    pub deleted_at: Option<chrono::NaiveDateTime>,
+    /// When the todo was archived, archived todos are hidden from lists
+    pub archived_at: Option<chrono::NaiveDateTime>,
+    /// When the todo was completed, `None` while it is open
+    #[serde(default)]
+    pub completed_at: Option<chrono::NaiveDateTime>,
+}
+
+/// A todo together with its data that lives in other tables,
+/// this is what the api returns for a todo
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TodoItem {
+    #[serde(flatten)]
+    pub todo: Todo,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Roll-up of the subtasks of the todo
+    #[serde(default)]
+    pub progress: Progress,
+}
+
+impl std::ops::Deref for TodoItem {
+    type Target = Todo;
+
+    fn deref(&self) -> &Self::Target {
+        &self.todo
+    }
+}
+
+impl std::ops::DerefMut for TodoItem {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.todo
+    }
+}
+
+/// Put before a matching word of a search highlight. It is a control
+/// character, which titles and notes can't have, so that no text of
+/// a todo is mistaken for a highlight
+pub const HIGHLIGHT_START: char = '\u{2}';
+
+/// Put after a matching word of a search highlight
+pub const HIGHLIGHT_END: char = '\u{3}';
+
+/// A todo found by a full-text search
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchHit {
+    #[serde(flatten)]
+    pub todo: TodoItem,
+    /// How well the todo matches the search, higher is better
+    pub rank: f32,
+    /// Title with the matching words wrapped in [`HIGHLIGHT_START`] and [`HIGHLIGHT_END`]
+    pub highlighted_title: String,
+    /// Parts of the notes around the matching words, highlighted like the title,
+    /// `None` when only the title matches
+    pub snippet: Option<String>,
+}
+
+/// Outcome of one operation of a bulk request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BulkResult {
+    /// Id of the todo the operation applied to
+    pub id: uuid::Uuid,
+    pub ok: bool,
+    /// Why the operation failed, it was rolled back
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// The todo after the operation, `None` for failed and delete operations
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub todo: Option<TodoItem>,
+}
+
+/// A todo that was deleted, clients drop their copy of it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tombstone {
+    pub id: uuid::Uuid,
+    pub deleted_at: chrono::NaiveDateTime,
+}
+
+impl From<&Todo> for Tombstone {
+    /// Tombstone of a todo in the trash
+    fn from(todo: &Todo) -> Self {
+        Self {
+            id: todo.id,
+            deleted_at: todo.deleted_at.unwrap_or(todo.updated_at),
+        }
+    }
+}
+
+/// What happened to the todos of a user since a sync token
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TodoDelta {
+    /// Todos created or updated since the token, in full
+    pub changed: Vec<TodoItem>,
+    /// Todos moved to the trash or deleted for good since the token
+    pub deleted: Vec<Tombstone>,
+    /// Token to ask for the changes that come after these
+    pub next_token: String,
+}
+
+/// Columns of a todo that can be changed after it is created,
+/// `None` leaves the column untouched
+#[derive(Debug, AsChangeset)]
+#[table_name = "todos"]
+pub struct TodoChanges {
+    pub title: Option<String>,
+    pub completed: Option<bool>,
+    pub due_at: Option<Option<chrono::NaiveDateTime>>,
+    pub priority: Option<Priority>,
+    pub project_id: Option<Option<uuid::Uuid>>,
+    pub recurrence: Option<Option<Recurrence>>,
+    pub notes: Option<Option<String>>,
+    pub archived_at: Option<Option<chrono::NaiveDateTime>>,
+    pub updated_at: chrono::NaiveDateTime,
+}
+
+impl TodoChanges {
+    /// Set the fields of `todo` that the changes have a value for
+    pub fn apply_to(self, todo: &mut Todo) {
+        if let Some(title) = self.title {
+            todo.title = title;
+        }
+
+        if let Some(completed) = self.completed {
+            todo.set_completed(completed, self.updated_at);
+        }
+
+        if let Some(due_at) = self.due_at {
+            todo.due_at = due_at;
+        }
+
+        if let Some(priority) = self.priority {
+            todo.priority = priority;
+        }
+
+        if let Some(project_id) = self.project_id {
+            todo.project_id = project_id;
+        }
+
+        if let Some(recurrence) = self.recurrence {
+            todo.recurrence = recurrence;
+        }
+
+        if let Some(notes) = self.notes {
+            todo.notes = notes;
+        }
+
+        if let Some(archived_at) = self.archived_at {
+            todo.archived_at = archived_at;
+        }
+
+        todo.updated_at = self.updated_at;
+    }
 }
 
 impl Todo {
@@ -22,6 +446,144 @@ impl Todo {
             user_id,
             created_at: chrono::Local::now().naive_local(),
             updated_at: chrono::Local::now().naive_local(),
+            due_at: None,
+            priority: Priority::None,
+            project_id: None,
+            recurrence: None,
+            series_id: None,
+            notes: None,
+            position: 0,
+            deleted_at: None,
+            archived_at: None,
+            completed_at: None,
+        }
+    }
+
+    /// Complete or reopen the todo at `now`
+    pub fn set_completed(&mut self, completed: bool, now: chrono::NaiveDateTime) {
+        self.completed_at = self.completed_at_after(completed, now);
+        self.completed = completed;
+    }
+
+    /// When the todo counts as completed once it is set to `completed` at `now`,
+    /// completing it again keeps when it was first completed
+    pub fn completed_at_after(
+        &self,
+        completed: bool,
+        now: chrono::NaiveDateTime,
+    ) -> Option<chrono::NaiveDateTime> {
+        match (completed, self.completed) {
+            // Todos saved before `completed_at` was kept were last changed when completed
+            (true, true) => self.completed_at.or(Some(self.updated_at)),
+            (true, false) => Some(now),
+            (false, _) => None,
+        }
+    }
+
+    /// A todo is overdue when it is still open and its due date has passed
+    pub fn is_overdue(&self) -> bool {
+        match self.due_at {
+            Some(due_at) => !self.completed && due_at < chrono::Local::now().naive_local(),
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod todo_model_test {
+    use super::{Recurrence, Todo, TodoChanges};
+    use chrono::{NaiveDateTime, Weekday};
+
+    fn at(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn test_parse_recurrence() {
+        assert_eq!("daily".parse(), Ok(Recurrence::Daily));
+        assert_eq!(
+            "weekly: fri,Mon".parse(),
+            Ok(Recurrence::Weekly(vec![Weekday::Mon, Weekday::Fri]))
+        );
+        assert_eq!("monthly:31".parse(), Ok(Recurrence::Monthly(31)));
+        assert_eq!("every:3".parse(), Ok(Recurrence::EveryDays(3)));
+
+        assert_eq!(
+            Recurrence::Weekly(vec![Weekday::Mon, Weekday::Fri]).to_string(),
+            "weekly:mon,fri"
+        );
+
+        for invalid in ["weekly", "weekly:someday", "monthly:0", "every:0", "hourly"] {
+            assert_eq!(invalid.parse::<Recurrence>().is_err(), true);
+        }
+    }
+
+    #[test]
+    fn test_next_due() {
+        // Completed early, the next standup is the day after the due date
+        assert_eq!(
+            Recurrence::Daily.next_due(Some(at("2022-10-03 09:00")), at("2022-10-03 08:00")),
+            at("2022-10-04 09:00")
+        );
+
+        // Missed occurrences are skipped
+        assert_eq!(
+            Recurrence::Daily.next_due(Some(at("2022-10-03 09:00")), at("2022-10-06 10:00")),
+            at("2022-10-07 09:00")
+        );
+
+        // Wednesday the 5th, next is Friday
+        let weekly = Recurrence::Weekly(vec![Weekday::Mon, Weekday::Fri]);
+
+        assert_eq!(
+            weekly.next_due(Some(at("2022-10-03 17:00")), at("2022-10-05 12:00")),
+            at("2022-10-07 17:00")
+        );
+
+        // February has no 31st
+        assert_eq!(
+            Recurrence::Monthly(31).next_due(Some(at("2023-01-31 12:00")), at("2023-01-31 11:00")),
+            at("2023-02-28 12:00")
+        );
+
+        assert_eq!(
+            Recurrence::EveryDays(3).next_due(Some(at("2022-10-01 09:00")), at("2022-10-05 20:00")),
+            at("2022-10-08 09:00")
+        );
+
+        // Without a due date occurrences are due at the end of the day
+        assert_eq!(
+            Recurrence::Daily
+                .next_due(None, at("2022-10-05 20:00"))
+                .to_string(),
+            "2022-10-06 23:59:59"
+        );
+    }
+
+    #[test]
+    fn test_completed_at() {
+        let mut todo = Todo::from("Water the plants".into(), uuid::Uuid::nil());
+
+        todo.set_completed(true, at("2022-10-03 09:00"));
+        assert_eq!(todo.completed_at, Some(at("2022-10-03 09:00")));
+
+        // Editing a completed todo keeps when it was completed
+        TodoChanges {
+            title: Some("Water the cactus".into()),
+            completed: Some(true),
+            due_at: None,
+            priority: None,
+            project_id: None,
+            recurrence: None,
+            notes: None,
+            archived_at: None,
+            updated_at: at("2022-10-20 18:00"),
         }
+        .apply_to(&mut todo);
+
+        assert_eq!(todo.completed_at, Some(at("2022-10-03 09:00")));
+
+        todo.set_completed(false, at("2022-10-21 08:00"));
+        assert_eq!(todo.completed_at, None);
     }
 }
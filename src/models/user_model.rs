@@ -6,7 +6,7 @@ use diesel::{Insertable, Queryable};
 use serde::{Deserialize, Serialize};
 use uuid;
 
-#[derive(Debug, Deserialize, Serialize, Insertable, Queryable)]
+#[derive(Debug, Clone, Deserialize, Serialize, Insertable, Queryable)]
 #[table_name = "users"]
 pub struct User {
     pub id: uuid::Uuid,
@@ -15,6 +15,8 @@ pub struct User {
     pub updated_at: chrono::NaiveDateTime,
     pub password: String,
     pub name: String,
+    /// When the user followed the link of the verification mail
+    pub email_verified_at: Option<chrono::NaiveDateTime>,
 }
 
 impl User {
@@ -26,6 +28,7 @@ impl User {
             name: name.into(),
             created_at: chrono::Local::now().naive_local(),
             updated_at: chrono::Local::now().naive_local(),
+            email_verified_at: None,
         }
     }
 }
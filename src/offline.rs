@@ -0,0 +1,487 @@
+use std::{collections::HashSet, path::PathBuf};
+
+use chrono::NaiveDateTime;
+use reqwest::{
+    blocking::Client,
+    header::{AUTHORIZATION, CONTENT_TYPE},
+    StatusCode,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    backend::filter_todos,
+    errors::BaseError,
+    models::{
+        project_model::Project,
+        todo_model::{Todo, TodoDelta, TodoItem, POSITION_GAP},
+    },
+    utils::make_api_url,
+};
+
+/// A change made while the server was unreachable, sent once it is back
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum QueuedChange {
+    /// The todo is created with the id it has locally, so that sending
+    /// it again after a timeout doesn't create it twice
+    Create { todo: Box<TodoItem> },
+    /// `updated_at` is the version of the todo that was completed,
+    /// the change is dropped when the server has a different one
+    Complete {
+        id: uuid::Uuid,
+        title: String,
+        updated_at: NaiveDateTime,
+    },
+    /// Same as [`QueuedChange::Complete`], for moving the todo to the trash
+    Delete {
+        id: uuid::Uuid,
+        title: String,
+        updated_at: NaiveDateTime,
+    },
+}
+
+/// What came of sending the queued changes
+#[derive(Debug, Default)]
+pub struct Replay {
+    /// Changes the server took
+    pub applied: usize,
+    /// Why each of the other changes was dropped
+    pub rejected: Vec<String>,
+}
+
+/// Copy of the todos kept at `~/todo/store.json`, next to the credentials,
+/// to keep the cli working while the server is unreachable
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct LocalStore {
+    pub todos: Vec<TodoItem>,
+    pub projects: Vec<Project>,
+    /// When the todos were last brought up to date with the server
+    pub synced_at: Option<NaiveDateTime>,
+    /// Token of `GET /api/todo/changes` the todos are up to date with
+    sync_token: Option<String>,
+    /// Changes made while offline, oldest first
+    queue: Vec<QueuedChange>,
+}
+
+impl LocalStore {
+    fn path() -> PathBuf {
+        let mut path = dirs::home_dir().unwrap();
+        path.push("todo/store.json");
+        path
+    }
+
+    /// Load the store, empty when nothing was saved yet
+    pub fn load() -> Result<Self, BaseError> {
+        let path = Self::path();
+
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let contents = std::fs::read_to_string(path)?;
+
+        Ok(serde_json::from_str(contents.as_str())?)
+    }
+
+    pub fn save(&self) -> Result<(), BaseError> {
+        let path = Self::path();
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        std::fs::write(path, serde_json::to_string(self)?)?;
+
+        Ok(())
+    }
+
+    pub fn has_queued_changes(&self) -> bool {
+        !self.queue.is_empty()
+    }
+
+    /// Ids of the todos that only exist locally so far
+    fn local_ids(&self) -> HashSet<uuid::Uuid> {
+        self.queue
+            .iter()
+            .filter_map(|change| match change {
+                QueuedChange::Create { todo } => Some(todo.id),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Keep a new todo to be created later, it goes to the top of the list
+    pub fn queue_create(&mut self, mut todo: TodoItem) {
+        todo.position = self
+            .todos
+            .iter()
+            .map(|todo| todo.position)
+            .min()
+            .map_or(0, |top| top - POSITION_GAP);
+
+        self.todos.push(todo.clone());
+        self.queue.push(QueuedChange::Create {
+            todo: Box::new(todo),
+        });
+    }
+
+    /// Complete a todo locally and keep the change to be sent later
+    pub fn queue_complete(&mut self, todo: &Todo) {
+        if let Some(cached) = self.todos.iter_mut().find(|cached| cached.id == todo.id) {
+            cached.set_completed(true, chrono::Local::now().naive_local());
+        }
+
+        self.queue.push(QueuedChange::Complete {
+            id: todo.id,
+            title: todo.title.clone(),
+            updated_at: todo.updated_at,
+        });
+    }
+
+    /// Drop a todo locally and keep the change to be sent later,
+    /// a todo created offline is just never sent
+    pub fn queue_delete(&mut self, todo: &Todo) {
+        self.todos.retain(|cached| cached.id != todo.id);
+
+        if self.local_ids().contains(&todo.id) {
+            self.queue.retain(|change| match change {
+                QueuedChange::Create { todo: created } => created.id != todo.id,
+                QueuedChange::Complete { id, .. } | QueuedChange::Delete { id, .. } => {
+                    *id != todo.id
+                }
+            });
+
+            return;
+        }
+
+        self.queue.push(QueuedChange::Delete {
+            id: todo.id,
+            title: todo.title.clone(),
+            updated_at: todo.updated_at,
+        });
+    }
+
+    /// The saved todos a list query would return, in the order it asks for
+    pub fn cached_todos(&self, query: &[(String, String)]) -> Result<Vec<TodoItem>, BaseError> {
+        filter_todos(&self.todos, &self.projects, query)
+    }
+
+    /// Bring the saved todos up to date with the server,
+    /// only fetching what changed since the last time
+    pub fn refresh(&mut self, client: &Client, token: &str) -> Result<(), BaseError> {
+        let mut request = client
+            .get(make_api_url("todo/changes"))
+            .header(AUTHORIZATION, format!("Bearer {}", token))
+            .header(CONTENT_TYPE, "application/json");
+
+        if let Some(since) = &self.sync_token {
+            request = request.query(&[("since", since)]);
+        }
+
+        let response = request.send()?;
+
+        // The server doesn't know the token anymore, start over
+        if response.status() == StatusCode::BAD_REQUEST && self.sync_token.is_some() {
+            self.sync_token = None;
+
+            return self.refresh(client, token);
+        }
+
+        let delta: TodoDelta = response.error_for_status()?.json()?;
+
+        // Without a token every todo is sent, the todos not sent are gone
+        if self.sync_token.is_none() {
+            let local_ids = self.local_ids();
+
+            self.todos.retain(|todo| local_ids.contains(&todo.id));
+        }
+
+        for tombstone in delta.deleted {
+            self.todos.retain(|todo| todo.id != tombstone.id);
+        }
+
+        for todo in delta.changed {
+            match self.todos.iter_mut().find(|cached| cached.id == todo.id) {
+                Some(cached) => *cached = todo,
+                None => self.todos.push(todo),
+            }
+        }
+
+        self.sync_token = Some(delta.next_token);
+        self.synced_at = Some(chrono::Local::now().naive_local());
+
+        Ok(())
+    }
+
+    /// Send the queued changes in the order they were made
+    ///
+    /// Changes to todos that changed on the server in the meantime are
+    /// dropped, the server version wins. Sending stops at the first change
+    /// that can't get through, that one and the ones after it stay queued
+    pub fn replay(&mut self, client: &Client, token: &str) -> Result<Replay, BaseError> {
+        let mut replay = Replay::default();
+
+        while let Some(change) = self.queue.first().cloned() {
+            match self.replay_change(client, token, &change) {
+                Ok(None) => replay.applied += 1,
+                Ok(Some(conflict)) => replay.rejected.push(conflict),
+                Err(e) => match e.downcast_ref::<reqwest::Error>().and_then(|e| e.status()) {
+                    // The server won't ever take this one
+                    Some(status)
+                        if status.is_client_error()
+                            && status != StatusCode::UNAUTHORIZED
+                            && status != StatusCode::FORBIDDEN =>
+                    {
+                        replay
+                            .rejected
+                            .push(format!("{} was dropped: {}", describe(&change), e));
+                    }
+                    _ => {
+                        self.save()?;
+                        return Err(e);
+                    }
+                },
+            }
+
+            // Saved after each change so that none is sent twice
+            self.queue.remove(0);
+            self.save()?;
+        }
+
+        Ok(replay)
+    }
+
+    /// Send one queued change, returns the conflict when there is one
+    fn replay_change(
+        &mut self,
+        client: &Client,
+        token: &str,
+        change: &QueuedChange,
+    ) -> Result<Option<String>, BaseError> {
+        let (id, updated_at) = match change {
+            QueuedChange::Create { todo } => {
+                let response = client
+                    .post(make_api_url("todo"))
+                    .header(AUTHORIZATION, format!("Bearer {}", token))
+                    .header(CONTENT_TYPE, "application/json")
+                    .json(&new_todo_body(todo))
+                    .send()?;
+
+                let created: TodoItem = response.error_for_status()?.json()?;
+
+                self.replace_local_id(todo.id, &created);
+
+                return Ok(None);
+            }
+            QueuedChange::Complete { id, updated_at, .. }
+            | QueuedChange::Delete { id, updated_at, .. } => (id, updated_at),
+        };
+
+        let url = make_api_url(format!("todo/{}", id).as_str());
+
+        let response = client
+            .get(url.as_str())
+            .header(AUTHORIZATION, format!("Bearer {}", token))
+            .header(CONTENT_TYPE, "application/json")
+            .send()?;
+
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(match change {
+                QueuedChange::Delete { .. } => None,
+                _ => Some(format!(
+                    "{} was dropped, the todo was deleted on the server",
+                    describe(change)
+                )),
+            });
+        }
+
+        let current: TodoItem = response.error_for_status()?.json()?;
+
+        if current.updated_at != *updated_at {
+            return Ok(Some(format!(
+                "{} was dropped, the todo changed on the server in the meantime",
+                describe(change)
+            )));
+        }
+
+        let request = match change {
+            QueuedChange::Complete { .. } => client.put(format!("{}/complete", url)),
+            _ => client.delete(url.as_str()),
+        };
+
+        request
+            .header(AUTHORIZATION, format!("Bearer {}", token))
+            .header(CONTENT_TYPE, "application/json")
+            .send()?
+            .error_for_status()?;
+
+        // Completing changed the version the later changes of the todo expect
+        if let QueuedChange::Complete { .. } = change {
+            let completed: TodoItem = client
+                .get(url)
+                .header(AUTHORIZATION, format!("Bearer {}", token))
+                .header(CONTENT_TYPE, "application/json")
+                .send()?
+                .error_for_status()?
+                .json()?;
+
+            self.replace_version(&completed);
+        }
+
+        Ok(None)
+    }
+
+    /// Make the queued changes of a todo expect the version the server has now
+    fn replace_version(&mut self, current: &TodoItem) {
+        for todo in self.todos.iter_mut().filter(|todo| todo.id == current.id) {
+            *todo = current.clone();
+        }
+
+        for change in self.queue.iter_mut() {
+            match change {
+                QueuedChange::Complete { id, updated_at, .. }
+                | QueuedChange::Delete { id, updated_at, .. }
+                    if *id == current.id =>
+                {
+                    *updated_at = current.updated_at;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Point everything that used the local id of a todo to the created todo
+    fn replace_local_id(&mut self, local_id: uuid::Uuid, created: &TodoItem) {
+        for todo in self.todos.iter_mut().filter(|todo| todo.id == local_id) {
+            *todo = created.clone();
+        }
+
+        for change in self.queue.iter_mut() {
+            match change {
+                QueuedChange::Complete { id, updated_at, .. }
+                | QueuedChange::Delete { id, updated_at, .. }
+                    if *id == local_id =>
+                {
+                    *id = created.id;
+                    *updated_at = created.updated_at;
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// How a queued change is referred to in messages
+fn describe(change: &QueuedChange) -> String {
+    match change {
+        QueuedChange::Create { todo } => format!("Creating \"{}\"", todo.title),
+        QueuedChange::Complete { title, .. } => format!("Completing \"{}\"", title),
+        QueuedChange::Delete { title, .. } => format!("Deleting \"{}\"", title),
+    }
+}
+
+/// Body of `POST /api/todo` for a new todo
+pub fn new_todo_body(todo: &TodoItem) -> serde_json::Value {
+    serde_json::json!({
+        "id": todo.id,
+        "title": todo.title,
+        "due_at": todo.due_at,
+        "priority": todo.priority,
+        "project_id": todo.project_id,
+        "tags": todo.tags,
+        "recurrence": todo.recurrence,
+        "notes": todo.notes,
+    })
+}
+
+/// Whether a request failed because the server couldn't be reached
+pub fn is_unreachable(error: &(dyn std::error::Error + 'static)) -> bool {
+    match error.downcast_ref::<reqwest::Error>() {
+        Some(e) => e.is_connect() || e.is_timeout(),
+        None => false,
+    }
+}
+
+/// Keep a change in the local store when `error` is because the server
+/// couldn't be reached, any other error is returned as it is
+pub fn queue_when_unreachable(
+    error: BaseError,
+    queue: impl FnOnce(&mut LocalStore),
+) -> Result<(), BaseError> {
+    if !is_unreachable(error.as_ref()) {
+        return Err(error);
+    }
+
+    let mut store = LocalStore::load()?;
+
+    queue(&mut store);
+
+    store.save()
+}
+
+#[cfg(test)]
+mod offline_test {
+    use super::{LocalStore, QueuedChange};
+    use crate::models::todo_model::{Todo, TodoItem};
+
+    fn item(title: &str, tags: &[&str]) -> TodoItem {
+        TodoItem {
+            todo: Todo::from(title.to_string(), uuid::Uuid::nil()),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            progress: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_queue_offline_changes() {
+        let mut store = LocalStore::default();
+
+        let saved = item("saved", &["home"]);
+        store.todos.push(saved.clone());
+
+        store.queue_create(item("new", &["home", "work"]));
+        store.queue_complete(&saved);
+
+        let query = vec![("tag".to_string(), "home".to_string())];
+        let titles: Vec<String> = store
+            .cached_todos(&query)
+            .unwrap()
+            .into_iter()
+            .map(|todo| format!("{} {}", todo.title, todo.completed))
+            .collect();
+
+        assert_eq!(titles, vec!["new false", "saved true"]);
+        assert_eq!(store.queue.len(), 2);
+
+        // A todo created offline and deleted again is never sent
+        let new = store.todos[1].clone();
+        store.queue_delete(&new);
+
+        assert_eq!(store.queue.len(), 1);
+        assert_eq!(store.todos.len(), 1);
+    }
+
+    #[test]
+    fn test_changes_after_a_complete_expect_the_completed_version() {
+        let mut store = LocalStore::default();
+
+        let saved = item("saved", &[]);
+        store.todos.push(saved.clone());
+
+        store.queue_complete(&saved);
+        store.queue_delete(&saved);
+
+        let mut completed = saved.clone();
+        completed.completed = true;
+        completed.updated_at += chrono::Duration::seconds(1);
+
+        store.replace_version(&completed);
+
+        match &store.queue[1] {
+            QueuedChange::Delete { updated_at, .. } => {
+                assert_eq!(*updated_at, completed.updated_at)
+            }
+            change => panic!("unexpected change {:?}", change),
+        }
+    }
+}
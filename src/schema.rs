@@ -1,5 +1,84 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    email_verifications (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        token_hash -> Varchar,
+        created_at -> Timestamp,
+        expires_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    password_resets (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        token_hash -> Varchar,
+        created_at -> Timestamp,
+        expires_at -> Timestamp,
+        used_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    projects (id) {
+        id -> Uuid,
+        name -> Varchar,
+        user_id -> Uuid,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    sessions (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        refresh_token_hash -> Varchar,
+        created_at -> Timestamp,
+        expires_at -> Timestamp,
+        revoked_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    subtasks (id) {
+        id -> Uuid,
+        todo_id -> Uuid,
+        title -> Varchar,
+        completed -> Bool,
+        position -> Int4,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    tags (id) {
+        id -> Uuid,
+        name -> Varchar,
+        user_id -> Uuid,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    todo_tags (todo_id, tag_id) {
+        todo_id -> Uuid,
+        tag_id -> Uuid,
+    }
+}
+
+diesel::table! {
+    todo_tombstones (todo_id) {
+        todo_id -> Uuid,
+        user_id -> Uuid,
+        deleted_at -> Timestamp,
+        sync_txid -> Int8,
+    }
+}
+
 diesel::table! {
     todos (id) {
         id -> Uuid,
@@ -8,6 +87,16 @@ diesel::table! {
         created_at -> Timestamp,
         updated_at -> Timestamp,
         user_id -> Uuid,
+        due_at -> Nullable<Timestamp>,
+        priority -> Int2,
+        project_id -> Nullable<Uuid>,
+        recurrence -> Nullable<Varchar>,
+        series_id -> Nullable<Uuid>,
+        notes -> Nullable<Text>,
+        position -> Int8,
+        deleted_at -> Nullable<Timestamp>,
+        archived_at -> Nullable<Timestamp>,
+        completed_at -> Nullable<Timestamp>,
     }
 }
 
@@ -19,9 +108,30 @@ diesel::table! {
         updated_at -> Timestamp,
         password -> Varchar,
         name -> Varchar,
+        email_verified_at -> Nullable<Timestamp>,
     }
 }
 
+diesel::joinable!(email_verifications -> users (user_id));
+diesel::joinable!(password_resets -> users (user_id));
+diesel::joinable!(projects -> users (user_id));
+diesel::joinable!(sessions -> users (user_id));
+diesel::joinable!(subtasks -> todos (todo_id));
+diesel::joinable!(tags -> users (user_id));
+diesel::joinable!(todo_tags -> tags (tag_id));
+diesel::joinable!(todo_tags -> todos (todo_id));
+diesel::joinable!(todos -> projects (project_id));
 diesel::joinable!(todos -> users (user_id));
 
-diesel::allow_tables_to_appear_in_same_query!(todos, users,);
+diesel::allow_tables_to_appear_in_same_query!(
+    email_verifications,
+    password_resets,
+    projects,
+    sessions,
+    subtasks,
+    tags,
+    todo_tags,
+    todo_tombstones,
+    todos,
+    users,
+);
@@ -1,68 +1,380 @@
-use std::str::FromStr;
-
-use inquire::Text;
-use reqwest::header::{AUTHORIZATION, CONTENT_TYPE};
+use crossterm::style::Stylize;
+use inquire::{Confirm, Editor, MultiSelect, Select, Text};
 
 use crate::{
-    models::todo_model::Todo,
+    backend::TodoBackend,
+    errors::BaseError,
+    models::{
+        project_model::Project,
+        subtask_model::Progress,
+        todo_model::{Priority, Recurrence, Todo, TodoItem},
+    },
+    offline::{is_unreachable, LocalStore},
     ui::todo_list_renderer::render_todo_list,
-    utils::{get_saved_token, make_api_url},
+    utils::{get_saved_token, parse_due_date, split_highlights, split_tags},
 };
 
+/// Find the project named `name`,
+/// offering to create it when it doesn't exist yet
+fn find_or_create_project(
+    backend: &dyn TodoBackend,
+    name: &str,
+) -> Result<Project, Box<dyn std::error::Error>> {
+    let projects = backend.projects()?;
+
+    if let Some(project) = projects.into_iter().find(|project| project.name == name) {
+        return Ok(project);
+    }
+
+    if backend.is_offline() {
+        return Err("Projects can't be created while the server is unreachable".into());
+    }
+
+    let create = Confirm::new(format!("Project {} doesn't exist, create it?", name).as_str())
+        .with_default(true)
+        .prompt()?;
+
+    if !create {
+        return Err(format!("Project {} not found", name).into());
+    }
+
+    backend.create_project(name)
+}
+
+/// Prompt for how a todo repeats, empty input means it doesn't
+fn prompt_recurrence(
+    current: Option<&Recurrence>,
+) -> Result<Option<Recurrence>, Box<dyn std::error::Error>> {
+    let current = current
+        .map(|recurrence| recurrence.to_string())
+        .unwrap_or_default();
+
+    let repeat = Text::new("Repeat")
+        .with_default(current.as_str())
+        .with_help_message(
+            "daily, weekly:mon,fri, monthly:15 or every:3 (days after completion), leave empty to not repeat",
+        )
+        .prompt()?;
+
+    if repeat.trim().is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(repeat.parse::<Recurrence>()?))
+}
+
+/// Prompt for the Markdown notes of a todo, opened in the user's editor
+fn prompt_notes(current: Option<&str>) -> Result<String, Box<dyn std::error::Error>> {
+    let notes = Editor::new("Notes")
+        .with_predefined_text(current.unwrap_or_default())
+        .with_file_extension(".md")
+        .with_help_message("Markdown, leave empty for no notes")
+        .prompt()?;
+
+    Ok(notes)
+}
+
 /// Prompt user to create new todo
-pub fn create_new_todo() -> Result<(), Box<dyn std::error::Error>> {
+///
+/// # Arguments
+/// * `project` optional name of the project to add the todo to
+pub fn create_new_todo(
+    backend: &dyn TodoBackend,
+    project: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
     let title = Text::new("Title")
-        .with_help_message("Title for your new todo")
+        .with_help_message("Title for your new todo, #words in it become tags")
         .prompt()?;
 
-    let token = get_saved_token()?;
+    let (title, tags) = split_tags(title.as_str());
 
-    let client = reqwest::blocking::Client::new();
-    let resp = client
-        .post(make_api_url("todo"))
-        .header(AUTHORIZATION, format!("Bearer {}", token))
-        .header(CONTENT_TYPE, "application/json")
-        .json::<serde_json::Value>(&serde_json::json!({ "title": title }))
-        .send()?;
+    let due = Text::new("Due")
+        .with_help_message("YYYY-MM-DD or YYYY-MM-DD HH:MM, leave empty for no due date")
+        .prompt()?;
+
+    let due_at = if due.trim().is_empty() {
+        None
+    } else {
+        Some(parse_due_date(due.as_str())?)
+    };
 
-    let _: serde_json::Value = resp.json()?;
+    let priority = Select::new("Priority", Priority::ALL.to_vec()).prompt()?;
 
-    println!("Todo created");
+    let recurrence = prompt_recurrence(None)?;
+
+    let notes = prompt_notes(None)?;
+
+    let project_id = match project {
+        Some(name) => Some(find_or_create_project(backend, name)?.id),
+        None => None,
+    };
+
+    // The user id is only known to the server, it fills it in
+    let mut todo = Todo::from(title, uuid::Uuid::nil());
+
+    todo.due_at = due_at;
+    todo.priority = priority;
+    todo.project_id = project_id;
+    todo.recurrence = recurrence;
+    todo.notes = Some(notes).filter(|notes| !notes.trim().is_empty());
+
+    let todo = TodoItem {
+        todo,
+        tags,
+        progress: Progress::default(),
+    };
+
+    backend.create_todo(&todo)?;
+
+    match backend.is_offline() {
+        true => println!("Server unreachable, the todo will be created once it is back"),
+        false => println!("Todo created"),
+    }
 
     Ok(())
 }
 
-/// List all the todos for user
-pub fn list_todos() -> Result<(), Box<dyn std::error::Error>> {
-    let client = reqwest::blocking::Client::new();
+/// Prompt user to edit an existing todo,
+/// current values are used as defaults
+pub fn edit_todo(
+    backend: &dyn TodoBackend,
+    todo_id: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let todo = backend.get_todo(todo_id)?;
 
-    let token = get_saved_token()?;
+    // Tags are edited along with the title as #words
+    let current_title = std::iter::once(todo.title.clone())
+        .chain(todo.tags.iter().map(|tag| format!("#{}", tag)))
+        .collect::<Vec<String>>()
+        .join(" ");
 
-    let response = client
-        .get(make_api_url("todo"))
-        .header(AUTHORIZATION, format!("Bearer {}", token))
-        .header(CONTENT_TYPE, "application/json")
-        .send()?;
+    let title = Text::new("Title")
+        .with_default(current_title.as_str())
+        .with_help_message("#words in the title become tags")
+        .prompt()?;
 
-    match response.status() {
-        reqwest::StatusCode::OK => {
-            let list = response.text()?;
+    let (title, tags) = split_tags(title.as_str());
 
-            let json = serde_json::Value::from_str(list.as_str())?;
+    let current_due = todo
+        .due_at
+        .map(|due_at| due_at.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_default();
 
-            let todos = json.get("todos").unwrap().to_owned();
+    let due = Text::new("Due")
+        .with_default(current_due.as_str())
+        .with_help_message("YYYY-MM-DD or YYYY-MM-DD HH:MM, leave empty for no due date")
+        .prompt()?;
 
-            let list: Vec<Todo> = serde_json::from_value(todos)?;
+    let due_at = if due.trim().is_empty() {
+        None
+    } else {
+        Some(parse_due_date(due.as_str())?)
+    };
 
-            render_todo_list(list)?;
-        }
-        reqwest::StatusCode::UNAUTHORIZED | reqwest::StatusCode::FORBIDDEN => {
-            eprintln!("Login First");
-        }
-        _ => {
-            eprintln!("Main line error");
+    let priority = Select::new("Priority", Priority::ALL.to_vec())
+        .with_starting_cursor(todo.priority as usize)
+        .prompt()?;
+
+    let recurrence = prompt_recurrence(todo.recurrence.as_ref())?;
+
+    let notes = prompt_notes(todo.notes.as_deref())?;
+
+    backend.update_todo(
+        todo_id,
+        serde_json::json!({
+            "title": title,
+            "due_at": due_at,
+            "priority": priority,
+            "tags": tags,
+            "recurrence": recurrence,
+            "notes": notes,
+        }),
+    )?;
+
+    println!("Todo updated");
+
+    Ok(())
+}
+
+/// List the todos in the trash and restore the ones the user picks
+///
+/// # Arguments
+/// * `restore` id of a todo to restore without prompting
+pub fn show_trash(
+    backend: &dyn TodoBackend,
+    restore: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(todo_id) = restore {
+        let todo = backend.restore_todo(todo_id)?;
+
+        println!("Restored {}", todo.title);
+
+        return Ok(());
+    }
+
+    let (trash, retention_days) = backend.trash()?;
+
+    if trash.is_empty() {
+        println!("Trash is empty");
+        return Ok(());
+    }
+
+    let options: Vec<String> = trash
+        .iter()
+        .map(|todo| {
+            let deleted_at = todo.deleted_at.unwrap_or(todo.updated_at);
+
+            format!(
+                "{}  (deleted {}, purged after {})",
+                todo.title,
+                deleted_at.format("%Y-%m-%d %H:%M"),
+                (deleted_at + chrono::Duration::days(retention_days)).format("%Y-%m-%d")
+            )
+        })
+        .collect();
+
+    let picked = MultiSelect::new("Trash", options)
+        .with_help_message(
+            "Space to pick the todos to restore, Enter to restore them, Esc to leave",
+        )
+        .raw_prompt_skippable()?
+        .unwrap_or_default();
+
+    for option in picked {
+        let todo = backend.restore_todo(trash[option.index].id.to_string().as_str())?;
+
+        println!("Restored {}", todo.title);
+    }
+
+    Ok(())
+}
+
+/// Search the titles and notes of the todos and print the matches, best first
+pub fn search_todos(
+    backend: &dyn TodoBackend,
+    query: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let hits = backend.search(query)?;
+
+    if hits.is_empty() {
+        println!("No todos match {}", query);
+        return Ok(());
+    }
+
+    for hit in hits {
+        let status = if hit.todo.archived_at.is_some() {
+            "[archived] "
+        } else if hit.todo.completed {
+            "[x] "
+        } else {
+            "[ ] "
+        };
+
+        println!(
+            "{}{}  {}",
+            status,
+            bold_highlights(&hit.highlighted_title),
+            hit.todo.id.to_string().dark_grey()
+        );
+
+        if let Some(snippet) = &hit.snippet {
+            println!("    {}", bold_highlights(snippet).replace('\n', " "));
         }
     }
 
     Ok(())
 }
+
+/// Turns the highlighted words of a search result bold for the terminal
+fn bold_highlights(text: &str) -> String {
+    split_highlights(text)
+        .into_iter()
+        .map(|(part, matched)| match matched {
+            true => part.bold().to_string(),
+            false => part.to_string(),
+        })
+        .collect()
+}
+
+/// List all the todos for user
+///
+/// # Arguments
+/// * `due` optional due date filter, one of `overdue`, `today` or `week`
+/// * `project` optional name of the project to list todos of
+/// * `tags` only list todos with these tags
+/// * `all_tags` todos need all of `tags` instead of any of them
+/// * `archived` also list the archived todos
+pub fn list_todos(
+    backend: &dyn TodoBackend,
+    due: Option<&str>,
+    project: Option<&str>,
+    tags: &[String],
+    all_tags: bool,
+    archived: bool,
+    sort: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // Filters are kept by the todo list, to be reused when switching projects
+    let mut filters: Vec<(String, String)> = vec![];
+
+    if let Some(due) = due {
+        filters.push(("due".into(), due.into()));
+    }
+
+    for tag in tags {
+        filters.push(("tag".into(), tag.trim_start_matches('#').into()));
+    }
+
+    if all_tags {
+        filters.push(("tag_match".into(), "all".into()));
+    }
+
+    if archived {
+        filters.push(("include".into(), "archived".into()));
+    }
+
+    if let Some(sort) = sort {
+        filters.push(("sort".into(), sort.into()));
+    }
+
+    let mut query = filters.clone();
+
+    if let Some(project) = project {
+        query.push(("project".into(), project.into()));
+    }
+
+    let list = backend.list_todos(&query)?;
+
+    let projects = backend.projects()?;
+
+    render_todo_list(backend, list, projects, project, filters)
+}
+
+/// Send the changes made while the server was unreachable,
+/// they are kept for later when it still is
+pub fn replay_offline_changes() -> Result<(), BaseError> {
+    let mut store = LocalStore::load()?;
+
+    if !store.has_queued_changes() {
+        return Ok(());
+    }
+
+    let token = get_saved_token()?;
+
+    let client = reqwest::blocking::Client::new();
+
+    match store.replay(&client, &token) {
+        Ok(replay) => {
+            if replay.applied > 0 {
+                println!("Sent {} changes made offline", replay.applied);
+            }
+
+            for rejected in replay.rejected {
+                eprintln!("{}", rejected);
+            }
+
+            Ok(())
+        }
+        Err(e) if is_unreachable(e.as_ref()) => Ok(()),
+        Err(e) => Err(e),
+    }
+}
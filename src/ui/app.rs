@@ -1,6 +1,15 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::Instant,
+};
+
 use tui::widgets::ListState;
 
-use crate::models::todo_model::Todo;
+use crate::models::{
+    project_model::Project,
+    subtask_model::Subtask,
+    todo_model::{SearchHit, TodoItem},
+};
 
 pub struct StatefulList<T> {
     pub state: ListState,
@@ -66,12 +75,32 @@ pub enum InputMode {
 /// Check the event handling at the bottom to see how to change the state on incoming events.
 /// Check the drawing logic for items on how to specify the highlighting style for selected items.
 pub struct App {
-    pub undone: StatefulList<Todo>,
-    pub done: StatefulList<Todo>,
+    pub undone: StatefulList<TodoItem>,
+    pub done: StatefulList<TodoItem>,
     pub error_message: String,
     pub input_text: String,
     pub message: String,
     pub input_mode: InputMode,
+    /// Projects shown as tabs, after the `All` tab
+    pub projects: Vec<Project>,
+    /// Index of the selected tab, `0` is the `All` tab
+    pub selected_tab: usize,
+    /// Query filters the todos were listed with
+    pub filters: Vec<(String, String)>,
+    /// Subtasks of the todos that are expanded in the list
+    pub expanded: HashMap<uuid::Uuid, Vec<Subtask>>,
+    /// Last todo moved to the trash and where it was in the list
+    pub last_deleted: Option<(usize, TodoItem)>,
+    /// Results of the search being typed
+    pub search: StatefulList<SearchHit>,
+    /// When to search again with what is being typed, set on each
+    /// keystroke so that a search is only sent once typing pauses
+    pub search_due: Option<Instant>,
+    /// Todos marked with Space, bulk actions apply to them
+    pub marked: HashSet<uuid::Uuid>,
+    /// Set while the server is unreachable and the saved copy of the
+    /// todos is shown, to when that copy was last synced
+    pub stale_since: Option<chrono::NaiveDateTime>,
     navigation_stack: Vec<Route>,
 }
 
@@ -87,6 +116,11 @@ pub enum RouteId {
     Error,
     Message,
     NewTodo,
+    EditTodo,
+    NewSubtask,
+    Search,
+    BulkRetag,
+    BulkMove,
 }
 
 #[derive(Clone, Copy, PartialEq, Debug)]
@@ -95,6 +129,11 @@ pub enum ActiveBlock {
     Error,
     Message,
     NewTodo,
+    EditTodo,
+    NewSubtask,
+    Search,
+    BulkRetag,
+    BulkMove,
 }
 
 pub const DEFAULT_ROUTE: Route = Route {
@@ -111,10 +150,69 @@ impl App {
             input_mode: InputMode::None,
             input_text: String::new(),
             message: String::new(),
+            projects: vec![],
+            selected_tab: 0,
+            filters: vec![],
+            expanded: HashMap::new(),
+            last_deleted: None,
+            search: StatefulList::with_items(vec![]),
+            search_due: None,
+            marked: HashSet::new(),
+            stale_since: None,
             navigation_stack: vec![DEFAULT_ROUTE],
         }
     }
 
+    /// Replace the listed todos, splitting them in undone and done
+    pub fn set_todos(&mut self, todos: Vec<TodoItem>) {
+        let (undone, done): (Vec<TodoItem>, Vec<TodoItem>) =
+            todos.into_iter().partition(|todo| !todo.completed);
+
+        self.undone = StatefulList::with_items(undone);
+        self.done = StatefulList::with_items(done);
+        self.expanded.clear();
+        self.marked.clear();
+    }
+
+    /// Sort the todos are listed in, the manual order without one
+    pub fn sort(&self) -> &str {
+        self.filters
+            .iter()
+            .find(|(key, _)| key == "sort")
+            .map_or("manual", |(_, sort)| sort.as_str())
+    }
+
+    /// List by priority, or back in the manual order from any other sort
+    pub fn toggle_sort(&mut self) {
+        let sort = match self.sort() {
+            "manual" => "priority",
+            _ => "manual",
+        };
+
+        self.filters.retain(|(key, _)| key != "sort");
+        self.filters.push(("sort".into(), sort.into()));
+    }
+
+    /// Project of the selected tab, `None` for the `All` tab
+    pub fn current_project(&self) -> Option<&Project> {
+        match self.selected_tab {
+            0 => None,
+            i => self.projects.get(i - 1),
+        }
+    }
+
+    pub fn next_tab(&mut self) {
+        self.selected_tab = (self.selected_tab + 1) % (self.projects.len() + 1);
+    }
+
+    pub fn previous_tab(&mut self) {
+        if self.selected_tab == 0 {
+            self.selected_tab = self.projects.len();
+        } else {
+            self.selected_tab -= 1;
+        }
+    }
+
     /// Rotate through the event list.
     /// This only exists to simulate some kind of "progress"
     pub fn on_tick(&mut self) {}
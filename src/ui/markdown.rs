@@ -0,0 +1,201 @@
+use tui::{
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+};
+
+/// Turns Markdown into styled lines for a `Paragraph`
+///
+/// Only the basics are supported: headings, lists, quotes,
+/// fenced code blocks, `**bold**`, `*italic*` and `` `code` ``
+pub fn render_markdown(text: &str) -> Vec<Spans<'static>> {
+    let mut lines = vec![];
+    let mut in_code_block = false;
+
+    for line in text.lines() {
+        if line.trim_start().starts_with("```") {
+            in_code_block = !in_code_block;
+            continue;
+        }
+
+        if in_code_block {
+            lines.push(Spans::from(Span::styled(
+                format!("  {}", line),
+                Style::default().fg(Color::Cyan),
+            )));
+            continue;
+        }
+
+        let trimmed = line.trim_start();
+        let indent = " ".repeat(line.len() - trimmed.len());
+
+        let heading_level = trimmed.chars().take_while(|c| *c == '#').count();
+
+        if (1..=6).contains(&heading_level) && trimmed[heading_level..].starts_with(' ') {
+            let mut style = Style::default().add_modifier(Modifier::BOLD);
+
+            if heading_level == 1 {
+                style = style.add_modifier(Modifier::UNDERLINED);
+            }
+
+            lines.push(Spans::from(Span::styled(
+                trimmed[heading_level..].trim().to_string(),
+                style,
+            )));
+            continue;
+        }
+
+        if let Some(quote) = trimmed.strip_prefix('>') {
+            let style = Style::default()
+                .fg(Color::DarkGray)
+                .add_modifier(Modifier::ITALIC);
+
+            let mut spans = vec![Span::styled(format!("{}│ ", indent), style)];
+            spans.extend(render_inline(quote.trim_start(), style));
+
+            lines.push(Spans::from(spans));
+            continue;
+        }
+
+        let list_item = ["- ", "* ", "+ "]
+            .iter()
+            .find_map(|marker| trimmed.strip_prefix(marker));
+
+        if let Some(item) = list_item {
+            let mut spans = vec![Span::raw(format!("{}• ", indent))];
+            spans.extend(render_inline(item, Style::default()));
+
+            lines.push(Spans::from(spans));
+            continue;
+        }
+
+        lines.push(Spans::from(render_inline(line, Style::default())));
+    }
+
+    lines
+}
+
+/// Styles the emphasis and code spans of a single line
+fn render_inline(text: &str, base: Style) -> Vec<Span<'static>> {
+    let mut spans = vec![];
+    let mut current = String::new();
+
+    let mut bold = false;
+    let mut italic = false;
+    let mut code = false;
+
+    let style = |bold: bool, italic: bool, code: bool| {
+        let mut style = base;
+
+        if bold {
+            style = style.add_modifier(Modifier::BOLD);
+        }
+
+        if italic {
+            style = style.add_modifier(Modifier::ITALIC);
+        }
+
+        if code {
+            style = style.fg(Color::Cyan);
+        }
+
+        style
+    };
+
+    let chars: Vec<char> = text.chars().collect();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+
+        let toggles = match c {
+            '`' => true,
+            '*' if !code => true,
+            // Underscores inside words, as in snake_case, are kept
+            '_' if !code => {
+                let before = i.checked_sub(1).map(|j| chars[j]);
+                let after = chars.get(i + 1).copied();
+
+                !before.is_some_and(char::is_alphanumeric)
+                    || !after.is_some_and(char::is_alphanumeric)
+            }
+            _ => false,
+        };
+
+        if !toggles {
+            current.push(c);
+            i += 1;
+            continue;
+        }
+
+        if !current.is_empty() {
+            spans.push(Span::styled(
+                std::mem::take(&mut current),
+                style(bold, italic, code),
+            ));
+        }
+
+        match c {
+            '`' => code = !code,
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                bold = !bold;
+                i += 1;
+            }
+            _ => italic = !italic,
+        }
+
+        i += 1;
+    }
+
+    if !current.is_empty() {
+        spans.push(Span::styled(current, style(bold, italic, code)));
+    }
+
+    spans
+}
+
+#[cfg(test)]
+mod markdown_test {
+    use super::render_markdown;
+    use tui::style::Modifier;
+
+    #[test]
+    fn test_render_markdown() {
+        let lines = render_markdown("# Plan\n- buy **milk**\n> later\n```\nlet x_y = 1;\n```");
+
+        assert_eq!(lines.len(), 4);
+
+        assert_eq!(lines[0].0[0].content, "Plan");
+        assert_eq!(
+            lines[0].0[0].style.add_modifier.contains(Modifier::BOLD),
+            true
+        );
+
+        assert_eq!(lines[1].0[0].content, "• ");
+        assert_eq!(lines[1].0[2].content, "milk");
+        assert_eq!(
+            lines[1].0[2].style.add_modifier.contains(Modifier::BOLD),
+            true
+        );
+
+        assert_eq!(lines[2].0[0].content, "│ ");
+
+        assert_eq!(lines[3].0[0].content, "  let x_y = 1;");
+    }
+
+    #[test]
+    fn test_render_inline_emphasis() {
+        let lines = render_markdown("use snake_case, _not_ `*this*`");
+
+        let contents: Vec<&str> = lines[0]
+            .0
+            .iter()
+            .map(|span| span.content.as_ref())
+            .collect();
+
+        assert_eq!(contents, vec!["use snake_case, ", "not", " ", "*this*"]);
+        assert_eq!(
+            lines[0].0[1].style.add_modifier.contains(Modifier::ITALIC),
+            true
+        );
+    }
+}
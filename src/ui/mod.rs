@@ -1,2 +1,3 @@
 mod app;
+mod markdown;
 pub mod todo_list_renderer;
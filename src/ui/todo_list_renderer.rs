@@ -1,33 +1,50 @@
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyModifiers},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use reqwest::{blocking::Response, header::AUTHORIZATION, Method};
 
-use std::{
-    str::FromStr,
-    time::{Duration, Instant},
-};
+use std::time::{Duration, Instant};
 
 use tui::{
     backend::{Backend, CrosstermBackend},
     layout::{Constraint, Corner, Direction, Layout},
     style::{Color, Modifier, Style},
     text::{Span, Spans, Text},
-    widgets::{Block, Borders, List, ListItem, Paragraph, Wrap},
+    widgets::{Block, Borders, List, ListItem, Paragraph, Tabs, Wrap},
     Frame, Terminal,
 };
 
 use crate::{
-    errors::{BaseError, TodoError},
-    models::todo_model::Todo,
-    ui::app::{ActiveBlock, App, InputMode, RouteId},
-    utils::get_saved_token,
+    backend::TodoBackend,
+    errors::BaseError,
+    models::{
+        project_model::Project,
+        subtask_model::Progress,
+        todo_model::{BulkResult, Priority, Todo, TodoItem},
+    },
+    ui::{
+        app::{ActiveBlock, App, InputMode, RouteId, StatefulList},
+        markdown::render_markdown,
+    },
+    utils::{split_highlights, split_tags},
 };
 
 /// Entry point to rendering the todo list
-pub fn render_todo_list(todos: Vec<Todo>) -> Result<(), BaseError> {
+///
+/// # Arguments
+/// * `todo_backend` where the todos are kept
+/// * `todos` todos to show initially
+/// * `projects` projects shown as tabs
+/// * `project` name or id of the project `todos` were listed for
+/// * `filters` query filters `todos` were listed with
+pub fn render_todo_list(
+    todo_backend: &dyn TodoBackend,
+    todos: Vec<TodoItem>,
+    projects: Vec<Project>,
+    project: Option<&str>,
+    filters: Vec<(String, String)>,
+) -> Result<(), BaseError> {
     // setup terminal
     enable_raw_mode()?;
     let mut stdout = std::io::stdout();
@@ -37,21 +54,31 @@ pub fn render_todo_list(todos: Vec<Todo>) -> Result<(), BaseError> {
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    let (undone, done): (Vec<&Todo>, Vec<&Todo>) = todos.iter().partition(|todo| !todo.completed);
-
     // create app and run it
     let tick_rate = Duration::from_millis(250);
     let mut app = App::new();
 
-    for i in 0..undone.len() {
-        app.undone.items.push(undone[i].to_owned());
+    app.set_todos(todos);
+    app.filters = filters;
+
+    // `todos` is the saved copy when the server was unreachable
+    if todo_backend.is_offline() {
+        app.stale_since = todo_backend.synced_at();
     }
 
-    for j in 0..done.len() {
-        app.done.items.push(done[j].to_owned());
+    // Open the tab of the project the todos were listed for
+    if let Some(project) = project {
+        if let Some(index) = projects
+            .iter()
+            .position(|p| p.name == project || p.id.to_string() == project)
+        {
+            app.selected_tab = index + 1;
+        }
     }
 
-    let res = run_app(&mut terminal, app, tick_rate);
+    app.projects = projects;
+
+    let res = run_app(todo_backend, &mut terminal, app, tick_rate);
 
     // restore terminal
     disable_raw_mode()?;
@@ -71,107 +98,229 @@ pub fn render_todo_list(todos: Vec<Todo>) -> Result<(), BaseError> {
     Ok(())
 }
 
-/// Handle http request response
-fn handle_response(response: Response) -> Result<String, BaseError> {
-    match response.status() {
-        reqwest::StatusCode::OK => {
-            let text = response.text()?;
-            return Ok(text);
-        }
-        reqwest::StatusCode::UNAUTHORIZED | reqwest::StatusCode::FORBIDDEN => {
-            return Err(Box::new(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                "Invalid Response for Signup, UNAUTHORIZED or FORBIDDEN",
-            )));
+/// Move the selected todo one place up or down the list
+fn move_selected_todo(backend: &dyn TodoBackend, app: &mut App, up: bool) {
+    // The neighbours in another sort are not the ones of the manual order
+    if app.sort() != "manual" {
+        app.handle_new_message(String::from(
+            "Todos are moved in the manual order, press o to switch to it",
+        ));
+        return;
+    }
+
+    let selected_index = match app.undone.state.selected() {
+        Some(selected_index) => selected_index,
+        None => return,
+    };
+
+    let target_index = if up {
+        match selected_index.checked_sub(1) {
+            Some(target_index) => target_index,
+            None => return,
         }
-        reqwest::StatusCode::NOT_FOUND => {
-            return Err(Box::new(std::io::Error::new(
-                std::io::ErrorKind::NotFound,
-                "Resource Not Found",
-            )));
+    } else {
+        selected_index + 1
+    };
+
+    let side = if up { "before" } else { "after" };
+
+    let target = match app.undone.items.get(target_index) {
+        Some(target) => serde_json::json!({ side: target.id }),
+        None => return,
+    };
+
+    let id = app.undone.items[selected_index].id.to_string();
+
+    match backend.move_todo(id.as_str(), target) {
+        Ok(_) => {
+            app.undone.items.swap(selected_index, target_index);
+            app.undone.state.select(Some(target_index));
         }
-        _ => {
-            eprintln!("{:?}", response);
-            return Err(Box::new(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                "Invalid Response for Signup",
-            )));
+        Err(e) => app.handle_error(e.to_string()),
+    }
+}
+
+/// Todos a bulk action applies to, the marked ones or else the selected one
+fn bulk_targets(app: &App) -> Vec<uuid::Uuid> {
+    if !app.marked.is_empty() {
+        return app
+            .undone
+            .items
+            .iter()
+            .filter(|todo| app.marked.contains(&todo.id))
+            .map(|todo| todo.id)
+            .collect();
+    }
+
+    app.undone
+        .state
+        .selected()
+        .and_then(|index| app.undone.items.get(index))
+        .map(|todo| vec![todo.id])
+        .unwrap_or_default()
+}
+
+/// Apply an operation to every target of a bulk action in a single request,
+/// then reload the list and tell how it went
+///
+/// # Arguments
+/// * `done` what happened to the todos, as in `Completed 3 todos`
+/// * `operation` builds the operation for the todo with the given id
+fn run_bulk_action(
+    backend: &dyn TodoBackend,
+    app: &mut App,
+    done: &str,
+    operation: impl Fn(uuid::Uuid) -> serde_json::Value,
+) {
+    let operations: Vec<serde_json::Value> = bulk_targets(app).into_iter().map(operation).collect();
+
+    if operations.is_empty() {
+        return;
+    }
+
+    match backend.bulk(operations) {
+        Ok(results) => {
+            switch_tab(backend, app);
+
+            let failed: Vec<&BulkResult> = results.iter().filter(|result| !result.ok).collect();
+
+            let message = match failed.first() {
+                None => format!("{} {} todos", done, results.len()),
+                Some(first) => format!(
+                    "{} {} todos, {} failed: {}",
+                    done,
+                    results.len() - failed.len(),
+                    failed.len(),
+                    first.error.clone().unwrap_or_default()
+                ),
+            };
+
+            app.handle_new_message(message);
         }
+        Err(e) => app.handle_error(e.to_string()),
     }
 }
 
-/// Request To Complete a todo
-fn request_complete_todo(todo_id: &str) -> Result<(), BaseError> {
-    let request = _make_request("PUT", format!("todo/{}/complete", todo_id).as_str(), None)?;
+/// How long typing has to pause before the search is sent
+const SEARCH_DELAY: Duration = Duration::from_millis(300);
 
-    let response = reqwest::blocking::Client::new().execute(request)?;
+/// Search again with what has been typed so far
+fn search_as_you_type(backend: &dyn TodoBackend, app: &mut App) {
+    // Queries without a single word are refused by the api
+    if !app.input_text.chars().any(char::is_alphanumeric) {
+        app.search = StatefulList::with_items(vec![]);
+        return;
+    }
 
-    let _ = handle_response(response);
+    match backend.search(app.input_text.as_str()) {
+        Ok(hits) => {
+            app.search = StatefulList::with_items(hits);
 
-    Ok(())
+            if !app.search.items.is_empty() {
+                app.search.state.select(Some(0));
+            }
+        }
+        Err(e) => {
+            app.input_mode = InputMode::None;
+            app.pop_navigation_stack();
+            app.handle_error(e.to_string());
+        }
+    }
 }
 
-fn _make_request(
-    method: &str,
-    short_url: &str,
-    data: Option<serde_json::Value>,
-) -> anyhow::Result<reqwest::blocking::Request, TodoError> {
-    let url = crate::utils::make_api_url(short_url);
-
-    let method = match method.to_uppercase().as_str() {
-        "POST" => Some(Method::POST),
-        "GET" => Some(Method::GET),
-        "DELETE" => Some(Method::DELETE),
-        "PUT" => Some(Method::PUT),
-        _ => None,
+/// Select the todo of the picked search result in the todo list
+fn select_search_hit(backend: &dyn TodoBackend, app: &mut App) {
+    let hit = match app
+        .search
+        .state
+        .selected()
+        .and_then(|index| app.search.items.get(index))
+    {
+        Some(hit) => hit.todo.clone(),
+        None => return,
     };
 
-    let mut request = reqwest::blocking::Client::new().request(method.unwrap(), url);
-
-    let token = get_saved_token()?;
+    if hit.archived_at.is_some() || hit.completed {
+        app.handle_new_message(format!("{} is already done", hit.title));
+        return;
+    }
 
-    if data.is_some() {
-        request = request.json::<serde_json::Value>(&data.unwrap());
+    // The todo may be in another project, the `All` tab has every open todo
+    if !app.undone.items.iter().any(|todo| todo.id == hit.id) && app.selected_tab != 0 {
+        app.selected_tab = 0;
+        switch_tab(backend, app);
     }
 
-    request
-        .header(AUTHORIZATION, format!("Bearer {}", token))
-        .header(reqwest::header::CONTENT_TYPE, "application/json")
-        .build()
-        .map_err(|e| {
-            return e.into();
-        })
+    match app.undone.items.iter().position(|todo| todo.id == hit.id) {
+        Some(index) => app.undone.state.select(Some(index)),
+        None => app.handle_new_message(format!("{} is hidden by the filters", hit.title)),
+    }
 }
 
-/// Request To Delete a Todo
-fn request_delete_todo(todo_id: &str) -> Result<(), BaseError> {
-    let request = _make_request("DELETE", format!("todo/{}", todo_id).as_str(), None)?;
+/// Let the user write the notes of a todo in their editor,
+/// the todo list is hidden in the meantime
+fn prompt_notes<B: Backend>(
+    terminal: &mut Terminal<B>,
+    current: &str,
+) -> Result<String, BaseError> {
+    disable_raw_mode()?;
+    execute!(std::io::stdout(), LeaveAlternateScreen, DisableMouseCapture)?;
 
-    let response = reqwest::blocking::Client::new().execute(request)?;
+    let notes = inquire::Editor::new("Notes")
+        .with_predefined_text(current)
+        .with_file_extension(".md")
+        .with_help_message("Markdown")
+        .prompt();
 
-    let _ = handle_response(response)?;
+    enable_raw_mode()?;
+    execute!(std::io::stdout(), EnterAlternateScreen, EnableMouseCapture)?;
+    terminal.clear()?;
 
-    Ok(())
+    Ok(notes?)
 }
 
-/// Make a request to add a new todo
-fn request_add_todo(title: String) -> Result<Todo, BaseError> {
-    let body = serde_json::json!({ "title": title });
+/// Reload the todos for the selected tab,
+/// the backend falls back to the saved copy when the server is unreachable
+fn switch_tab(backend: &dyn TodoBackend, app: &mut App) {
+    let mut query = app.filters.clone();
 
-    let request = _make_request("POST", "todo", Some(body))?;
+    if let Some(project) = app.current_project() {
+        query.push(("project".into(), project.id.to_string()));
+    }
 
-    let response = reqwest::blocking::Client::new().execute(request)?;
+    match backend.list_todos(&query) {
+        Ok(todos) => {
+            app.set_todos(todos);
+            app.stale_since = match backend.is_offline() {
+                true => backend.synced_at(),
+                false => None,
+            };
+        }
+        Err(e) => app.handle_error(e.to_string()),
+    }
+}
 
-    let data = handle_response(response)?;
+/// Add a new todo from what was typed, #words become its tags
+fn add_todo(
+    backend: &dyn TodoBackend,
+    input: String,
+    project: Option<&Project>,
+) -> Result<TodoItem, BaseError> {
+    let (title, tags) = split_tags(input.as_str());
 
-    let value = serde_json::Value::from_str(data.as_str())?;
+    let mut todo = Todo::from(title, uuid::Uuid::nil());
 
-    let todo: Todo = serde_json::from_value(value)?;
+    todo.project_id = project.map(|p| p.id);
 
-    Ok(todo)
+    backend.create_todo(&TodoItem {
+        todo,
+        tags,
+        progress: Progress::default(),
+    })
 }
 
 fn run_app<B: Backend>(
+    backend: &dyn TodoBackend,
     terminal: &mut Terminal<B>,
     mut app: App,
     tick_rate: Duration,
@@ -180,10 +329,15 @@ fn run_app<B: Backend>(
     loop {
         terminal.draw(|f| ui(f, &mut app))?;
 
-        let timeout = tick_rate
+        let mut timeout = tick_rate
             .checked_sub(last_tick.elapsed())
             .unwrap_or_else(|| Duration::from_secs(0));
 
+        // Wake up in time for a pending search
+        if let Some(due) = app.search_due {
+            timeout = timeout.min(due.saturating_duration_since(Instant::now()));
+        }
+
         if crossterm::event::poll(timeout)? {
             if let Event::Key(key) = event::read()? {
                 match app.input_mode {
@@ -194,18 +348,38 @@ fn run_app<B: Backend>(
                                 ActiveBlock::Error | ActiveBlock::Message => {
                                     app.pop_navigation_stack();
                                 }
-                                ActiveBlock::NewTodo => {
+                                ActiveBlock::NewTodo
+                                | ActiveBlock::EditTodo
+                                | ActiveBlock::NewSubtask => {
                                     app.input_text = String::new();
                                     app.pop_navigation_stack();
                                     app.input_mode = InputMode::None;
                                 }
+                                ActiveBlock::Home => app.marked.clear(),
                                 _ => {}
                             }
                         }
                         KeyCode::Char('q') => return Ok(()),
+                        KeyCode::Up if key.modifiers.contains(KeyModifiers::SHIFT) => {
+                            move_selected_todo(backend, &mut app, true)
+                        }
+                        KeyCode::Down if key.modifiers.contains(KeyModifiers::SHIFT) => {
+                            move_selected_todo(backend, &mut app, false)
+                        }
+                        // For terminals that don't report Shift with the arrow keys
+                        KeyCode::Char('K') => move_selected_todo(backend, &mut app, true),
+                        KeyCode::Char('J') => move_selected_todo(backend, &mut app, false),
                         KeyCode::Left => app.undone.unselect(),
                         KeyCode::Down => app.undone.next(),
                         KeyCode::Up => app.undone.previous(),
+                        KeyCode::Tab => {
+                            app.next_tab();
+                            switch_tab(backend, &mut app);
+                        }
+                        KeyCode::BackTab => {
+                            app.previous_tab();
+                            switch_tab(backend, &mut app);
+                        }
                         KeyCode::Char('a') => match app.get_current_route().active_block {
                             ActiveBlock::Home => {
                                 app.push_navigation_stack(RouteId::NewTodo, ActiveBlock::NewTodo);
@@ -214,12 +388,39 @@ fn run_app<B: Backend>(
                             }
                             _ => {}
                         },
+                        KeyCode::Char('/') => {
+                            app.search = StatefulList::with_items(vec![]);
+                            app.input_text = String::new();
+                            app.push_navigation_stack(RouteId::Search, ActiveBlock::Search);
+                            app.input_mode = InputMode::Editing;
+                        }
+                        KeyCode::Char('o') => {
+                            app.toggle_sort();
+                            switch_tab(backend, &mut app);
+                        }
+                        KeyCode::Char('A') => match backend.archive_done() {
+                            Ok(count) => {
+                                app.done.items.clear();
+                                app.handle_new_message(format!("Archived {} todos", count));
+                            }
+                            Err(e) => app.handle_error(e.to_string()),
+                        },
+                        KeyCode::Char('d') if !app.marked.is_empty() => run_bulk_action(
+                            backend,
+                            &mut app,
+                            "Completed",
+                            |id| serde_json::json!({ "op": "complete", "id": id }),
+                        ),
                         KeyCode::Char('d') => {
                             let selected_index = app.undone.state.selected().unwrap();
 
                             let selected_item = &app.undone.items[selected_index];
 
-                            match request_complete_todo(selected_item.id.to_string().as_str()) {
+                            let recurring = selected_item.recurrence.is_some();
+
+                            match backend.complete_todo(selected_item) {
+                                // Reload to show the next occurrence of a recurring todo
+                                Ok(_) if recurring => switch_tab(backend, &mut app),
                                 Ok(_) => {
                                     if let Some(item) = app.undone.items.get_mut(selected_index) {
                                         app.done.items.push(item.to_owned());
@@ -231,6 +432,127 @@ fn run_app<B: Backend>(
                                 Err(e) => app.handle_error(e.to_string()),
                             }
                         }
+                        KeyCode::Char('e') => {
+                            if let Some(selected_index) = app.undone.state.selected() {
+                                if app.get_current_route().active_block == ActiveBlock::Home {
+                                    let todo = &app.undone.items[selected_index];
+
+                                    // Tags are edited along with the title as #words
+                                    app.input_text = std::iter::once(todo.title.clone())
+                                        .chain(todo.tags.iter().map(|tag| format!("#{}", tag)))
+                                        .collect::<Vec<String>>()
+                                        .join(" ");
+                                    app.push_navigation_stack(
+                                        RouteId::EditTodo,
+                                        ActiveBlock::EditTodo,
+                                    );
+                                    app.input_mode = InputMode::Editing;
+                                }
+                            }
+                        }
+                        KeyCode::Char(' ') => {
+                            // Mark or unmark the selected todo for a bulk action
+                            if let Some(selected_index) = app.undone.state.selected() {
+                                let id = app.undone.items[selected_index].id;
+
+                                if !app.marked.remove(&id) {
+                                    app.marked.insert(id);
+                                }
+
+                                app.undone.next();
+                            }
+                        }
+                        KeyCode::Enter => {
+                            // Expand or collapse the subtasks of the selected todo
+                            if let Some(selected_index) = app.undone.state.selected() {
+                                let id = app.undone.items[selected_index].id;
+
+                                if app.expanded.remove(&id).is_none() {
+                                    match backend.subtasks(id.to_string().as_str()) {
+                                        Ok(subtasks) => {
+                                            app.expanded.insert(id, subtasks);
+                                        }
+                                        Err(e) => app.handle_error(e.to_string()),
+                                    }
+                                }
+                            }
+                        }
+                        KeyCode::Char('s')
+                            if app.undone.state.selected().is_some()
+                                && app.get_current_route().active_block == ActiveBlock::Home =>
+                        {
+                            app.push_navigation_stack(RouteId::NewSubtask, ActiveBlock::NewSubtask);
+                            app.input_mode = InputMode::Editing;
+                            app.input_text = String::new();
+                        }
+                        KeyCode::Char('n') => {
+                            if let Some(selected_index) = app.undone.state.selected() {
+                                let todo = &app.undone.items[selected_index];
+
+                                let id = todo.id.to_string();
+                                let current = todo.notes.clone().unwrap_or_default();
+
+                                let updated =
+                                    prompt_notes(terminal, current.as_str()).and_then(|notes| {
+                                        backend.update_todo(
+                                            id.as_str(),
+                                            serde_json::json!({ "notes": notes }),
+                                        )
+                                    });
+
+                                match updated {
+                                    Ok(todo) => app.undone.items[selected_index] = todo,
+                                    Err(e) => app.handle_error(e.to_string()),
+                                }
+                            }
+                        }
+                        KeyCode::Char('u') => {
+                            if let Some((index, deleted)) = app.last_deleted.take() {
+                                match backend.restore_todo(deleted.id.to_string().as_str()) {
+                                    Ok(todo) => {
+                                        let index = index.min(app.undone.items.len());
+                                        app.undone.items.insert(index, todo);
+                                        app.handle_new_message(String::from("Todo restored"));
+                                    }
+                                    Err(e) => app.handle_error(e.to_string()),
+                                }
+                            }
+                        }
+                        KeyCode::Char('p') => {
+                            if let Some(selected_index) = app.undone.state.selected() {
+                                let selected_item = &app.undone.items[selected_index];
+
+                                let priority = selected_item.priority.next();
+
+                                match backend
+                                    .set_priority(selected_item.id.to_string().as_str(), priority)
+                                {
+                                    Ok(_) => app.undone.items[selected_index].priority = priority,
+                                    Err(e) => app.handle_error(e.to_string()),
+                                }
+                            }
+                        }
+                        KeyCode::Char('x') if !app.marked.is_empty() => run_bulk_action(
+                            backend,
+                            &mut app,
+                            "Moved to the trash",
+                            |id| serde_json::json!({ "op": "delete", "id": id }),
+                        ),
+                        KeyCode::Char('t') | KeyCode::Char('m')
+                            if !bulk_targets(&app).is_empty()
+                                && app.get_current_route().active_block == ActiveBlock::Home =>
+                        {
+                            if key.code == KeyCode::Char('t') {
+                                app.push_navigation_stack(
+                                    RouteId::BulkRetag,
+                                    ActiveBlock::BulkRetag,
+                                );
+                            } else {
+                                app.push_navigation_stack(RouteId::BulkMove, ActiveBlock::BulkMove);
+                            }
+                            app.input_mode = InputMode::Editing;
+                            app.input_text = String::new();
+                        }
                         KeyCode::Char('x') => {
                             let selected_index = app.undone.state.selected().unwrap();
 
@@ -238,10 +560,13 @@ fn run_app<B: Backend>(
 
                             let id = selected_item.id.to_string();
 
-                            match request_delete_todo(id.as_str()) {
+                            match backend.delete_todo(selected_item) {
                                 Ok(_) => {
-                                    app.undone.items.remove(selected_index);
-                                    app.handle_new_message(String::from("Todo Item Deleted"));
+                                    let deleted = app.undone.items.remove(selected_index);
+                                    app.last_deleted = Some((selected_index, deleted));
+                                    app.handle_new_message(String::from(
+                                        "Todo moved to the trash, press u to restore it",
+                                    ));
                                 }
                                 Err(e) => {
                                     app.handle_error(format!(
@@ -257,39 +582,151 @@ fn run_app<B: Backend>(
                     InputMode::Editing => match key.code {
                         KeyCode::Char(c) => {
                             app.input_text.push(c);
+
+                            if app.get_current_route().active_block == ActiveBlock::Search {
+                                app.search_due = Some(Instant::now() + SEARCH_DELAY);
+                            }
                         }
                         KeyCode::Backspace => {
                             app.input_text.pop();
+
+                            if app.get_current_route().active_block == ActiveBlock::Search {
+                                app.search_due = Some(Instant::now() + SEARCH_DELAY);
+                            }
+                        }
+                        KeyCode::Down
+                            if app.get_current_route().active_block == ActiveBlock::Search =>
+                        {
+                            app.search.next()
+                        }
+                        KeyCode::Up
+                            if app.get_current_route().active_block == ActiveBlock::Search =>
+                        {
+                            app.search.previous()
                         }
                         KeyCode::Esc => {
                             app.pop_navigation_stack();
                             app.input_mode = InputMode::None;
                             app.input_text = String::new();
+                            app.search_due = None;
                         }
                         KeyCode::Enter => {
-                            // Create new Todo
-                            println!("TODO ITEM {}", app.input_text);
+                            // Pick from the results of everything typed
+                            if app.search_due.take().is_some() {
+                                search_as_you_type(backend, &mut app);
+
+                                // The search failed and its error is shown
+                                if matches!(app.input_mode, InputMode::None) {
+                                    continue;
+                                }
+                            }
+
+                            let active_block = app.get_current_route().active_block;
+
                             app.input_mode = InputMode::None;
                             let todo_title = app.input_text;
 
                             app.input_text = String::new();
                             app.pop_navigation_stack();
 
-                            match request_add_todo(todo_title) {
-                                Ok(todo) => {
-                                    let new_todo = Todo {
-                                        id: todo.id,
-                                        title: todo.title,
-                                        created_at: todo.created_at,
-                                        completed: todo.completed,
-                                        updated_at: todo.updated_at,
-                                        user_id: todo.user_id,
+                            match active_block {
+                                ActiveBlock::Search => select_search_hit(backend, &mut app),
+                                ActiveBlock::BulkRetag => {
+                                    // `#tag` or `tag` adds it, `-tag` or `-#tag` removes it
+                                    let (remove, add): (Vec<&str>, Vec<&str>) = todo_title
+                                        .split_whitespace()
+                                        .partition(|word| word.starts_with('-'));
+
+                                    let remove: Vec<&str> = remove
+                                        .iter()
+                                        .map(|word| word.trim_start_matches('-'))
+                                        .collect();
+
+                                    run_bulk_action(backend, &mut app, "Retagged", |id| {
+                                        serde_json::json!({
+                                            "op": "retag",
+                                            "id": id,
+                                            "add": add,
+                                            "remove": remove,
+                                        })
+                                    });
+                                }
+                                ActiveBlock::BulkMove => {
+                                    let name = todo_title.trim();
+
+                                    // No name takes the todos out of their project
+                                    let project = match name.is_empty() {
+                                        true => Ok(None),
+                                        false => app
+                                            .projects
+                                            .iter()
+                                            .find(|project| project.name.eq_ignore_ascii_case(name))
+                                            .map(|project| Some(project.id))
+                                            .ok_or(format!("No project named {}", name)),
                                     };
 
-                                    app.undone.items.insert(0, new_todo.clone());
+                                    match project {
+                                        Ok(project) => {
+                                            run_bulk_action(backend, &mut app, "Moved", |id| {
+                                                serde_json::json!({
+                                                    "op": "move",
+                                                    "id": id,
+                                                    "project_id": project,
+                                                })
+                                            })
+                                        }
+                                        Err(e) => app.handle_error(e),
+                                    }
                                 }
-                                Err(e) => {
-                                    app.handle_error(e.to_string());
+                                ActiveBlock::EditTodo => {
+                                    // Rename and retag the selected Todo
+                                    if let Some(selected_index) = app.undone.state.selected() {
+                                        let id = app.undone.items[selected_index].id.to_string();
+
+                                        let (title, tags) = split_tags(todo_title.as_str());
+
+                                        match backend.update_todo(
+                                            id.as_str(),
+                                            serde_json::json!({ "title": title, "tags": tags }),
+                                        ) {
+                                            Ok(todo) => app.undone.items[selected_index] = todo,
+                                            Err(e) => app.handle_error(e.to_string()),
+                                        }
+                                    }
+                                }
+                                ActiveBlock::NewSubtask => {
+                                    // Add a subtask to the selected Todo and expand it
+                                    if let Some(selected_index) = app.undone.state.selected() {
+                                        let id = app.undone.items[selected_index].id;
+
+                                        match backend
+                                            .add_subtask(id.to_string().as_str(), todo_title)
+                                        {
+                                            Ok(_) => {
+                                                app.undone.items[selected_index].progress.total +=
+                                                    1;
+
+                                                match backend.subtasks(id.to_string().as_str()) {
+                                                    Ok(subtasks) => {
+                                                        app.expanded.insert(id, subtasks);
+                                                    }
+                                                    Err(e) => app.handle_error(e.to_string()),
+                                                }
+                                            }
+                                            Err(e) => app.handle_error(e.to_string()),
+                                        }
+                                    }
+                                }
+                                _ => {
+                                    // Create new Todo
+                                    match add_todo(backend, todo_title, app.current_project()) {
+                                        Ok(todo) => {
+                                            app.undone.items.insert(0, todo);
+                                        }
+                                        Err(e) => {
+                                            app.handle_error(e.to_string());
+                                        }
+                                    }
                                 }
                             }
                         }
@@ -299,6 +736,11 @@ fn run_app<B: Backend>(
             }
         }
 
+        if app.search_due.is_some_and(|due| due <= Instant::now()) {
+            app.search_due = None;
+            search_as_you_type(backend, &mut app);
+        }
+
         if last_tick.elapsed() >= tick_rate {
             app.on_tick();
             last_tick = Instant::now();
@@ -340,6 +782,59 @@ where
 /// Draws a new window where
 /// new todo item form is presented
 fn draw_new_todo_content<B>(f: &mut Frame<B>, app: &App)
+where
+    B: Backend,
+{
+    draw_todo_title_form(f, app, "Enter New Todo title", "add the todo item");
+}
+
+/// Draws a window where a subtask
+/// is added to the selected todo item
+fn draw_new_subtask_content<B>(f: &mut Frame<B>, app: &App)
+where
+    B: Backend,
+{
+    draw_todo_title_form(f, app, "Enter New Subtask title", "add the subtask");
+}
+
+/// Draws a window where tags are added to or
+/// removed from the marked todo items
+fn draw_bulk_retag_content<B>(f: &mut Frame<B>, app: &App)
+where
+    B: Backend,
+{
+    draw_todo_title_form(
+        f,
+        app,
+        "Enter tags to add, or -tag to remove them",
+        "retag the todo items",
+    );
+}
+
+/// Draws a window where the project
+/// of the marked todo items is changed
+fn draw_bulk_move_content<B>(f: &mut Frame<B>, app: &App)
+where
+    B: Backend,
+{
+    draw_todo_title_form(
+        f,
+        app,
+        "Enter a project name, or nothing to take them out of their project",
+        "move the todo items",
+    );
+}
+
+/// Draws a window where the title
+/// of the selected todo item can be changed
+fn draw_edit_todo_content<B>(f: &mut Frame<B>, app: &App)
+where
+    B: Backend,
+{
+    draw_todo_title_form(f, app, "Edit Todo title", "save the todo item");
+}
+
+fn draw_todo_title_form<B>(f: &mut Frame<B>, app: &App, title: &str, action: &str)
 where
     B: Backend,
 {
@@ -350,12 +845,12 @@ where
         .split(f.size());
 
     let prompt_message = vec![
-        Span::raw("Enter New Todo title"),
+        Span::raw(format!("{} ", title)),
         Span::raw("Press "),
         Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
         Span::raw(" to stop editing, "),
         Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
-        Span::raw(" to add the todo item"),
+        Span::raw(format!(" to {}", action)),
     ];
 
     let help_text = Text::from(Spans::from(prompt_message));
@@ -370,6 +865,85 @@ where
     f.render_widget(input, chunks[1]);
 }
 
+/// Draws the search input with
+/// the matching todos below it
+fn draw_search_content<B>(f: &mut Frame<B>, app: &mut App)
+where
+    B: Backend,
+{
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Min(0)].as_ref())
+        .margin(2)
+        .split(f.size());
+
+    let input = Paragraph::new(app.input_text.as_ref())
+        .style(Style::default().fg(Color::Yellow))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Search (Enter to go to the todo, Esc to close)"),
+        );
+
+    f.render_widget(input, chunks[0]);
+
+    let matched = Style::default()
+        .fg(Color::Yellow)
+        .add_modifier(Modifier::BOLD);
+
+    let highlighted = |text: &str, style: Style| -> Spans<'static> {
+        Spans::from(
+            split_highlights(text)
+                .into_iter()
+                .map(|(part, is_match)| match is_match {
+                    true => Span::styled(part.to_string(), matched),
+                    false => Span::styled(part.to_string(), style),
+                })
+                .collect::<Vec<Span>>(),
+        )
+    };
+
+    let items: Vec<ListItem> = app
+        .search
+        .items
+        .iter()
+        .map(|hit| {
+            let mut title = highlighted(&hit.highlighted_title, Style::default());
+
+            if hit.todo.completed {
+                title.0.insert(0, Span::raw("[x] "));
+            }
+
+            let mut lines = vec![title];
+
+            if let Some(snippet) = &hit.snippet {
+                let mut snippet = highlighted(
+                    &snippet.replace('\n', " "),
+                    Style::default().fg(Color::DarkGray),
+                );
+
+                snippet.0.insert(0, Span::raw("    "));
+                lines.push(snippet);
+            }
+
+            ListItem::new(lines)
+        })
+        .collect();
+
+    let results_title = match app.search.items.len() {
+        0 if app.input_text.is_empty() => String::from("Type to search titles and notes"),
+        0 => String::from("No matches"),
+        count => format!("{} matches", count),
+    };
+
+    let results = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(results_title))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .highlight_symbol(">> ");
+
+    f.render_stateful_widget(results, chunks[1], &mut app.search.state);
+}
+
 // Draws Error if occured
 fn draw_error_content<B>(f: &mut Frame<B>, app: &App)
 where
@@ -396,12 +970,75 @@ where
     f.render_widget(error_paragraph, chunks[0]);
 }
 
+/// Colored marker shown in front of a todo title
+fn priority_marker<'a>(priority: Priority) -> Span<'a> {
+    let color = match priority {
+        Priority::None => return Span::raw("  "),
+        Priority::Low => Color::Blue,
+        Priority::Medium => Color::Yellow,
+        Priority::High => Color::LightRed,
+        Priority::Urgent => Color::Red,
+    };
+
+    Span::styled(
+        "● ",
+        Style::default().fg(color).add_modifier(Modifier::BOLD),
+    )
+}
+
 fn draw_home_content<B: Backend>(f: &mut Frame<B>, app: &mut App) {
+    // Project tabs at the top, the todo lists below them
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Min(0)].as_ref())
+        .split(f.size());
+
+    let tab_titles: Vec<Spans> = std::iter::once("All")
+        .chain(app.projects.iter().map(|project| project.name.as_str()))
+        .map(Spans::from)
+        .collect();
+
+    let tabs = Tabs::new(tab_titles)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Projects (Tab to switch)"),
+        )
+        .select(app.selected_tab)
+        .highlight_style(
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        );
+
+    f.render_widget(tabs, rows[0]);
+
     // Create two chunks with equal horizontal screen space
     let chunks = Layout::default()
         .direction(Direction::Horizontal)
         .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
-        .split(f.size());
+        .split(rows[1]);
+
+    // The selected todo gets a detail pane below the list
+    let selected = app
+        .undone
+        .state
+        .selected()
+        .and_then(|index| app.undone.items.get(index));
+
+    let todo_area = match selected {
+        Some(todo) => {
+            let todo_chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Percentage(60), Constraint::Percentage(40)].as_ref())
+                .split(chunks[0]);
+
+            f.render_widget(todo_details(todo), todo_chunks[1]);
+
+            todo_chunks[0]
+        }
+        None => chunks[0],
+    };
 
     // Iterate through all elements in the `items` app and append some debug text to it.
     let items: Vec<ListItem> = app
@@ -409,14 +1046,89 @@ fn draw_home_content<B: Backend>(f: &mut Frame<B>, app: &mut App) {
         .items
         .iter()
         .map(|todo| {
-            let lines = vec![Spans::from(todo.title.as_str())];
-            ListItem::new(lines).style(Style::default().fg(Color::Black).bg(Color::White))
+            let mut spans = vec![];
+
+            // Marked todos get a check in front, the others keep their alignment
+            if !app.marked.is_empty() {
+                spans.push(match app.marked.contains(&todo.id) {
+                    true => Span::styled("✓ ", Style::default().add_modifier(Modifier::BOLD)),
+                    false => Span::raw("  "),
+                });
+            }
+
+            spans.push(priority_marker(todo.priority));
+            spans.push(Span::raw(todo.title.as_str()));
+
+            for tag in &todo.tags {
+                spans.push(Span::styled(
+                    format!(" #{}", tag),
+                    Style::default().fg(Color::DarkGray),
+                ));
+            }
+
+            if let Some(recurrence) = &todo.recurrence {
+                spans.push(Span::styled(
+                    format!("  ↻ {}", recurrence),
+                    Style::default().fg(Color::DarkGray),
+                ));
+            }
+
+            if todo.progress.total > 0 {
+                spans.push(Span::styled(
+                    format!("  [{}]", todo.progress),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ));
+            }
+
+            // Show when the todo is due, overdue ones are highlighted
+            if let Some(due_at) = todo.due_at {
+                spans.push(Span::raw("  "));
+                spans.push(Span::styled(
+                    format!("due {}", due_at.format("%Y-%m-%d %H:%M")),
+                    Style::default().add_modifier(Modifier::ITALIC),
+                ));
+            }
+
+            let style = if todo.is_overdue() {
+                Style::default().fg(Color::White).bg(Color::Red)
+            } else {
+                Style::default().fg(Color::Black).bg(Color::White)
+            };
+
+            let mut lines = vec![Spans::from(spans)];
+
+            // Expanded todos list their subtasks below the title
+            if let Some(subtasks) = app.expanded.get(&todo.id) {
+                if subtasks.is_empty() {
+                    lines.push(Spans::from("      no subtasks, press s to add one"));
+                }
+
+                for subtask in subtasks {
+                    let check = if subtask.completed { "[x]" } else { "[ ]" };
+
+                    lines.push(Spans::from(format!("      {} {}", check, subtask.title)));
+                }
+            }
+
+            ListItem::new(lines).style(style)
         })
         .collect();
 
     // Create a List from all list items and highlight the currently selected one
+    let todo_title = match (app.marked.len(), app.stale_since) {
+        (0, Some(synced_at)) => format!(
+            "Todo (offline, as of {})",
+            synced_at.format("%Y-%m-%d %H:%M")
+        ),
+        (0, None) => format!(
+            "Todo by {} (/ to search, o to sort, Space to mark)",
+            app.sort()
+        ),
+        (count, _) => format!("Todo ({} marked: d done, x trash, t tag, m move)", count),
+    };
+
     let items = List::new(items)
-        .block(Block::default().borders(Borders::ALL).title("Todo"))
+        .block(Block::default().borders(Borders::ALL).title(todo_title))
         .highlight_style(
             Style::default()
                 .bg(Color::LightYellow)
@@ -425,16 +1137,36 @@ fn draw_home_content<B: Backend>(f: &mut Frame<B>, app: &mut App) {
         .highlight_symbol(">> ");
 
     // We can now render the item list
-    f.render_stateful_widget(items, chunks[0], &mut app.undone.state);
+    f.render_stateful_widget(items, todo_area, &mut app.undone.state);
+
+    // Completed occurrences of a recurring todo are shown together as its history
+    let mut done_groups: Vec<Vec<&TodoItem>> = vec![];
+
+    for todo in &app.done.items {
+        let series_group = todo.series_id.and_then(|series| {
+            done_groups
+                .iter_mut()
+                .find(|group| group[0].series_id == Some(series))
+        });
+
+        match series_group {
+            Some(group) => group.push(todo),
+            None => done_groups.push(vec![todo]),
+        }
+    }
 
     // Let's do the same for the events.
     // The event list doesn't have any state and only displays the current state of the list.
-    let events: Vec<ListItem> = app
-        .done
-        .items
-        .iter()
+    let events: Vec<ListItem> = done_groups
+        .into_iter()
         .rev()
-        .map(|todo| {
+        .map(|mut group| {
+            if group[0].series_id.is_some() {
+                return series_history_item(&mut group, chunks[1].width);
+            }
+
+            let todo = group[0];
+
             // Colorcode the level depending on its type
             // Add a example datetime and apply proper spacing between them
             let s = Style::default();
@@ -467,12 +1199,71 @@ fn draw_home_content<B: Backend>(f: &mut Frame<B>, app: &mut App) {
         .collect();
 
     let events_list = List::new(events)
-        .block(Block::default().borders(Borders::ALL).title("Done"))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Done (A to archive)"),
+        )
         .start_corner(Corner::BottomLeft);
 
     f.render_widget(events_list, chunks[1]);
 }
 
+/// Notes of a todo rendered from Markdown
+fn todo_details(todo: &TodoItem) -> Paragraph<'static> {
+    let text = match &todo.notes {
+        Some(notes) => render_markdown(notes),
+        None => vec![Spans::from(Span::styled(
+            "No notes, press n to write some",
+            Style::default().fg(Color::DarkGray),
+        ))],
+    };
+
+    Paragraph::new(text).wrap(Wrap { trim: false }).block(
+        Block::default().borders(Borders::ALL).title(Span::styled(
+            todo.title.clone(),
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+    )
+}
+
+/// Lists when each todo of a recurring series was completed, latest first
+fn series_history_item<'a>(group: &mut [&'a TodoItem], width: u16) -> ListItem<'a> {
+    group.sort_by_key(|todo| std::cmp::Reverse(todo.updated_at));
+
+    let latest = group[0];
+
+    let mut title = vec![Span::styled(
+        latest.title.as_str(),
+        Style::default().add_modifier(Modifier::BOLD),
+    )];
+
+    if let Some(recurrence) = &latest.recurrence {
+        title.push(Span::raw(format!("  ↻ {}", recurrence)));
+    }
+
+    let mut lines = vec![
+        Spans::from("-".repeat(width as usize)),
+        Spans::from(title),
+        Spans::from(""),
+    ];
+
+    for todo in group.iter() {
+        let due = todo
+            .due_at
+            .map(|due_at| format!("  (due {})", due_at.format("%Y-%m-%d %H:%M")))
+            .unwrap_or_default();
+
+        lines.push(Spans::from(format!(
+            "  ✓ {}{}",
+            todo.updated_at.format("%Y-%m-%d %H:%M"),
+            due
+        )));
+    }
+
+    ListItem::new(lines)
+}
+
 fn ui<B: Backend>(f: &mut Frame<B>, app: &mut App) {
     let current_route = app.get_current_route();
 
@@ -481,5 +1272,10 @@ fn ui<B: Backend>(f: &mut Frame<B>, app: &mut App) {
         ActiveBlock::Home => draw_home_content(f, app),
         ActiveBlock::Error => draw_error_content(f, app),
         ActiveBlock::NewTodo => draw_new_todo_content(f, app),
+        ActiveBlock::EditTodo => draw_edit_todo_content(f, app),
+        ActiveBlock::NewSubtask => draw_new_subtask_content(f, app),
+        ActiveBlock::Search => draw_search_content(f, app),
+        ActiveBlock::BulkRetag => draw_bulk_retag_content(f, app),
+        ActiveBlock::BulkMove => draw_bulk_move_content(f, app),
     }
 }
@@ -1,11 +1,18 @@
+#[cfg(any(windows, target_os = "macos"))]
+use std::process::{Command, Stdio};
 use std::{
     error,
     io::Write,
-    process::{Command, Stdio},
-    str::FromStr,
+    path::{Path, PathBuf},
 };
 
-use crate::config::API_URL;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::API_URL,
+    models::todo_model::{HIGHLIGHT_END, HIGHLIGHT_START},
+};
 
 /// Checks if the `Todo` server is already running for windows
 ///
@@ -65,35 +72,93 @@ pub fn is_server_running(port: &str) -> Result<bool, Box<dyn std::error::Error>>
     }
 }
 
-/// Get token saved to credentials
-pub fn get_saved_token() -> Result<String, Box<dyn error::Error>> {
+/// Checks if `Todo` server is running on linux and the other systems,
+/// anything accepting connections on the port is taken for the server
+/// # Arguments
+/// * `port` server port
+#[cfg(not(any(windows, target_os = "macos")))]
+pub fn is_server_running(port: &str) -> Result<bool, Box<dyn std::error::Error>> {
+    let address = format!("localhost:{}", port);
+
+    Ok(std::net::TcpStream::connect(address).is_ok())
+}
+
+/// Tokens of the logged in user, saved at ~/todo/credentials
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Credentials {
+    pub token: String,
+    /// Missing in credentials saved before tokens could be refreshed
+    pub refresh_token: Option<String>,
+    /// When `token` expires
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+fn credentials_path() -> PathBuf {
     let mut path = dirs::home_dir().unwrap();
     path.push("todo/credentials");
 
-    if !path.exists() {}
+    path
+}
 
+/// Get token saved to credentials, a token about to expire is
+/// refreshed first so the user doesn't have to login again
+pub fn get_saved_token() -> Result<String, Box<dyn error::Error>> {
+    saved_token(&credentials_path())
+}
+
+/// Token of the credentials saved at `path`
+fn saved_token(path: &Path) -> Result<String, Box<dyn error::Error>> {
     let contents = std::fs::read_to_string(path)?;
 
-    let json = serde_json::Value::from_str(contents.as_str())?;
+    let credentials: Credentials = serde_json::from_str(contents.as_str())?;
+
+    let expiring = credentials
+        .expires_at
+        .is_some_and(|expires_at| expires_at - Duration::seconds(30) < Utc::now());
 
-    let token = json.get("token").unwrap().as_str().unwrap();
+    match (&credentials.refresh_token, expiring) {
+        (Some(refresh_token), true) => match refresh_credentials(refresh_token) {
+            Ok(refreshed) => {
+                save_credentials_to(path, &refreshed)?;
 
-    Ok(String::from(token))
+                Ok(refreshed.token)
+            }
+            // Without the server the old token is as good as any, the
+            // api tells if it has to login again
+            Err(_) => Ok(credentials.token),
+        },
+        _ => Ok(credentials.token),
+    }
 }
 
-/// Saves `Todo` login token at ~/todo/.credentials
-pub fn save_token(token: &str) -> Result<(), Box<dyn error::Error>> {
-    use serde_json::json;
+/// Exchanges the refresh token for new credentials
+fn refresh_credentials(refresh_token: &str) -> Result<Credentials, Box<dyn error::Error>> {
+    let response = reqwest::blocking::Client::new()
+        .post(make_api_url("auth/refresh"))
+        .json(&serde_json::json!({ "refresh_token": refresh_token }))
+        .send()?;
 
-    let mut path = dirs::home_dir().unwrap();
+    if response.status() != reqwest::StatusCode::OK {
+        return Err("Refresh token rejected".into());
+    }
 
-    path.push("todo");
+    Ok(response.json()?)
+}
 
-    if !path.exists() {
-        std::fs::create_dir(&path)?;
-    }
+/// Saves `Todo` login credentials at ~/todo/credentials
+pub fn save_credentials(credentials: &Credentials) -> Result<(), Box<dyn error::Error>> {
+    save_credentials_to(&credentials_path(), credentials)
+}
 
-    path.push("credentials");
+fn save_credentials_to(
+    path: &Path,
+    credentials: &Credentials,
+) -> Result<(), Box<dyn error::Error>> {
+    if let Some(dir) = path.parent() {
+        if !dir.exists() {
+            std::fs::create_dir(dir)?;
+        }
+    }
 
     let mut file = std::fs::OpenOptions::new()
         .create(true) // Create new file if doesn't exist
@@ -104,9 +169,18 @@ pub fn save_token(token: &str) -> Result<(), Box<dyn error::Error>> {
     // Remove all contents of the file
     file.set_len(0)?;
 
-    let data = json!({ "token": token });
+    file.write_all(serde_json::to_string(credentials)?.as_bytes())?;
 
-    file.write(data.to_string().as_bytes())?;
+    Ok(())
+}
+
+/// Removes the saved credentials, after which the user has to login again
+pub fn remove_credentials() -> Result<(), Box<dyn error::Error>> {
+    let path = credentials_path();
+
+    if path.exists() {
+        std::fs::remove_file(path)?;
+    }
 
     Ok(())
 }
@@ -115,12 +189,89 @@ pub fn make_api_url(resource: &str) -> String {
     format!("http://{}/api/{}", API_URL.as_str(), resource)
 }
 
+/// Parses a due date typed by the user
+///
+/// Accepts `YYYY-MM-DD HH:MM` or just `YYYY-MM-DD`,
+/// in which case the todo is due at the end of that day
+pub fn parse_due_date(input: &str) -> Result<chrono::NaiveDateTime, chrono::ParseError> {
+    let input = input.trim();
+
+    match chrono::NaiveDateTime::parse_from_str(input, "%Y-%m-%d %H:%M") {
+        Ok(due_at) => Ok(due_at),
+        Err(_) => chrono::NaiveDate::parse_from_str(input, "%Y-%m-%d")
+            .map(|date| date.and_hms_opt(23, 59, 59).unwrap()),
+    }
+}
+
+/// Splits the `#tag` tokens out of a todo title
+///
+/// `"Buy milk #home #errands"` becomes `("Buy milk", ["home", "errands"])`,
+/// a `#` or `##` without a name stays in the title
+pub fn split_tags(input: &str) -> (String, Vec<String>) {
+    let (tags, words): (Vec<&str>, Vec<&str>) = input
+        .split_whitespace()
+        .partition(|word| word.starts_with('#') && !word.trim_start_matches('#').is_empty());
+
+    let tags = tags
+        .iter()
+        .map(|tag| tag.trim_start_matches('#').to_string())
+        .collect();
+
+    (words.join(" "), tags)
+}
+
+/// Splits a search highlight into its parts, each one telling if it was
+/// wrapped in `HIGHLIGHT_START` and `HIGHLIGHT_END`, meaning it matched the search
+///
+/// `"Buy \u{2}milk\u{3}"` becomes `[("Buy ", false), ("milk", true)]`
+pub fn split_highlights(text: &str) -> Vec<(&str, bool)> {
+    let mut parts = vec![];
+    let mut rest = text;
+
+    while let Some(start) = rest.find(HIGHLIGHT_START) {
+        let (before, highlighted) = rest.split_at(start);
+        let highlighted = &highlighted[HIGHLIGHT_START.len_utf8()..];
+
+        if !before.is_empty() {
+            parts.push((before, false));
+        }
+
+        match highlighted.find(HIGHLIGHT_END) {
+            Some(end) => {
+                parts.push((&highlighted[..end], true));
+                rest = &highlighted[end + HIGHLIGHT_END.len_utf8()..];
+            }
+            None => {
+                parts.push((highlighted, true));
+                rest = "";
+            }
+        }
+    }
+
+    if !rest.is_empty() {
+        parts.push((rest, false));
+    }
+
+    parts
+}
+
 #[cfg(test)]
 mod utils_test {
-    use super::{get_saved_token, is_server_running, make_api_url, save_token};
-    use dirs::home_dir;
+    use super::{
+        is_server_running, make_api_url, parse_due_date, save_credentials_to, saved_token,
+        split_highlights, split_tags, Credentials,
+    };
     use std::path::PathBuf;
 
+    /// Credentials file in a new directory under `target/`, so that the
+    /// tests don't touch the credentials of whoever runs them
+    fn credentials_file() -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("target")
+            .join(format!("todo-{}", uuid::Uuid::new_v4()))
+            .join("credentials")
+    }
+
     #[test]
     fn test_is_server_running() {
         let res = is_server_running("5900");
@@ -140,36 +291,100 @@ mod utils_test {
         );
     }
 
+    #[test]
+    fn test_parse_due_date() {
+        let due_at = parse_due_date("2022-10-02 09:30").unwrap();
+
+        assert_eq!(due_at.to_string(), "2022-10-02 09:30:00");
+
+        let due_at = parse_due_date(" 2022-10-02 ").unwrap();
+
+        assert_eq!(due_at.to_string(), "2022-10-02 23:59:59");
+
+        assert_eq!(parse_due_date("tomorrow").is_err(), true);
+    }
+
+    #[test]
+    fn test_split_tags() {
+        let (title, tags) = split_tags("Buy milk #home  #errands");
+
+        assert_eq!(title, "Buy milk");
+        assert_eq!(tags, vec!["home", "errands"]);
+
+        let (title, tags) = split_tags("Issue # 42 ##");
+
+        assert_eq!(title, "Issue # 42 ##");
+        assert_eq!(tags.is_empty(), true);
+    }
+
+    #[test]
+    fn test_split_highlights() {
+        assert_eq!(
+            split_highlights("Buy \u{2}milk\u{3} and \u{2}eggs\u{3}"),
+            vec![
+                ("Buy ", false),
+                ("milk", true),
+                (" and ", false),
+                ("eggs", true)
+            ]
+        );
+
+        assert_eq!(split_highlights("No match"), vec![("No match", false)]);
+
+        assert_eq!(
+            split_highlights("Fix <b>bold</b> \u{2}tags\u{3}"),
+            vec![("Fix <b>bold</b> ", false), ("tags", true)]
+        );
+    }
+
     #[test]
     fn test_save_token() {
-        let token = "randombytesisthe";
+        let credentials = Credentials {
+            token: "randombytesisthe".to_string(),
+            refresh_token: None,
+            expires_at: None,
+        };
 
-        let res = save_token(token);
+        let cred_path = credentials_file();
 
-        assert_eq!(res.is_ok(), true);
+        let res = save_credentials_to(&cred_path, &credentials);
 
-        let mut cred_path = PathBuf::new();
-        cred_path.push(home_dir().unwrap());
-        cred_path.push("todo");
-        cred_path.push("credentials");
+        assert_eq!(res.is_ok(), true);
 
-        let file_resp = std::fs::read_to_string(cred_path);
+        let file_resp = std::fs::read_to_string(&cred_path);
 
         assert_eq!(file_resp.is_ok(), true); //File exists
 
         let data = file_resp.unwrap();
 
-        assert_eq!(data, "{\"token\":\"randombytesisthe\"}");
+        assert_eq!(
+            data,
+            "{\"token\":\"randombytesisthe\",\"refresh_token\":null,\"expires_at\":null}"
+        );
+
+        std::fs::remove_dir_all(cred_path.parent().unwrap()).unwrap();
     }
 
     #[test]
     fn test_get_token() {
-        let token = get_saved_token();
+        let cred_path = credentials_file();
+
+        let credentials = Credentials {
+            token: "randombytesisthe".to_string(),
+            refresh_token: None,
+            expires_at: None,
+        };
+
+        save_credentials_to(&cred_path, &credentials).unwrap();
+
+        let token = saved_token(&cred_path);
 
         assert_eq!(token.is_ok(), true);
 
         let token = token.unwrap();
 
-        assert_eq!(token.len() > 1, true);
+        assert_eq!(token, "randombytesisthe");
+
+        std::fs::remove_dir_all(cred_path.parent().unwrap()).unwrap();
     }
 }